@@ -0,0 +1,103 @@
+// Backs off the sampling interval while the machine is overloaded, so the monitor gets lighter
+// exactly when resources are scarce. Each overloaded iteration doubles the interval up to a cap,
+// and the base interval comes back as soon as the load and cpu usage fall below the thresholds.
+
+use sysinfo::System;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadThresholds {
+    /// 1 minute load average divided by the number of cpus
+    pub load_per_cpu: f64,
+    /// Global cpu usage in percent
+    pub cpu_percent: f32,
+}
+
+impl LoadThresholds {
+    pub fn exceeded_by(&self, load_per_cpu: f64, cpu_percent: f32) -> bool {
+        load_per_cpu >= self.load_per_cpu || cpu_percent >= self.cpu_percent
+    }
+}
+
+pub struct AdaptiveInterval {
+    /// Cpu usage source, refreshed before each adjustment
+    system: System,
+    thresholds: LoadThresholds,
+    base_seconds: u64,
+    max_seconds: u64,
+    current_seconds: u64,
+}
+
+impl AdaptiveInterval {
+    pub fn new(base_seconds: u64, max_seconds: u64, thresholds: LoadThresholds) -> Self {
+        let mut system = System::new();
+        system.refresh_cpu_usage();
+        Self {
+            system,
+            thresholds,
+            base_seconds,
+            max_seconds: max_seconds.max(base_seconds),
+            current_seconds: base_seconds,
+        }
+    }
+
+    /// Reads the current load and returns the interval to sleep before the next iteration
+    pub fn next_interval(&mut self) -> u64 {
+        self.system.refresh_cpu_usage();
+        let cpus = self.system.cpus().len().max(1);
+        let load_per_cpu = System::load_average().one / cpus as f64;
+        self.adjust(load_per_cpu, self.system.global_cpu_usage())
+    }
+
+    /// Doubles the interval, up to the cap, when the load exceeds the thresholds and goes back to
+    /// the base interval otherwise
+    pub fn adjust(&mut self, load_per_cpu: f64, cpu_percent: f32) -> u64 {
+        let next = if self.thresholds.exceeded_by(load_per_cpu, cpu_percent) {
+            (self.current_seconds.max(1) * 2).min(self.max_seconds)
+        } else {
+            self.base_seconds
+        };
+        if next != self.current_seconds {
+            log::info!(
+                "Sampling interval changed from {}s to {next}s (load per cpu {load_per_cpu:.2}, \
+                 cpu usage {cpu_percent:.1}%)",
+                self.current_seconds
+            );
+            self.current_seconds = next;
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLDS: LoadThresholds = LoadThresholds {
+        load_per_cpu: 2.0,
+        cpu_percent: 90.0,
+    };
+
+    #[test]
+    fn either_threshold_counts_as_overloaded() {
+        assert!(!THRESHOLDS.exceeded_by(1.0, 50.0));
+        assert!(THRESHOLDS.exceeded_by(2.0, 50.0));
+        assert!(THRESHOLDS.exceeded_by(1.0, 95.0));
+    }
+
+    #[test]
+    fn interval_doubles_up_to_the_cap_and_resets() {
+        let mut interval = AdaptiveInterval::new(1, 5, THRESHOLDS);
+        let intervals: Vec<u64> = [3.0, 3.0, 3.0, 3.0, 0.5, 3.0]
+            .into_iter()
+            .map(|load_per_cpu| interval.adjust(load_per_cpu, 10.0))
+            .collect();
+        assert_eq!(intervals, [2, 4, 5, 5, 1, 2]);
+    }
+
+    #[test]
+    fn zero_base_interval_still_backs_off() {
+        let mut interval = AdaptiveInterval::new(0, 4, THRESHOLDS);
+        assert_eq!(interval.adjust(0.0, 100.0), 2);
+        assert_eq!(interval.adjust(0.0, 0.0), 0);
+    }
+}
@@ -0,0 +1,119 @@
+// Usage alerts with hysteresis. An alert fires when the value rises to the high threshold and only
+// clears once it falls to the low threshold, so a value hovering near a single threshold doesn't
+// make the alert flap.
+
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub high: f64,
+    pub low: f64,
+}
+
+impl Thresholds {
+    /// Thresholds from the command line, the low threshold defaults to the high one
+    pub fn new(high: Option<f64>, low: Option<f64>) -> Option<Self> {
+        let high = high?;
+        Some(Self {
+            high,
+            low: low.unwrap_or(high).min(high),
+        })
+    }
+}
+
+/// Parses a per-mount threshold given as `<mount point>=<percent>`, e.g. `/var=95`
+pub fn parse_mount_threshold(value: &str) -> Result<(PathBuf, f64), String> {
+    let Some((mount_point, percent)) = value.rsplit_once('=') else {
+        return Err(format!("expected <mount point>=<percent>, got `{value}`"));
+    };
+    let percent = percent
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid percentage `{percent}`: {e}"))?;
+    Ok((PathBuf::from(mount_point.trim()), percent))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    Fired,
+    Cleared,
+}
+
+impl Transition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transition::Fired => "fired",
+            Transition::Cleared => "cleared",
+        }
+    }
+}
+
+/// Updates the state of an alert with a new value, returning the transition if the alert fired
+/// or cleared
+pub fn update(active: &mut bool, value: f64, thresholds: Thresholds) -> Option<Transition> {
+    if !*active && value >= thresholds.high {
+        *active = true;
+        Some(Transition::Fired)
+    } else if *active && value <= thresholds.low {
+        *active = false;
+        Some(Transition::Cleared)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_threshold_defaults_to_the_high_one() {
+        assert_eq!(Thresholds::new(None, Some(80.0)), None);
+        assert_eq!(
+            Thresholds::new(Some(90.0), None),
+            Some(Thresholds {
+                high: 90.0,
+                low: 90.0
+            })
+        );
+        assert_eq!(Thresholds::new(Some(90.0), Some(95.0)).unwrap().low, 90.0);
+    }
+
+    #[test]
+    fn alert_clears_below_the_low_threshold() {
+        let thresholds = Thresholds::new(Some(90.0), Some(80.0)).unwrap();
+        let mut active = false;
+        let transitions: Vec<_> = [85.0, 90.0, 95.0, 85.0, 91.0, 80.0, 70.0, 90.0]
+            .into_iter()
+            .map(|value| update(&mut active, value, thresholds))
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                None,
+                Some(Transition::Fired),
+                None,
+                None,
+                None,
+                Some(Transition::Cleared),
+                None,
+                Some(Transition::Fired),
+            ]
+        );
+        assert!(active);
+    }
+
+    #[test]
+    fn parses_mount_thresholds() {
+        assert_eq!(
+            parse_mount_threshold("/var = 95.5"),
+            Ok((PathBuf::from("/var"), 95.5))
+        );
+        assert_eq!(
+            parse_mount_threshold("/mnt/a=b=80"),
+            Ok((PathBuf::from("/mnt/a=b"), 80.0))
+        );
+        assert!(parse_mount_threshold("/var").is_err());
+        assert!(parse_mount_threshold("/var=full").is_err());
+    }
+}
@@ -0,0 +1,273 @@
+// Reads the resource limits of the cgroup the monitor is running in.
+//
+// `/proc/self/cgroup` tells which cgroup that is, relative to the root of the hierarchy mounted
+// at `/sys/fs/cgroup`. cgroup v2 exposes every controller in the single directory of that cgroup,
+// while v1 splits the memory and cpu controllers into their own hierarchies. Inside a cgroup
+// namespace the monitor's cgroup is the root of what is mounted, so the path is `/`.
+
+use std::path::{Path, PathBuf};
+
+use crate::channels::CgroupStats;
+use crate::kernel_fs;
+
+/// Paths of the cgroups of a process relative to the root of their hierarchy, from
+/// `/proc/<pid>/cgroup`
+#[derive(Debug, Default, PartialEq)]
+pub struct Membership {
+    /// cgroup of the v2 unified hierarchy
+    pub unified: Option<String>,
+    /// cgroup of the v1 memory controller
+    pub memory: Option<String>,
+    /// cgroup of the v1 cpu controller
+    pub cpu: Option<String>,
+}
+
+pub fn read_limits() -> Option<CgroupStats> {
+    read_limits_in(kernel_fs::proc_root()?, kernel_fs::sys_root()?)
+}
+
+/// Reads the limits of the monitor's cgroup, finding it in the proc directory at `proc_root` and
+/// reading it from the `fs/cgroup` hierarchy of the sys directory at `sys_root`
+pub fn read_limits_in(proc_root: &Path, sys_root: &Path) -> Option<CgroupStats> {
+    let membership = parse_membership(&read_file(&proc_root.join("self/cgroup"))?);
+    let hierarchy = sys_root.join("fs/cgroup");
+    let v2 = || read_v2(&resolve(&hierarchy, membership.unified.as_deref()?)?);
+    let v1 = || {
+        let memory = resolve(&hierarchy.join("memory"), membership.memory.as_deref()?)?;
+        // Without a cpu controller the limits still have the memory, with an unlimited cpu
+        let cpu = resolve(
+            &hierarchy.join("cpu"),
+            membership.cpu.as_deref().unwrap_or("/"),
+        )
+        .unwrap_or_else(|| hierarchy.join("cpu"));
+        read_v1(&memory, &cpu)
+    };
+    v2().or_else(v1)
+}
+
+/// Parses the `<id>:<controllers>:<path>` lines of `/proc/<pid>/cgroup`, where the v2 line has
+/// the id 0 and no controller
+pub fn parse_membership(contents: &str) -> Membership {
+    let mut membership = Membership::default();
+    for line in contents.lines() {
+        let mut fields = line.splitn(3, ':');
+        let (Some(id), Some(controllers), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if id == "0" && controllers.is_empty() {
+            membership.unified = Some(path.to_string());
+            continue;
+        }
+        for controller in controllers.split(',') {
+            match controller {
+                "memory" => membership.memory = Some(path.to_string()),
+                "cpu" => membership.cpu = Some(path.to_string()),
+                _ => {}
+            }
+        }
+    }
+    membership
+}
+
+/// Directory of the cgroup at `path` in the hierarchy mounted at `hierarchy`. Without a cgroup
+/// namespace, a container can list a cgroup of the host that isn't mounted inside it, whose
+/// limits are then the ones of the mounted root.
+fn resolve(hierarchy: &Path, path: &str) -> Option<PathBuf> {
+    let directory = hierarchy.join(path.trim_start_matches('/'));
+    if directory.is_dir() {
+        Some(directory)
+    } else if hierarchy.is_dir() {
+        Some(hierarchy.to_path_buf())
+    } else {
+        None
+    }
+}
+
+/// Reads the limits of the cgroup v2 directory at `cgroup`
+pub fn read_v2(cgroup: &Path) -> Option<CgroupStats> {
+    let memory_max = read_file(&cgroup.join("memory.max"))?;
+    let memory_current = read_file(&cgroup.join("memory.current"))?;
+    let (cpu_quota, cpu_period) = read_file(&cgroup.join("cpu.max"))
+        .and_then(|contents| parse_cpu_max(&contents))
+        .unwrap_or((None, 0));
+
+    Some(CgroupStats {
+        memory_limit_bytes: parse_limit(&memory_max)?,
+        memory_usage_bytes: memory_current.trim().parse().ok()?,
+        cpu_quota,
+        cpu_period,
+    })
+}
+
+/// Reads the limits of the cgroup v1 directories of the memory and cpu controllers
+pub fn read_v1(memory: &Path, cpu: &Path) -> Option<CgroupStats> {
+    let memory_limit = read_file(&memory.join("memory.limit_in_bytes"))?;
+    let memory_usage = read_file(&memory.join("memory.usage_in_bytes"))?;
+    let cpu_quota = read_file(&cpu.join("cpu.cfs_quota_us"))
+        .and_then(|contents| parse_v1_quota(&contents))
+        .unwrap_or(None);
+    let cpu_period = read_file(&cpu.join("cpu.cfs_period_us"))
+        .and_then(|contents| contents.trim().parse().ok())
+        .unwrap_or(0);
+
+    Some(CgroupStats {
+        memory_limit_bytes: parse_v1_memory_limit(&memory_limit)?,
+        memory_usage_bytes: memory_usage.trim().parse().ok()?,
+        cpu_quota,
+        cpu_period,
+    })
+}
+
+/// Parses a v2 limit file, where `max` means unlimited
+pub fn parse_limit(contents: &str) -> Option<Option<u64>> {
+    match contents.trim() {
+        "max" => Some(None),
+        value => value.parse().ok().map(Some),
+    }
+}
+
+/// Parses the v2 `cpu.max` file, formatted as `<quota|max> <period>`
+pub fn parse_cpu_max(contents: &str) -> Option<(Option<u64>, u64)> {
+    let mut fields = contents.split_whitespace();
+    let quota = parse_limit(fields.next()?)?;
+    let period = fields.next()?.parse().ok()?;
+    Some((quota, period))
+}
+
+/// Parses the v1 `memory.limit_in_bytes` file. v1 has no "max" keyword and instead reports a
+/// huge page-aligned value when the memory is unlimited.
+pub fn parse_v1_memory_limit(contents: &str) -> Option<Option<u64>> {
+    const V1_UNLIMITED_THRESHOLD: u64 = i64::MAX as u64 / 2;
+
+    let limit: u64 = contents.trim().parse().ok()?;
+    Some((limit < V1_UNLIMITED_THRESHOLD).then_some(limit))
+}
+
+/// Parses the v1 `cpu.cfs_quota_us` file, where `-1` means unlimited
+pub fn parse_v1_quota(contents: &str) -> Option<Option<u64>> {
+    let quota: i64 = contents.trim().parse().ok()?;
+    Some(u64::try_from(quota).ok())
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    fn limits(stats: &CgroupStats) -> (Option<u64>, u64, Option<u64>, u64) {
+        (
+            stats.memory_limit_bytes,
+            stats.memory_usage_bytes,
+            stats.cpu_quota,
+            stats.cpu_period,
+        )
+    }
+
+    #[test]
+    fn parses_v2_membership() {
+        let membership = parse_membership("0::/system.slice/app.service\n");
+        assert_eq!(
+            membership,
+            Membership {
+                unified: Some("/system.slice/app.service".to_string()),
+                memory: None,
+                cpu: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_v1_membership() {
+        let membership = parse_membership(
+            "12:pids:/docker/abc\n\
+             4:cpu,cpuacct:/docker/abc\n\
+             3:memory:/docker/abc\n\
+             1:name=systemd:/docker/abc\n",
+        );
+        assert_eq!(membership.unified, None);
+        assert_eq!(membership.memory.as_deref(), Some("/docker/abc"));
+        assert_eq!(membership.cpu.as_deref(), Some("/docker/abc"));
+    }
+
+    #[test]
+    fn parses_the_limit_files() {
+        assert_eq!(parse_limit("max\n"), Some(None));
+        assert_eq!(parse_limit("1073741824\n"), Some(Some(1073741824)));
+        assert_eq!(parse_limit("lots"), None);
+        assert_eq!(parse_cpu_max("50000 100000\n"), Some((Some(50000), 100000)));
+        assert_eq!(parse_cpu_max("max 100000\n"), Some((None, 100000)));
+        assert_eq!(parse_v1_memory_limit("9223372036854771712\n"), Some(None));
+        assert_eq!(parse_v1_memory_limit("536870912\n"), Some(Some(536870912)));
+        assert_eq!(parse_v1_quota("-1\n"), Some(None));
+        assert_eq!(parse_v1_quota("25000\n"), Some(Some(25000)));
+    }
+
+    #[test]
+    fn reads_the_monitors_v2_cgroup() {
+        let root = Fixture::new("cgroup-v2");
+        root.write("proc/self/cgroup", "0::/system.slice/app.service\n")
+            // The root cgroup has no limits of its own
+            .write("sys/fs/cgroup/cgroup.controllers", "cpu memory\n")
+            .write(
+                "sys/fs/cgroup/system.slice/app.service/memory.max",
+                "536870912\n",
+            )
+            .write(
+                "sys/fs/cgroup/system.slice/app.service/memory.current",
+                "1048576\n",
+            )
+            .write(
+                "sys/fs/cgroup/system.slice/app.service/cpu.max",
+                "50000 100000\n",
+            );
+        let stats = read_limits_in(&root.join("proc"), &root.join("sys")).unwrap();
+        assert_eq!(
+            limits(&stats),
+            (Some(536870912), 1048576, Some(50000), 100000)
+        );
+    }
+
+    #[test]
+    fn reads_the_mounted_root_of_an_unmounted_cgroup() {
+        let root = Fixture::new("cgroup-namespace");
+        root.write("proc/self/cgroup", "0::/host/only/path\n")
+            .write("sys/fs/cgroup/memory.max", "max\n")
+            .write("sys/fs/cgroup/memory.current", "4096\n");
+        let stats = read_limits_in(&root.join("proc"), &root.join("sys")).unwrap();
+        assert_eq!(limits(&stats), (None, 4096, None, 0));
+    }
+
+    #[test]
+    fn reads_the_monitors_v1_cgroups() {
+        let root = Fixture::new("cgroup-v1");
+        root.write(
+            "proc/self/cgroup",
+            "4:cpu,cpuacct:/docker/abc\n3:memory:/docker/abc\n",
+        )
+        .write(
+            "sys/fs/cgroup/memory/docker/abc/memory.limit_in_bytes",
+            "536870912\n",
+        )
+        .write(
+            "sys/fs/cgroup/memory/docker/abc/memory.usage_in_bytes",
+            "2048\n",
+        )
+        .write("sys/fs/cgroup/cpu/docker/abc/cpu.cfs_quota_us", "-1\n")
+        .write("sys/fs/cgroup/cpu/docker/abc/cpu.cfs_period_us", "100000\n");
+        let stats = read_limits_in(&root.join("proc"), &root.join("sys")).unwrap();
+        assert_eq!(limits(&stats), (Some(536870912), 2048, None, 100000));
+    }
+
+    #[test]
+    fn no_limits_outside_a_cgroup() {
+        let root = Fixture::new("cgroup-none");
+        root.write("proc/self/cgroup", "0::/\n");
+        assert!(read_limits_in(&root.join("proc"), &root.join("sys")).is_none());
+    }
+}
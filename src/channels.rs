@@ -1,15 +1,139 @@
 // This file contains the channels used to log the system information.
 
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::LazyLock;
+
+use foxglove::TypedChannel;
 use schemars::JsonSchema;
+use schemars::r#gen::SchemaSettings;
 use serde::Serialize;
 
+use crate::temperature::TemperatureUnit;
+
+/// A channel that is only registered the first time it is used, but whose topic is known
+/// upfront so a message can be filtered out without registering the channel
+pub struct LazyChannel<T: Serialize + JsonSchema> {
+    topic: &'static str,
+    channel: LazyLock<TypedChannel<T>, fn() -> TypedChannel<T>>,
+}
+
+impl<T: Serialize + JsonSchema> LazyChannel<T> {
+    pub const fn new(topic: &'static str, build: fn() -> TypedChannel<T>) -> Self {
+        Self {
+            topic,
+            channel: LazyLock::new(build),
+        }
+    }
+
+    pub fn topic(&self) -> &'static str {
+        self.topic
+    }
+
+    /// Name of the schema the channel is registered with, without registering it
+    pub fn schema_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
+
+impl<T: Serialize + JsonSchema> Deref for LazyChannel<T> {
+    type Target = TypedChannel<T>;
+
+    fn deref(&self) -> &TypedChannel<T> {
+        &self.channel
+    }
+}
+
+/// JSON schema of a message type, generated like foxglove does for typed channels but with a
+/// stable `fox-monitor/<type>` title and `$id` that consumers can key on, and with the `seq` field
+/// `payload::Sequenced` adds to every message
+pub fn json_schema<T: JsonSchema>() -> foxglove::Schema {
+    let settings = SchemaSettings::draft07().with(|settings| {
+        settings.inline_subschemas = true;
+    });
+    let mut generator = settings.into_generator();
+    let seq = generator.subschema_for::<u64>();
+    let mut schema = generator.into_root_schema_for::<T>();
+    let object = schema.schema.object();
+    object.properties.insert("seq".to_string(), seq);
+    object.required.insert("seq".to_string());
+    let id = format!("fox-monitor/{}", T::schema_name());
+    let metadata = schema.schema.metadata();
+    metadata.id = Some(id.clone());
+    metadata.title = Some(id);
+    foxglove::Schema::new(
+        std::any::type_name::<T>(),
+        "jsonschema",
+        serde_json::to_vec(&schema).expect("Failed to serialize schema"),
+    )
+}
+
+/// Like `foxglove::static_typed_channel!`, but also attaches the unit and description of the
+/// listed fields as channel metadata, under the `<field>.unit` and `<field>.description` keys.
+/// Fields nested in a list are named `<list>.<field>`.
+macro_rules! static_typed_channel_with_fields {
+    ($vis:vis $ident:ident, $topic:literal, $ty:ty, {
+        $($field:literal => ($unit:expr, $description:literal)),* $(,)?
+    }) => {
+        $vis static $ident: LazyChannel<$ty> = LazyChannel::new($topic, || {
+            let metadata = BTreeMap::from([
+                $(
+                    (concat!($field, ".unit").to_string(), $unit.to_string()),
+                    (concat!($field, ".description").to_string(), $description.to_string()),
+                )*
+            ]);
+            match foxglove::ChannelBuilder::new($topic)
+                .metadata(metadata)
+                .schema(json_schema::<$ty>())
+                .build_typed()
+            {
+                Ok(channel) => channel,
+                Err(e) => panic!("Failed to create channel for {}: {:?}", $topic, e),
+            }
+        });
+    };
+    // Fields shared by several channels, as a slice of field, unit and description
+    ($vis:vis $ident:ident, $topic:literal, $ty:ty, $fields:expr) => {
+        $vis static $ident: LazyChannel<$ty> = LazyChannel::new($topic, || {
+            let metadata = $fields
+                .iter()
+                .flat_map(|(field, unit, description)| {
+                    [
+                        (format!("{field}.unit"), unit.to_string()),
+                        (format!("{field}.description"), description.to_string()),
+                    ]
+                })
+                .collect::<BTreeMap<_, _>>();
+            match foxglove::ChannelBuilder::new($topic)
+                .metadata(metadata)
+                .schema(json_schema::<$ty>())
+                .build_typed()
+            {
+                Ok(channel) => channel,
+                Err(e) => panic!("Failed to create channel for {}: {:?}", $topic, e),
+            }
+        });
+    };
+}
+
 // CPU Channel
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct CoreStats {
+    /// Logical cpu index, identifies the core when `--max-cores` leaves some out
+    pub index: u32,
     pub usage: f32,
     pub frequency_mhz: u64,
-    pub vendor_id: String,
-    pub brand: String,
+    /// Left out when `--compact-cpu` moved them to the cpu because every core has the same
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    /// Physical package and core of the logical cpu, `None` off Linux
+    pub package_id: Option<u32>,
+    pub core_id: Option<u32>,
+    /// Time spent idle and busy since boot, `None` unless `--cpu-raw-counters` is set on Linux
+    pub idle_time_ms: Option<u64>,
+    pub busy_time_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize, JsonSchema)]
@@ -17,19 +141,58 @@ pub struct CpuStats {
     pub usage: f32,
     pub physical_cores: u16,
     pub cores: Vec<CoreStats>,
+    /// Set when `--max-cores` or the `cpu.include_cores` parameter left cores out of `cores`, the
+    /// other fields still cover every core
+    pub cores_truncated: bool,
+    /// Vendor and brand shared by every core with `--compact-cpu`, `None` when the cores differ
+    pub vendor_id: Option<String>,
+    pub brand: Option<String>,
+    /// Set when the kernel reports new throttling events, or when the cpu runs well below its
+    /// maximum frequency while hot
+    pub thermal_throttling: bool,
+    /// Share of the cpu time a hypervisor ran other guests since the previous sample, zero off
+    /// Linux
+    pub steal_percent: f32,
 }
-foxglove::static_typed_channel!(pub(crate) CPU, "/cpu", CpuStats);
+static_typed_channel_with_fields!(pub(crate) CPU, "/cpu", CpuStats, {
+    "usage" => ("%", "Global cpu usage"),
+    "cores.usage" => ("%", "Core usage"),
+    "cores.frequency_mhz" => ("MHz", "Core frequency"),
+    "cores.idle_time_ms" => ("ms", "Time the core spent idle since boot"),
+    "cores.busy_time_ms" => ("ms", "Time the core spent busy since boot"),
+    "steal_percent" => ("%", "Cpu time stolen by the hypervisor"),
+});
 
 // Memory Channel
+//
+// `free_kb` is memory nothing uses at all, while `available_kb` also counts the caches and buffers
+// the kernel can reclaim, so it is usually much larger. `used_kb` follows each platform's own
+// definition:
+// - Linux: total minus available, so caches and buffers are not counted as used. This matches the
+//   `used` column of recent `free` and `htop`.
+// - macOS: active, wired, compressed and speculative pages, as in Activity Monitor. Used and
+//   available don't always add up to the total.
+// - Windows: total minus available, and free is the same as available.
+//
+// Despite their `_kb` suffix the fields are in bytes, as they always were, so existing recordings
+// and dashboards keep working.
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct MemoryStats {
     pub total_kb: u64,
     pub available_kb: u64,
+    pub free_kb: u64,
     pub used_kb: u64,
     pub swap_total_kb: u64,
     pub swap_used_kb: u64,
 }
-foxglove::static_typed_channel!(pub(crate) MEMORY, "/memory", MemoryStats);
+static_typed_channel_with_fields!(pub(crate) MEMORY, "/memory", MemoryStats, {
+    "total_kb" => ("B", "Total RAM"),
+    "available_kb" => ("B", "RAM available for new allocations"),
+    "free_kb" => ("B", "Unused RAM, not counting reclaimable caches"),
+    "used_kb" => ("B", "Used RAM"),
+    "swap_total_kb" => ("B", "Total swap"),
+    "swap_used_kb" => ("B", "Used swap"),
+});
 
 // Components Channel
 #[derive(Debug, Serialize, JsonSchema)]
@@ -41,8 +204,26 @@ pub struct ComponentStats {
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct ComponentsStats {
     pub components: Vec<ComponentStats>,
+    /// Unit of the temperatures, also listed in the channel metadata
+    pub unit: TemperatureUnit,
+}
+static_typed_channel_with_fields!(pub(crate) COMPONENTS, "/components", ComponentsStats, {
+    "components.temperature" => (TemperatureUnit::selected().symbol(), "Component temperature"),
+});
+
+// Temperature summary Channel
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TemperatureSummaryStats {
+    pub max_temperature: f32,
+    pub max_label: String,
+    pub avg_temperature: f32,
+    /// Unit of the temperatures, also listed in the channel metadata
+    pub unit: TemperatureUnit,
 }
-foxglove::static_typed_channel!(pub(crate) COMPONENTS, "/components", ComponentsStats);
+static_typed_channel_with_fields!(pub(crate) TEMPERATURE_SUMMARY, "/temperature_summary", TemperatureSummaryStats, {
+    "max_temperature" => (TemperatureUnit::selected().symbol(), "Temperature of the hottest component"),
+    "avg_temperature" => (TemperatureUnit::selected().symbol(), "Average temperature across components"),
+});
 
 // Disks channel
 #[derive(Debug, Serialize, JsonSchema)]
@@ -53,13 +234,31 @@ pub struct DiskStats {
     pub total_written_kb: u64,
     pub read_kb: u64,
     pub written_kb: u64,
+    /// `None` on platforms without `statvfs`
+    pub inodes_total: Option<u64>,
+    pub inodes_used: Option<u64>,
+    pub inodes_free: Option<u64>,
+    /// Average latency of the operations completed since the previous sample, `None` on the
+    /// first sample, without completed operations and off Linux
+    pub read_latency_ms: Option<f32>,
+    pub write_latency_ms: Option<f32>,
 }
 
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct DisksStats {
     pub disks: Vec<DiskStats>,
 }
-foxglove::static_typed_channel!(pub(crate) DISKS, "/disks", DisksStats);
+static_typed_channel_with_fields!(pub(crate) DISKS, "/disks", DisksStats, {
+    "disks.total_read_kb" => ("kB", "Data read since boot"),
+    "disks.total_written_kb" => ("kB", "Data written since boot"),
+    "disks.read_kb" => ("kB", "Data read since the previous sample"),
+    "disks.written_kb" => ("kB", "Data written since the previous sample"),
+    "disks.inodes_total" => ("inodes", "Total inodes of the filesystem"),
+    "disks.inodes_used" => ("inodes", "Used inodes of the filesystem"),
+    "disks.inodes_free" => ("inodes", "Free inodes of the filesystem"),
+    "disks.read_latency_ms" => ("ms", "Average read latency since the previous sample"),
+    "disks.write_latency_ms" => ("ms", "Average write latency since the previous sample"),
+});
 
 // Networks channel
 #[derive(Debug, Serialize, JsonSchema)]
@@ -70,13 +269,40 @@ pub struct NetworkStats {
     pub transmitted: u64,
     pub total_received: u64,
     pub total_transmitted: u64,
+    /// `None` for virtual or disconnected interfaces and off Linux
+    pub link_speed_mbps: Option<u64>,
+    /// `None` off Linux
+    pub mtu: Option<u32>,
 }
 
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct NetworksStats {
     pub networks: Vec<NetworkStats>,
 }
-foxglove::static_typed_channel!(pub(crate) NETWORKS, "/networks", NetworksStats);
+static_typed_channel_with_fields!(pub(crate) NETWORKS, "/networks", NetworksStats, {
+    "networks.received" => ("B", "Data received since the previous sample"),
+    "networks.transmitted" => ("B", "Data transmitted since the previous sample"),
+    "networks.total_received" => ("B", "Data received since boot"),
+    "networks.total_transmitted" => ("B", "Data transmitted since boot"),
+    "networks.link_speed_mbps" => ("Mbit/s", "Negotiated link speed"),
+    "networks.mtu" => ("B", "Maximum transmission unit"),
+});
+
+// Network total channel, the sum of all interfaces
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct NetworkTotalStats {
+    pub interfaces: u32,
+    pub received: u64,
+    pub transmitted: u64,
+    pub total_received: u64,
+    pub total_transmitted: u64,
+}
+static_typed_channel_with_fields!(pub(crate) NETWORK_TOTAL, "/network_total", NetworkTotalStats, {
+    "received" => ("B", "Data received since the previous sample"),
+    "transmitted" => ("B", "Data transmitted since the previous sample"),
+    "total_received" => ("B", "Data received since boot"),
+    "total_transmitted" => ("B", "Data transmitted since boot"),
+});
 
 // Processes
 #[derive(Debug, Serialize, JsonSchema)]
@@ -85,31 +311,554 @@ pub struct ProcessStats {
     pub parent_pid: String,
     pub name: String,
     pub status: String,
+    /// Percent of a single core, so a process using several cores exceeds 100%, unless
+    /// `--process-cpu-normalized` makes it a percent of the whole machine
     pub cpu_usage: f32,
     pub memory_usage_kb: u64,
     pub start_time_seconds: u64,
+    /// Start time as an RFC 3339 timestamp in the --timezone zone, empty when the start time is
+    /// unknown
+    pub start_time_iso: String,
     pub run_time_seconds: u64,
+    /// `None` unless `--process-sockets` is set on Linux
+    pub open_sockets: Option<u32>,
+    /// Disk read and write rates since the previous sample, zero on the first sample of a process
+    pub read_bytes_per_second: f64,
+    pub written_bytes_per_second: f64,
+    /// Kernel scheduling priority, `None` unless `--process-scheduling` is set on Linux
+    pub priority: Option<i32>,
+    /// Nice value from -20 to 19, `None` unless `--process-scheduling` is set on Linux
+    pub nice: Option<i32>,
 }
 
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct ProcessesStats {
     pub processes: Vec<ProcessStats>,
 }
-foxglove::static_typed_channel!(pub(crate) PROCESSES, "/processes", ProcessesStats);
+/// Metadata of the processes fields, shared by /processes and /processes_full
+const PROCESS_FIELDS: &[(&str, &str, &str)] = &[
+    (
+        "processes.cpu_usage",
+        "%",
+        "Process cpu usage, per core unless --process-cpu-normalized",
+    ),
+    (
+        "processes.memory_usage_kb",
+        "kB",
+        "Resident memory of the process",
+    ),
+    (
+        "processes.start_time_seconds",
+        "s",
+        "Process start time since the Unix epoch",
+    ),
+    (
+        "processes.run_time_seconds",
+        "s",
+        "Time since the process started",
+    ),
+    (
+        "processes.open_sockets",
+        "sockets",
+        "Open sockets of the process",
+    ),
+    (
+        "processes.read_bytes_per_second",
+        "B/s",
+        "Disk read rate of the process",
+    ),
+    (
+        "processes.written_bytes_per_second",
+        "B/s",
+        "Disk write rate of the process",
+    ),
+];
+static_typed_channel_with_fields!(pub(crate) PROCESSES, "/processes", ProcessesStats, PROCESS_FIELDS);
+
+// Full processes snapshot, every process regardless of the processes filters, logged at a
+// slower interval
+static_typed_channel_with_fields!(pub(crate) PROCESSES_FULL, "/processes_full", ProcessesStats, PROCESS_FIELDS);
+
+// Compact processes, a slimmer alternative to the processes channel. A typical serialized
+// process is ~75 bytes instead of ~310 bytes for a full `ProcessStats`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CompactProcessStats {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_usage: f32,
+    pub memory_usage_kb: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CompactProcessesStats {
+    pub processes: Vec<CompactProcessStats>,
+}
+static_typed_channel_with_fields!(pub(crate) PROCESSES_COMPACT, "/processes_compact", CompactProcessesStats, {
+    "processes.cpu_usage" => ("%", "Process cpu usage, per core unless --process-cpu-normalized"),
+    "processes.memory_usage_kb" => ("kB", "Resident memory of the process"),
+});
 
 // System
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct SystemStats {
-    pub name: String,
-    pub kernel_version: String,
-    pub os_version: String,
-    pub os_long_version: String,
-    pub host_name: String,
-    pub kernel: String,
+    /// The identity fields are left out when `--info` logs them once on /info
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_long_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
     pub boot_time_seconds: u64,
     pub uptime_seconds: u64,
     pub load_avg_one: f64,
     pub load_avg_five: f64,
     pub load_avg_fifteen: f64,
+    /// Entropy available to the kernel random number generator, `None` off Linux
+    pub entropy_available: Option<u32>,
+}
+static_typed_channel_with_fields!(pub(crate) SYSTEM, "/system", SystemStats, {
+    "boot_time_seconds" => ("s", "Boot time since the Unix epoch"),
+    "uptime_seconds" => ("s", "Time since boot"),
+    "load_avg_one" => ("", "Load average over the last minute"),
+    "load_avg_five" => ("", "Load average over the last 5 minutes"),
+    "load_avg_fifteen" => ("", "Load average over the last 15 minutes"),
+    "entropy_available" => ("bits", "Entropy available to the kernel random number generator"),
+});
+
+// Static system facts, logged once at startup
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct InfoStats {
+    pub name: String,
+    pub kernel_version: String,
+    pub os_version: String,
+    pub os_long_version: String,
+    pub host_name: String,
+    pub cpu_arch: String,
+    /// `None` when the platform doesn't report it
+    pub physical_cores: Option<usize>,
+    pub logical_cores: usize,
+}
+static_typed_channel_with_fields!(pub(crate) INFO, "/info", InfoStats, {
+    "physical_cores" => ("", "Number of physical cores"),
+    "logical_cores" => ("", "Number of logical cpus"),
+});
+
+// Cgroup
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CgroupStats {
+    /// `None` when the memory is unlimited
+    pub memory_limit_bytes: Option<u64>,
+    pub memory_usage_bytes: u64,
+    /// `None` when the cpu time is unlimited
+    pub cpu_quota: Option<u64>,
+    pub cpu_period: u64,
+}
+static_typed_channel_with_fields!(pub(crate) CGROUP, "/cgroup", CgroupStats, {
+    "memory_limit_bytes" => ("B", "Memory limit of the cgroup"),
+    "memory_usage_bytes" => ("B", "Memory used by the cgroup"),
+    "cpu_quota" => ("µs", "Cpu time the cgroup can use per period"),
+    "cpu_period" => ("µs", "Length of a cpu quota period"),
+});
+
+// Pressure stall information
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PressureAverages {
+    pub avg10: f32,
+    pub avg60: f32,
+    pub avg300: f32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ResourcePressure {
+    pub some: PressureAverages,
+    /// `None` when the kernel doesn't report it, e.g. for the cpu before Linux 5.13
+    pub full: Option<PressureAverages>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PressureStats {
+    /// `None` when the resource's pressure file is missing
+    pub cpu: Option<ResourcePressure>,
+    pub memory: Option<ResourcePressure>,
+    pub io: Option<ResourcePressure>,
+}
+static_typed_channel_with_fields!(pub(crate) PRESSURE, "/pressure", PressureStats, {
+    "cpu.some.avg10" => ("%", "Share of the last 10 seconds some tasks were stalled on the cpu"),
+    "memory.some.avg10" => ("%", "Share of the last 10 seconds some tasks were stalled on memory"),
+    "memory.full.avg10" => ("%", "Share of the last 10 seconds all tasks were stalled on memory"),
+    "io.some.avg10" => ("%", "Share of the last 10 seconds some tasks were stalled on io"),
+    "io.full.avg10" => ("%", "Share of the last 10 seconds all tasks were stalled on io"),
+});
+
+// Services
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ServiceStats {
+    pub name: String,
+    pub display_name: String,
+    pub status: String,
+    pub start_type: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ServicesStats {
+    pub services: Vec<ServiceStats>,
+}
+static_typed_channel_with_fields!(pub(crate) SERVICES, "/services", ServicesStats, {});
+
+// Power
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PowerStats {
+    pub cpu_power_watts: f32,
+    pub gpu_power_watts: f32,
+    pub package_power_watts: f32,
+}
+static_typed_channel_with_fields!(pub(crate) POWER, "/power", PowerStats, {
+    "cpu_power_watts" => ("W", "Cpu power draw"),
+    "gpu_power_watts" => ("W", "Gpu power draw"),
+    "package_power_watts" => ("W", "Package power draw"),
+});
+
+// Processes using a GPU
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct GpuProcessesStats {
+    pub processes: Vec<GpuProcessStats>,
+}
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct GpuProcessStats {
+    pub pid: u32,
+    /// Empty when the process is not in the system process table, e.g. in another pid namespace
+    pub name: String,
+    pub gpu_uuid: String,
+    pub used_memory_bytes: u64,
+}
+static_typed_channel_with_fields!(pub(crate) GPU_PROCESSES, "/gpu_processes", GpuProcessesStats, {
+    "processes.used_memory_bytes" => ("B", "Video memory used by the process"),
+});
+
+// Processes in uninterruptible sleep, usually waiting on a hung disk or network filesystem
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BlockedProcessesStats {
+    pub processes: Vec<BlockedProcessStats>,
+}
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BlockedProcessStats {
+    pub pid: u32,
+    pub name: String,
+    pub run_time_seconds: u64,
+}
+static_typed_channel_with_fields!(pub(crate) BLOCKED_PROCESSES, "/blocked_processes", BlockedProcessesStats, {
+    "processes.run_time_seconds" => ("s", "Time since the process started"),
+});
+
+// SMART health of the disks
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SmartStats {
+    pub devices: Vec<SmartDeviceStats>,
+    /// Unit of the temperatures, also listed in the channel metadata
+    pub unit: TemperatureUnit,
+}
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SmartDeviceStats {
+    pub device: String,
+    /// `PASSED`, `FAILED` or `Unknown`
+    pub health: String,
+    pub temperature: Option<f32>,
+    pub power_on_hours: Option<u64>,
+}
+static_typed_channel_with_fields!(pub(crate) SMART, "/smart", SmartStats, {
+    "devices.temperature" => (TemperatureUnit::selected().symbol(), "Drive temperature"),
+    "devices.power_on_hours" => ("h", "Time the drive has been powered on"),
+});
+
+// Alerts, logged when a usage alert fires or clears
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AlertStats {
+    /// `memory`, or `disk:<mount point>`
+    pub metric: String,
+    /// `fired` or `cleared`
+    pub state: String,
+    pub value_percent: f64,
+    /// Threshold that was crossed
+    pub threshold_percent: f64,
+}
+static_typed_channel_with_fields!(pub(crate) ALERTS, "/alerts", AlertStats, {
+    "value_percent" => ("%", "Usage when the alert fired or cleared"),
+    "threshold_percent" => ("%", "Threshold that was crossed"),
+});
+
+// One-off events detected while monitoring
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct EventStats {
+    /// `reboot_detected`
+    pub kind: String,
+    pub detail: String,
+}
+static_typed_channel_with_fields!(pub(crate) EVENTS, "/events", EventStats, {});
+
+// Run info, created on demand since the tags are also attached as channel metadata
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RunInfoStats {
+    pub tags: BTreeMap<String, String>,
+}
+pub const RUN_INFO_TOPIC: &str = "/run_info";
+
+// Resource usage of the monitor process
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SelfStats {
+    pub pid: u32,
+    /// Cpu usage since the previous sample, zero on the first one
+    pub cpu_usage: f32,
+    pub memory_usage_kb: u64,
+    pub virtual_memory_kb: u64,
+}
+static_typed_channel_with_fields!(pub(crate) SELF, "/self", SelfStats, {
+    "cpu_usage" => ("%", "Cpu usage of the monitor"),
+    "memory_usage_kb" => ("kB", "Resident memory of the monitor"),
+    "virtual_memory_kb" => ("kB", "Virtual memory of the monitor"),
+});
+
+// History of the processes given with --track-pid
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TrackedProcessesStats {
+    pub processes: Vec<TrackedProcessStats>,
+}
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TrackedProcessStats {
+    pub pid: u32,
+    /// Name of the process, empty until it was seen
+    pub name: String,
+    /// Whether the process was running at this sample, the history of an exited process is kept
+    pub running: bool,
+    /// `None` until the process was seen
+    pub start_time_seconds: Option<u64>,
+    /// From the oldest to the newest sample, reset when the pid is reused
+    pub history: Vec<TrackedSampleStats>,
+}
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TrackedSampleStats {
+    pub timestamp_seconds: u64,
+    pub cpu_usage: f32,
+    pub memory_usage_kb: u64,
+}
+static_typed_channel_with_fields!(pub(crate) TRACKED_PROCESSES, "/tracked_processes", TrackedProcessesStats, {
+    "processes.start_time_seconds" => ("s", "Process start time since the Unix epoch"),
+    "processes.history.timestamp_seconds" => ("s", "Time of the sample since the Unix epoch"),
+    "processes.history.cpu_usage" => ("%", "Cpu usage of the process"),
+    "processes.history.memory_usage_kb" => ("kB", "Resident memory of the process"),
+});
+
+// Status of the monitor itself
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct StatusStats {
+    /// Achieved sampling rate, averaged over the last few iterations
+    pub samples_per_second: f64,
+    pub requested_samples_per_second: f64,
+    /// Messages dropped since startup because the output couldn't keep up
+    pub dropped_samples: u64,
+    /// Messages skipped on purpose since startup by --dedupe, --omit-empty or
+    /// --processes-on-change, which also leave gaps in the `seq` of their topic
+    pub skipped_samples: u64,
+}
+static_typed_channel_with_fields!(pub(crate) STATUS, "/status", StatusStats, {
+    "samples_per_second" => ("Hz", "Achieved sampling rate"),
+    "requested_samples_per_second" => ("Hz", "Sampling rate requested by the interval"),
+    "dropped_samples" => ("messages", "Messages dropped by the output queue"),
+    "skipped_samples" => ("messages", "Messages skipped on purpose"),
+});
+
+// Number of items of each metric, without their details, as a cheap liveness metric
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CountsStats {
+    pub cpu_count: usize,
+    pub disk_count: usize,
+    pub network_count: usize,
+    pub process_count: usize,
+    pub sensor_count: usize,
+}
+static_typed_channel_with_fields!(pub(crate) COUNTS, "/counts", CountsStats, {
+    "cpu_count" => ("cpus", "Logical cpus"),
+    "disk_count" => ("disks", "Mounted disks"),
+    "network_count" => ("interfaces", "Network interfaces"),
+    "process_count" => ("processes", "Running processes"),
+    "sensor_count" => ("sensors", "Temperature sensors"),
+});
+
+// Min, average and max of headline values over each --summary-interval
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RollupStats {
+    /// Length of the summarized window, at least the summary interval
+    pub window_seconds: f64,
+    /// Global cpu usage, `None` without --cpu
+    pub cpu_usage: Option<RollupValueStats>,
+    /// `None` without --memory
+    pub memory_used_kb: Option<RollupValueStats>,
+    pub load_avg_one: Option<RollupValueStats>,
+}
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RollupValueStats {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+static_typed_channel_with_fields!(pub(crate) ROLLUP, "/rollup", RollupStats, {
+    "window_seconds" => ("s", "Length of the summarized window"),
+    "cpu_usage.min" => ("%", "Lowest global cpu usage over the window"),
+    "cpu_usage.avg" => ("%", "Average global cpu usage over the window"),
+    "cpu_usage.max" => ("%", "Highest global cpu usage over the window"),
+    "memory_used_kb.min" => ("kB", "Lowest used memory over the window"),
+    "memory_used_kb.avg" => ("kB", "Average used memory over the window"),
+    "memory_used_kb.max" => ("kB", "Highest used memory over the window"),
+    "load_avg_one.min" => ("", "Lowest 1 minute load average over the window"),
+    "load_avg_one.avg" => ("", "Average 1 minute load average over the window"),
+    "load_avg_one.max" => ("", "Highest 1 minute load average over the window"),
+});
+
+/// A message listing the items of a metric, which has none on hosts lacking that hardware
+pub trait Collection {
+    fn is_empty(&self) -> bool;
+}
+
+macro_rules! impl_collection {
+    ($($stats:ty => $items:ident),* $(,)?) => {
+        $(impl Collection for $stats {
+            fn is_empty(&self) -> bool {
+                self.$items.is_empty()
+            }
+        })*
+    };
+}
+
+impl_collection!(
+    CpuStats => cores,
+    ComponentsStats => components,
+    DisksStats => disks,
+    NetworksStats => networks,
+    ProcessesStats => processes,
+    CompactProcessesStats => processes,
+    ServicesStats => services,
+    GpuProcessesStats => processes,
+    BlockedProcessesStats => processes,
+    SmartStats => devices,
+    TrackedProcessesStats => processes,
+);
+
+/// Topic and schema name of every channel the monitor can log on, enabled or not
+pub fn all_channels() -> Vec<(&'static str, &'static str)> {
+    macro_rules! channels {
+        ($($channel:ident),* $(,)?) => {
+            vec![$(($channel.topic(), $channel.schema_name())),*]
+        };
+    }
+    let mut channels = channels!(
+        CPU,
+        MEMORY,
+        COMPONENTS,
+        TEMPERATURE_SUMMARY,
+        DISKS,
+        NETWORKS,
+        NETWORK_TOTAL,
+        PROCESSES,
+        PROCESSES_FULL,
+        PROCESSES_COMPACT,
+        SYSTEM,
+        INFO,
+        CGROUP,
+        PRESSURE,
+        SERVICES,
+        POWER,
+        GPU_PROCESSES,
+        BLOCKED_PROCESSES,
+        SMART,
+        ALERTS,
+        EVENTS,
+        SELF,
+        TRACKED_PROCESSES,
+        STATUS,
+        COUNTS,
+        ROLLUP,
+    );
+    channels.push((RUN_INFO_TOPIC, std::any::type_name::<RunInfoStats>()));
+    channels
+}
+
+#[cfg(test)]
+mod tests {
+    use foxglove::Context;
+
+    use super::*;
+
+    #[test]
+    fn fields_have_their_unit_and_description_in_the_metadata() {
+        // Registers the channel
+        let _ = CPU.schema();
+        let channel = Context::get_default()
+            .get_channel_by_topic(CPU.topic())
+            .unwrap();
+        let metadata = channel.metadata();
+        assert_eq!(metadata["usage.unit"], "%");
+        assert_eq!(metadata["usage.description"], "Global cpu usage");
+        assert_eq!(metadata["cores.frequency_mhz.unit"], "MHz");
+    }
+
+    #[test]
+    fn schema_has_a_stable_id_and_the_seq_field() {
+        let schema = json_schema::<CountsStats>();
+        assert_eq!(schema.encoding, "jsonschema");
+        let schema: serde_json::Value = serde_json::from_slice(&schema.data).unwrap();
+        assert_eq!(schema["$id"], "fox-monitor/CountsStats");
+        assert_eq!(schema["title"], "fox-monitor/CountsStats");
+        assert_eq!(schema["properties"]["seq"]["type"], "integer");
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&"seq".into()));
+        assert!(required.contains(&"process_count".into()));
+    }
+
+    #[test]
+    fn compact_process_is_a_fraction_of_the_full_size() {
+        let full = ProcessStats {
+            pid: 12345,
+            parent_pid: "1".to_string(),
+            name: "fox-monitor".to_string(),
+            status: "Runnable".to_string(),
+            cpu_usage: 1.25,
+            memory_usage_kb: 20480,
+            start_time_seconds: 1_700_000_000,
+            start_time_iso: "2023-11-14T22:13:20Z".to_string(),
+            run_time_seconds: 3600,
+            open_sockets: None,
+            read_bytes_per_second: 0.0,
+            written_bytes_per_second: 512.0,
+            priority: Some(20),
+            nice: Some(0),
+        };
+        let compact = CompactProcessStats {
+            pid: full.pid,
+            name: full.name.clone(),
+            cpu_usage: full.cpu_usage,
+            memory_usage_kb: full.memory_usage_kb,
+        };
+        let full_len = serde_json::to_vec(&full).unwrap().len();
+        let compact_len = serde_json::to_vec(&compact).unwrap().len();
+        assert!(compact_len * 3 < full_len, "{compact_len} vs {full_len}");
+    }
+
+    #[test]
+    fn full_snapshot_shares_the_processes_metadata() {
+        let _ = PROCESSES.schema();
+        let _ = PROCESSES_FULL.schema();
+        let context = Context::get_default();
+        let processes = context.get_channel_by_topic(PROCESSES.topic()).unwrap();
+        let full = context
+            .get_channel_by_topic(PROCESSES_FULL.topic())
+            .unwrap();
+        assert_eq!(processes.metadata(), full.metadata());
+        assert_eq!(processes.metadata()["processes.memory_usage_kb.unit"], "kB");
+        assert_eq!(processes.metadata().len(), PROCESS_FIELDS.len() * 2);
+    }
 }
-foxglove::static_typed_channel!(pub(crate) SYSTEM, "/system", SystemStats);
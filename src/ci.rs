@@ -0,0 +1,69 @@
+// Detects whether the monitor runs in a CI job, where nobody connects to the websocket server.
+
+/// Variables set by common CI providers, checked after the generic `CI` variable
+const PROVIDER_VARIABLES: &[&str] = &[
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "JENKINS_URL",
+    "TEAMCITY_VERSION",
+    "TF_BUILD",
+];
+
+/// Name of the variable that marks the environment as CI, if any
+pub fn detect() -> Option<&'static str> {
+    detect_with(|name| std::env::var(name).ok())
+}
+
+/// Like `detect`, reading the variables through `var`
+pub fn detect_with(var: impl Fn(&str) -> Option<String>) -> Option<&'static str> {
+    let is_set = |name: &str| {
+        var(name).is_some_and(|value| {
+            !value.is_empty() && !value.eq_ignore_ascii_case("false") && value != "0"
+        })
+    };
+    std::iter::once("CI")
+        .chain(PROVIDER_VARIABLES.iter().copied())
+        .find(|name| is_set(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_in(variables: &[(&str, &str)]) -> Option<&'static str> {
+        detect_with(|name| {
+            variables
+                .iter()
+                .find(|(variable, _)| *variable == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn detects_the_generic_variable_first() {
+        assert_eq!(
+            detect_in(&[("GITLAB_CI", "true"), ("CI", "true")]),
+            Some("CI")
+        );
+        assert_eq!(
+            detect_in(&[("JENKINS_URL", "http://ci")]),
+            Some("JENKINS_URL")
+        );
+    }
+
+    #[test]
+    fn ignores_unset_and_false_values() {
+        assert_eq!(detect_in(&[]), None);
+        assert_eq!(
+            detect_in(&[("CI", "false"), ("TRAVIS", "0"), ("TF_BUILD", "")]),
+            None
+        );
+        assert_eq!(
+            detect_in(&[("CI", "FALSE"), ("GITHUB_ACTIONS", "true")]),
+            Some("GITHUB_ACTIONS")
+        );
+    }
+}
@@ -0,0 +1,149 @@
+// Generates the log times of the messages. By default the wall clock is only read once at
+// startup and the monotonic clock measures the time since, so an NTP correction during the run
+// can't make the timestamps go backwards. An offset can shift every timestamp to line it up with
+// an external clock.
+
+use std::sync::LazyLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Reads a clock in nanoseconds
+type TimeSource = fn() -> u64;
+
+pub struct LogClock {
+    /// Nanoseconds since the Unix epoch
+    wall_clock: TimeSource,
+    /// Nanoseconds since an arbitrary point, never going backwards
+    monotonic: TimeSource,
+    /// Monotonic and wall-clock times captured together at startup, `None` when the wall clock
+    /// is read for every message
+    base: Option<(u64, u64)>,
+    /// Added to every timestamp, in nanoseconds
+    offset_ns: i64,
+}
+
+impl LogClock {
+    pub fn monotonic() -> Self {
+        Self::monotonic_from(wall_clock_nanos, monotonic_nanos)
+    }
+
+    pub fn wall_clock() -> Self {
+        Self::wall_clock_from(wall_clock_nanos)
+    }
+
+    /// Like `monotonic`, reading the times from the given sources
+    fn monotonic_from(wall_clock: TimeSource, monotonic: TimeSource) -> Self {
+        Self {
+            wall_clock,
+            monotonic,
+            base: Some((monotonic(), wall_clock())),
+            offset_ns: 0,
+        }
+    }
+
+    /// Like `wall_clock`, reading the time from the given source
+    fn wall_clock_from(wall_clock: TimeSource) -> Self {
+        Self {
+            wall_clock,
+            monotonic: monotonic_nanos,
+            base: None,
+            offset_ns: 0,
+        }
+    }
+
+    /// Shifts every timestamp by `offset_ns`
+    pub fn with_offset(self, offset_ns: i64) -> Self {
+        Self { offset_ns, ..self }
+    }
+
+    /// Current time in nanoseconds since the Unix epoch, shifted by the offset. A shift before
+    /// the epoch is clamped to it, `offset_is_valid` rejects such offsets upfront.
+    pub fn now_nanos(&self) -> u64 {
+        let now = match self.base {
+            Some((monotonic, wall_clock)) => wall_clock + ((self.monotonic)() - monotonic),
+            None => (self.wall_clock)(),
+        };
+        now.saturating_add_signed(self.offset_ns)
+    }
+}
+
+/// Whether shifting the current time by `offset_ns` still gives a time after the Unix epoch
+pub fn offset_is_valid(offset_ns: i64) -> bool {
+    wall_clock_nanos().checked_add_signed(offset_ns).is_some()
+}
+
+fn wall_clock_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+fn monotonic_nanos() -> u64 {
+    static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+    START.elapsed().as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+    /// Tolerance between two clocks read one after the other
+    const SLACK_NS: u64 = 1_000_000_000;
+
+    #[test]
+    fn monotonic_clock_follows_the_wall_clock() {
+        let clock = LogClock::monotonic();
+        let first = clock.now_nanos();
+        let second = clock.now_nanos();
+        assert!(second >= first);
+        assert!(wall_clock_nanos().abs_diff(second) < SLACK_NS);
+        assert!(wall_clock_nanos().abs_diff(LogClock::wall_clock().now_nanos()) < SLACK_NS);
+    }
+
+    #[test]
+    fn offset_shifts_every_timestamp() {
+        let clock = LogClock::wall_clock().with_offset(-3_600_000_000_000);
+        let expected = wall_clock_nanos() - 3_600_000_000_000;
+        assert!(clock.now_nanos().abs_diff(expected) < SLACK_NS);
+        let clock = LogClock::monotonic().with_offset(3_600_000_000_000);
+        let expected = wall_clock_nanos() + 3_600_000_000_000;
+        assert!(clock.now_nanos().abs_diff(expected) < SLACK_NS);
+    }
+
+    #[test]
+    fn offset_before_the_epoch_is_invalid_and_clamped() {
+        assert!(offset_is_valid(-1_000_000_000));
+        assert!(!offset_is_valid(i64::MIN));
+        assert_eq!(LogClock::wall_clock().with_offset(i64::MIN).now_nanos(), 0);
+    }
+
+    static FAKE_WALL_CLOCK: AtomicU64 = AtomicU64::new(0);
+    static FAKE_MONOTONIC: AtomicU64 = AtomicU64::new(0);
+
+    fn fake_wall_clock() -> u64 {
+        FAKE_WALL_CLOCK.load(Ordering::SeqCst)
+    }
+
+    fn fake_monotonic() -> u64 {
+        FAKE_MONOTONIC.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn monotonic_clock_ignores_a_backward_wall_clock_jump() {
+        FAKE_WALL_CLOCK.store(10_000 * NANOS_PER_SECOND, Ordering::SeqCst);
+        FAKE_MONOTONIC.store(5 * NANOS_PER_SECOND, Ordering::SeqCst);
+        let monotonic = LogClock::monotonic_from(fake_wall_clock, fake_monotonic);
+        let wall_clock = LogClock::wall_clock_from(fake_wall_clock);
+        assert_eq!(monotonic.now_nanos(), 10_000 * NANOS_PER_SECOND);
+
+        // An NTP correction sets the wall clock an hour back while one second passes
+        FAKE_WALL_CLOCK.store((10_001 - 3_600) * NANOS_PER_SECOND, Ordering::SeqCst);
+        FAKE_MONOTONIC.store(6 * NANOS_PER_SECOND, Ordering::SeqCst);
+        assert_eq!(monotonic.now_nanos(), 10_001 * NANOS_PER_SECOND);
+        assert_eq!(wall_clock.now_nanos(), (10_001 - 3_600) * NANOS_PER_SECOND);
+    }
+}
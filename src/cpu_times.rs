@@ -0,0 +1,177 @@
+// Reads the cumulative cpu times of `/proc/stat`, from which the steal time, the time a virtual
+// cpu waited for the hypervisor to run it, and the raw per-core idle and busy counters are
+// derived. The steal time is only counted by guest kernels, so it stays at zero on bare metal and
+// wherever `/proc/stat` can't be read, where the raw counters are `null` too.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::kernel_fs;
+
+/// Cumulative cpu time since boot, in clock ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuTimes {
+    pub total: u64,
+    pub steal: u64,
+}
+
+impl CpuTimes {
+    /// Share of the cpu time since `previous` that was stolen, in percent
+    pub fn steal_percent_since(&self, previous: &CpuTimes) -> f32 {
+        let total = self.total.saturating_sub(previous.total);
+        if total == 0 {
+            return 0.0;
+        }
+        self.steal.saturating_sub(previous.steal) as f32 * 100.0 / total as f32
+    }
+}
+
+/// Cumulative idle and busy time of a core since boot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoreTimes {
+    pub idle_ms: u64,
+    pub busy_ms: u64,
+}
+
+pub fn read_stat() -> Option<String> {
+    read_stat_in(kernel_fs::proc_root()?)
+}
+
+/// Reads `stat` in the proc directory at `proc_root`
+pub fn read_stat_in(proc_root: &Path) -> Option<String> {
+    std::fs::read_to_string(proc_root.join("stat")).ok()
+}
+
+/// Length of a clock tick in milliseconds
+#[cfg(unix)]
+fn tick_ms() -> f64 {
+    // SAFETY: sysconf has no preconditions
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    1000.0
+        / if ticks_per_second > 0 {
+            ticks_per_second as f64
+        } else {
+            100.0
+        }
+}
+
+#[cfg(not(unix))]
+fn tick_ms() -> f64 {
+    10.0
+}
+
+/// Parses the aggregate `cpu` line of `/proc/stat`
+pub fn parse_stat(contents: &str) -> Option<CpuTimes> {
+    let columns = contents
+        .lines()
+        .find_map(|line| columns(line).filter(|(name, _)| *name == "cpu"))?
+        .1;
+    Some(CpuTimes {
+        total: columns.iter().sum(),
+        // Kernels before 2.6.11 have no steal column
+        steal: columns.get(7).copied().unwrap_or_default(),
+    })
+}
+
+/// Parses the per-core `cpu<N>` lines of `/proc/stat`, by core name. The idle time includes the
+/// time spent waiting for io.
+pub fn parse_core_times(contents: &str) -> HashMap<String, CoreTimes> {
+    let tick_ms = tick_ms();
+    contents
+        .lines()
+        .filter_map(columns)
+        .filter(|(name, _)| name.len() > 3)
+        .map(|(name, columns)| {
+            let idle = columns[3] + columns.get(4).copied().unwrap_or_default();
+            let total: u64 = columns.iter().sum();
+            (
+                name.to_string(),
+                CoreTimes {
+                    idle_ms: (idle as f64 * tick_ms) as u64,
+                    busy_ms: (total.saturating_sub(idle) as f64 * tick_ms) as u64,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Splits a `cpu` line into its name and time columns. The guest columns are left out since the
+/// kernel already counts them in the user columns.
+fn columns(line: &str) -> Option<(&str, Vec<u64>)> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next().filter(|name| name.starts_with("cpu"))?;
+    let columns: Vec<u64> = fields
+        .take(8)
+        .map(|column| column.parse().ok())
+        .collect::<Option<_>>()?;
+    (columns.len() >= 4).then_some((name, columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    const STAT: &str = "cpu  100 0 50 800 20 0 5 25 0 0\n\
+                        cpu0 60 0 30 400 10 0 3 15 0 0\n\
+                        cpu1 40 0 20 400 10 0 2 10 0 0\n\
+                        intr 12345 0 0\n\
+                        ctxt 67890\n";
+
+    #[test]
+    fn parses_the_aggregate_line() {
+        // The guest columns are not counted twice
+        assert_eq!(
+            parse_stat(STAT),
+            Some(CpuTimes {
+                total: 1000,
+                steal: 25
+            })
+        );
+    }
+
+    #[test]
+    fn steal_is_optional_on_old_kernels() {
+        assert_eq!(
+            parse_stat("cpu 100 0 50 800\n"),
+            Some(CpuTimes {
+                total: 950,
+                steal: 0
+            })
+        );
+        assert_eq!(parse_stat("intr 12345\n"), None);
+    }
+
+    #[test]
+    fn steal_percent_between_samples() {
+        let previous = CpuTimes {
+            total: 1000,
+            steal: 25,
+        };
+        let current = CpuTimes {
+            total: 1200,
+            steal: 35,
+        };
+        assert_eq!(current.steal_percent_since(&previous), 5.0);
+        assert_eq!(previous.steal_percent_since(&previous), 0.0);
+    }
+
+    #[test]
+    fn parses_the_core_lines() {
+        let cores = parse_core_times(STAT);
+        assert_eq!(cores.len(), 2);
+        let tick_ms = tick_ms();
+        let cpu0 = cores["cpu0"];
+        // Idle includes the io wait
+        assert_eq!(cpu0.idle_ms, (410.0 * tick_ms) as u64);
+        assert_eq!(cpu0.busy_ms, (108.0 * tick_ms) as u64);
+    }
+
+    #[test]
+    fn reads_the_stat_file() {
+        let root = Fixture::new("cpu-times");
+        root.write("stat", STAT);
+        assert_eq!(read_stat_in(&root).as_deref(), Some(STAT));
+        assert_eq!(read_stat_in(&Fixture::new("cpu-times-missing")), None);
+    }
+}
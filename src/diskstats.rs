@@ -0,0 +1,123 @@
+// Reads the per-device io counters of `/proc/diskstats`, from which the average read and write
+// latency between two samples is derived. The disks are matched to their device by name, so a
+// disk whose device isn't listed, like a network filesystem, or every disk where the file can't be
+// read, gets no latency.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::kernel_fs;
+
+/// Completed operations and the milliseconds spent on them, since boot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IoCounters {
+    pub reads: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub write_ms: u64,
+}
+
+impl IoCounters {
+    /// Average read and write latency in milliseconds of the operations completed since
+    /// `previous`, `None` for a direction without any completed operation
+    pub fn latency_since(&self, previous: &IoCounters) -> (Option<f32>, Option<f32>) {
+        let latency = |ops: u64, previous_ops: u64, ms: u64, previous_ms: u64| {
+            let ops = ops.checked_sub(previous_ops).filter(|&ops| ops > 0)?;
+            Some(ms.saturating_sub(previous_ms) as f32 / ops as f32)
+        };
+        (
+            latency(self.reads, previous.reads, self.read_ms, previous.read_ms),
+            latency(
+                self.writes,
+                previous.writes,
+                self.write_ms,
+                previous.write_ms,
+            ),
+        )
+    }
+}
+
+pub fn read_diskstats() -> HashMap<String, IoCounters> {
+    kernel_fs::proc_root()
+        .map(read_diskstats_in)
+        .unwrap_or_default()
+}
+
+/// Reads the counters of every device from `diskstats` in the proc directory at `proc_root`
+pub fn read_diskstats_in(proc_root: &Path) -> HashMap<String, IoCounters> {
+    std::fs::read_to_string(proc_root.join("diskstats"))
+        .map(|contents| contents.lines().filter_map(parse_line).collect())
+        .unwrap_or_default()
+}
+
+/// Parses a `/proc/diskstats` line into the device name and its counters
+pub fn parse_line(line: &str) -> Option<(String, IoCounters)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let counter = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    Some((
+        fields.get(2)?.to_string(),
+        IoCounters {
+            reads: counter(3)?,
+            read_ms: counter(6)?,
+            writes: counter(7)?,
+            write_ms: counter(10)?,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    const DISKSTATS: &str = concat!(
+        "   8       0 sda 1000 10 8000 500 2000 20 16000 3000 0 2500 3500\n",
+        "   8       1 sda1 900 5 7000 450 1800 10 14000 2700 0 2200 3150\n",
+        "   7       0 loop0 0 0 0 0 0 0 0 0 0 0 0\n",
+    );
+
+    #[test]
+    fn parses_a_device_line() {
+        let (name, counters) = parse_line(DISKSTATS.lines().next().unwrap()).unwrap();
+        assert_eq!(name, "sda");
+        assert_eq!(
+            counters,
+            IoCounters {
+                reads: 1000,
+                read_ms: 500,
+                writes: 2000,
+                write_ms: 3000
+            }
+        );
+        assert_eq!(parse_line("   8       0 sda 1000"), None);
+    }
+
+    #[test]
+    fn latency_of_the_completed_operations() {
+        let previous = IoCounters {
+            reads: 1000,
+            read_ms: 500,
+            writes: 2000,
+            write_ms: 3000,
+        };
+        let current = IoCounters {
+            reads: 1010,
+            read_ms: 550,
+            writes: 2000,
+            write_ms: 3000,
+        };
+        assert_eq!(current.latency_since(&previous), (Some(5.0), None));
+        // The counters went backwards, e.g. the device was replaced
+        assert_eq!(previous.latency_since(&current), (None, None));
+    }
+
+    #[test]
+    fn reads_every_device() {
+        let root = Fixture::new("diskstats");
+        root.write("diskstats", DISKSTATS);
+        let devices = read_diskstats_in(&root);
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices["sda1"].writes, 1800);
+        assert!(read_diskstats_in(&Fixture::new("diskstats-missing")).is_empty());
+    }
+}
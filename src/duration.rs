@@ -0,0 +1,92 @@
+// Parses human durations like `30s`, `5m`, `2h`, `1d`, `1h30m` or `90 minutes` into whole seconds,
+// for the flags that take a duration. The units are parsed by jiff's friendly duration format, with
+// days of 24 hours. A plain number is still read as seconds, so the numeric form keeps working.
+//
+// jiff is used instead of humantime: it is already a dependency for the time zones, and its
+// friendly format accepts the same units, so a second crate would only add to the build.
+
+use jiff::{Span, SpanRelativeTo};
+
+/// Parses a positive number of seconds or a human duration like `5m` or `1h30m`, rejecting zero
+/// since none of the durations makes sense empty, e.g. `--interval 0` would spin the loop
+pub fn parse_seconds(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let seconds = match value.parse::<u64>() {
+        Ok(seconds) => seconds,
+        Err(_) => parse_span(value)?,
+    };
+    if seconds == 0 {
+        return Err("duration must be at least 1s".to_string());
+    }
+    Ok(seconds)
+}
+
+fn parse_span(value: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "invalid duration `{value}`, expected seconds or a duration like 30s, 5m, 2h, 1d or \
+             1h30m"
+        )
+    };
+    let duration = value
+        .parse::<Span>()
+        .map_err(|_| invalid())?
+        .to_duration(SpanRelativeTo::days_are_24_hours())
+        .map_err(|_| format!("duration `{value}` must not use months or years"))?;
+    if duration.is_negative() {
+        return Err(format!("duration `{value}` must not be negative"));
+    }
+    if duration.subsec_nanos() != 0 {
+        return Err(format!(
+            "duration `{value}` must be a whole number of seconds"
+        ));
+    }
+    Ok(duration.as_secs() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_seconds() {
+        assert_eq!(parse_seconds("30"), Ok(30));
+        assert_eq!(parse_seconds(" 5 "), Ok(5));
+    }
+
+    #[test]
+    fn parses_human_durations() {
+        assert_eq!(parse_seconds("30s"), Ok(30));
+        assert_eq!(parse_seconds("5m"), Ok(300));
+        assert_eq!(parse_seconds("2h"), Ok(7200));
+        assert_eq!(parse_seconds("1d"), Ok(86_400));
+        assert_eq!(parse_seconds("1h30m"), Ok(5400));
+        assert_eq!(parse_seconds("90 minutes"), Ok(5400));
+    }
+
+    #[test]
+    fn rejects_durations_without_a_whole_positive_number_of_seconds() {
+        assert!(parse_seconds("0").is_err());
+        assert!(parse_seconds("0s").is_err());
+        assert!(
+            parse_seconds("-5m")
+                .unwrap_err()
+                .ends_with("must not be negative")
+        );
+        assert!(
+            parse_seconds("1.5s")
+                .unwrap_err()
+                .ends_with("whole number of seconds")
+        );
+        assert!(
+            parse_seconds("1 month")
+                .unwrap_err()
+                .ends_with("months or years")
+        );
+        assert!(
+            parse_seconds("soon")
+                .unwrap_err()
+                .starts_with("invalid duration `soon`")
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// Reads the entropy available to the kernel random number generator from
+// `/proc/sys/kernel/random/entropy_avail`. Since Linux 5.18 the kernel always reports a full pool
+// of 256 bits, so the value mostly tells something on older kernels. `entropy_available` is
+// `null` on /system where the file can't be read.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+
+pub fn read_entropy() -> Option<u32> {
+    read_entropy_in(kernel_fs::proc_root()?)
+}
+
+/// Reads the available entropy in bits from the proc directory at `proc_root`
+pub fn read_entropy_in(proc_root: &Path) -> Option<u32> {
+    std::fs::read_to_string(proc_root.join("sys/kernel/random/entropy_avail"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn reads_the_available_entropy() {
+        let root = Fixture::new("entropy");
+        root.write("sys/kernel/random/entropy_avail", "256\n");
+        assert_eq!(read_entropy_in(&root), Some(256));
+    }
+
+    #[test]
+    fn unreadable_entropy_is_none() {
+        let root = Fixture::new("entropy-invalid");
+        assert_eq!(read_entropy_in(&root), None);
+        root.write("sys/kernel/random/entropy_avail", "full\n");
+        assert_eq!(read_entropy_in(&root), None);
+    }
+}
@@ -0,0 +1,73 @@
+// Lists the processes using an NVIDIA GPU and their video memory through `nvidia-smi`, which
+// ships with the driver and queries NVML. Hosts without an NVIDIA driver have no `nvidia-smi`, so
+// `read_gpu_processes` returns `None` there.
+
+/// A process running on a GPU, as reported by the driver
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuProcess {
+    pub pid: u32,
+    pub gpu_uuid: String,
+    pub used_memory_bytes: u64,
+}
+
+pub fn read_gpu_processes() -> Option<Vec<GpuProcess>> {
+    let output = std::process::Command::new("nvidia-smi")
+        .args([
+            "--query-compute-apps=pid,gpu_uuid,used_memory",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_compute_apps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the `pid, gpu_uuid, used_memory` csv lines of `nvidia-smi`, where the memory is in MiB.
+/// The memory is `[N/A]` for processes of other containers, which are skipped.
+pub fn parse_compute_apps(output: &str) -> Vec<GpuProcess> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split(',').map(str::trim);
+            let pid = columns.next()?.parse().ok()?;
+            let gpu_uuid = columns.next()?.to_string();
+            let used_memory_mib: u64 = columns.next()?.parse().ok()?;
+            Some(GpuProcess {
+                pid,
+                gpu_uuid,
+                used_memory_bytes: used_memory_mib * 1024 * 1024,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compute_apps_in_bytes() {
+        let output = "1234, GPU-0a1b, 512\n\
+                      5678, GPU-2c3d, [N/A]\n\
+                      not a line\n\
+                      91, GPU-0a1b, 2\n";
+        assert_eq!(
+            parse_compute_apps(output),
+            [
+                GpuProcess {
+                    pid: 1234,
+                    gpu_uuid: "GPU-0a1b".to_string(),
+                    used_memory_bytes: 512 * 1024 * 1024,
+                },
+                GpuProcess {
+                    pid: 91,
+                    gpu_uuid: "GPU-0a1b".to_string(),
+                    used_memory_bytes: 2 * 1024 * 1024,
+                },
+            ]
+        );
+        assert!(parse_compute_apps("").is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+// Sidecar JSON index of the mcap files produced by successive runs, so tooling can find the file
+// covering a time range without opening each one. Every run merges its files into the index: an
+// entry for a path that was written again is replaced, the others are kept.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::verify::VerifyError;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    pub files: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Path of the file, relative to the index when it is in the same directory
+    pub path: PathBuf,
+    /// Log time of the first message, in nanoseconds since the epoch
+    pub start_time: u64,
+    /// Log time of the last message, in nanoseconds since the epoch
+    pub end_time: u64,
+    pub message_count: u64,
+}
+
+/// Path of the index of the mcap files written at `path`, e.g. `output.index.json` for
+/// `output.mcap`
+pub fn index_path(path: &Path) -> PathBuf {
+    path.with_extension("index.json")
+}
+
+/// Reads the time range and message count of a finished mcap file from its summary
+pub fn read_entry(path: &Path) -> Result<IndexEntry, VerifyError> {
+    let contents = std::fs::read(path).map_err(VerifyError::Io)?;
+    let stats = mcap::Summary::read(&contents)
+        .map_err(VerifyError::Corrupt)?
+        .and_then(|summary| summary.stats)
+        .filter(|stats| stats.message_count > 0)
+        .ok_or(VerifyError::Empty)?;
+    Ok(IndexEntry {
+        path: path.to_path_buf(),
+        start_time: stats.message_start_time,
+        end_time: stats.message_end_time,
+        message_count: stats.message_count,
+    })
+}
+
+impl Index {
+    /// Reads the index at `path`, an index that doesn't exist yet is empty
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(contents) => serde_json::from_slice(&contents).map_err(std::io::Error::other),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Adds the entries of a run, replacing the entries of the same files and keeping the files
+    /// sorted by start time
+    pub fn merge(&mut self, entries: Vec<IndexEntry>) {
+        self.files
+            .retain(|file| !entries.iter().any(|entry| entry.path == file.path));
+        self.files.extend(entries);
+        self.files
+            .sort_by(|a, b| (a.start_time, &a.path).cmp(&(b.start_time, &b.path)));
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Merges the files written by this run into the index at `index_path`, files that can't be
+/// read or have no messages are left out
+pub fn update(index_path: &Path, mcap_paths: &[PathBuf]) -> std::io::Result<()> {
+    let mut entries = Vec::with_capacity(mcap_paths.len());
+    for path in mcap_paths {
+        match read_entry(path) {
+            Ok(mut entry) => {
+                if let Some(relative) = index_path
+                    .parent()
+                    .and_then(|directory| path.strip_prefix(directory).ok())
+                {
+                    entry.path = relative.to_path_buf();
+                }
+                entries.push(entry)
+            }
+            Err(e) => log::warn!("Not indexing {}: {e}", path.display()),
+        }
+    }
+    let mut index = Index::read(index_path)?;
+    index.merge(entries);
+    index.write(index_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+    use crate::output::{Message, OutputSink};
+    use crate::recording::McapOutput;
+
+    /// Records messages with the given log times to `path`, replacing the file
+    fn record(path: &Path, topic: &str, log_times: &[u64]) {
+        let channel = ChannelBuilder::new(topic)
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let mut output = Box::new(McapOutput::single(path, true).unwrap());
+        for &log_time in log_times {
+            output.write(&Message::new(&channel, b"{}", log_time));
+        }
+        output.close().unwrap();
+    }
+
+    #[test]
+    fn index_is_next_to_the_mcap_file() {
+        assert_eq!(
+            index_path(Path::new("runs/output.mcap")),
+            Path::new("runs/output.index.json")
+        );
+    }
+
+    #[test]
+    fn runs_are_merged_by_start_time() {
+        let root = Fixture::new("index-merge");
+        let index = root.join("output.index.json");
+        record(&root.join("second.mcap"), "/index_merge_second", &[30, 40]);
+        update(&index, &[root.join("second.mcap")]).unwrap();
+        record(
+            &root.join("first.mcap"),
+            "/index_merge_first",
+            &[10, 15, 20],
+        );
+        update(&index, &[root.join("first.mcap")]).unwrap();
+
+        let files = Index::read(&index).unwrap().files;
+        let paths: Vec<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("first.mcap"), Path::new("second.mcap")]);
+        assert_eq!((files[0].start_time, files[0].end_time), (10, 20));
+        assert_eq!(files[0].message_count, 3);
+    }
+
+    #[test]
+    fn rewritten_file_replaces_its_entry() {
+        let root = Fixture::new("index-rewrite");
+        let index = root.join("output.index.json");
+        let path = root.join("output.mcap");
+        record(&path, "/index_rewrite", &[10]);
+        update(&index, std::slice::from_ref(&path)).unwrap();
+        record(&path, "/index_rewrite_again", &[50, 60]);
+        update(&index, &[path, root.join("missing.mcap")]).unwrap();
+
+        let files = Index::read(&index).unwrap().files;
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].start_time, files[0].message_count), (50, 2));
+    }
+}
@@ -0,0 +1,57 @@
+// Reads the inode usage of a mounted filesystem through `statvfs`. The call is only available on
+// Unix, so `read_inodes` always returns `None` elsewhere.
+
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy)]
+pub struct InodeUsage {
+    pub total: u64,
+    pub used: u64,
+    pub free: u64,
+}
+
+#[cfg(unix)]
+pub fn read_inodes(mount_point: &Path) -> Option<InodeUsage> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(mount_point.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the path is null-terminated and the stat buffer is only read after a successful call
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+
+    #[allow(clippy::useless_conversion)] // the field types differ between platforms
+    let (total, free) = (u64::from(stat.f_files), u64::from(stat.f_ffree));
+    Some(InodeUsage {
+        total,
+        used: total.saturating_sub(free),
+        free,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn read_inodes(_mount_point: &Path) -> Option<InodeUsage> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_inodes_of_a_mounted_filesystem() {
+        let inodes = read_inodes(&std::env::temp_dir()).unwrap();
+        assert_eq!(inodes.used + inodes.free, inodes.total);
+    }
+
+    #[test]
+    fn missing_mount_point_is_none() {
+        assert!(read_inodes(Path::new("/nonexistent/fox-monitor")).is_none());
+        assert!(read_inodes(Path::new("with\0nul")).is_none());
+    }
+}
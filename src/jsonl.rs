@@ -0,0 +1,70 @@
+// Writes the logged messages to stdout as JSON lines, one message per line, so another process
+// (e.g. a fox-monitor ingesting a remote host with --remote) can consume them. The first line of
+// a topic also carries its schema and metadata, so the reader can register the channel again.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::{Message, OutputSink};
+
+/// A message as written on a line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Line {
+    pub topic: String,
+    pub log_time: u64,
+    pub message: serde_json::Value,
+    /// Only on the first line of a topic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<LineSchema>,
+    /// Only on the first line of a topic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineSchema {
+    pub name: String,
+    pub encoding: String,
+    /// The schema itself, as a string since jsonschema is the only encoding in use
+    pub data: String,
+}
+
+#[derive(Default)]
+pub struct JsonlOutput {
+    /// Topics whose schema was already written
+    described: HashSet<String>,
+}
+
+impl OutputSink for JsonlOutput {
+    fn write(&mut self, message: &Message) {
+        let Some(json) = message.json() else {
+            return;
+        };
+        let channel = message.channel;
+        let first = self.described.insert(channel.topic().to_string());
+        let line = Line {
+            topic: channel.topic().to_string(),
+            log_time: message.log_time,
+            message: json.clone(),
+            schema: channel.schema().filter(|_| first).map(|schema| LineSchema {
+                name: schema.name.clone(),
+                encoding: schema.encoding.clone(),
+                data: String::from_utf8_lossy(&schema.data).into_owned(),
+            }),
+            metadata: first.then(|| channel.metadata().clone()),
+        };
+        let mut json = serde_json::to_vec(&line).expect("Failed to serialize line");
+        json.push(b'\n');
+        if let Err(e) = std::io::stdout().lock().write_all(&json) {
+            log::error!("Failed to write {} to stdout: {e}", channel.topic());
+        }
+    }
+
+    fn close(self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        std::io::stdout().flush()?;
+        Ok(Vec::new())
+    }
+}
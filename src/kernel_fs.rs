@@ -0,0 +1,67 @@
+// Mount points of the kernel pseudo filesystems, `/proc` and `/sys`, that the Linux-only metrics
+// read from. This is the only platform specific part of those readers: they take the mount point
+// as a parameter, find nothing where it is `None`, and read a fixture directory in the tests.
+
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+pub fn proc_root() -> Option<&'static Path> {
+    Some(Path::new("/proc"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn proc_root() -> Option<&'static Path> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+pub fn sys_root() -> Option<&'static Path> {
+    Some(Path::new("/sys"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn sys_root() -> Option<&'static Path> {
+    None
+}
+
+/// Directory of the fixture files of a test, under the system temp directory, removed with them
+/// when dropped
+#[cfg(test)]
+pub struct Fixture(std::path::PathBuf);
+
+#[cfg(test)]
+impl Fixture {
+    /// Creates an empty fixture directory named after the test
+    pub fn new(test: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("fox-monitor-{}-{test}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).expect("Failed to create the fixture directory");
+        Self(root)
+    }
+
+    /// Writes a fixture file at `path` in the directory, creating its parent directories
+    pub fn write(&self, path: &str, contents: &str) -> &Self {
+        let path = self.0.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Failed to create the fixture directory");
+        }
+        std::fs::write(path, contents).expect("Failed to write the fixture file");
+        self
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for Fixture {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
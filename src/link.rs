@@ -0,0 +1,58 @@
+// Reads the negotiated link speed and the MTU of a network interface from
+// `/sys/class/net/<interface>`. sysinfo lists the interfaces but doesn't expose either, so both are
+// `None` where sysfs isn't available.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkInfo {
+    pub speed_mbps: Option<u64>,
+    pub mtu: Option<u32>,
+}
+
+pub fn read_link(interface_name: &str) -> LinkInfo {
+    kernel_fs::sys_root()
+        .map(|sys_root| read_link_in(sys_root, interface_name))
+        .unwrap_or_default()
+}
+
+/// Reads the link of an interface in the sys directory at `sys_root`. Virtual and disconnected
+/// interfaces report a speed of -1 or fail to read it, both are `None`.
+pub fn read_link_in(sys_root: &Path, interface_name: &str) -> LinkInfo {
+    let interface = sys_root.join("class/net").join(interface_name);
+    let read = |file: &str| std::fs::read_to_string(interface.join(file)).ok();
+    LinkInfo {
+        speed_mbps: read("speed").and_then(|speed| speed.trim().parse().ok()),
+        mtu: read("mtu").and_then(|mtu| mtu.trim().parse().ok()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn reads_speed_and_mtu() {
+        let root = Fixture::new("link");
+        root.write("class/net/eth0/speed", "1000\n")
+            .write("class/net/eth0/mtu", "1500\n");
+        let link = read_link_in(&root, "eth0");
+        assert_eq!(link.speed_mbps, Some(1000));
+        assert_eq!(link.mtu, Some(1500));
+    }
+
+    #[test]
+    fn unknown_speed_is_none() {
+        let root = Fixture::new("link-virtual");
+        root.write("class/net/veth0/speed", "-1\n")
+            .write("class/net/veth0/mtu", "65536\n");
+        let link = read_link_in(&root, "veth0");
+        assert_eq!(link.speed_mbps, None);
+        assert_eq!(link.mtu, Some(65536));
+        let missing = read_link_in(&root, "eth9");
+        assert_eq!((missing.speed_mbps, missing.mtu), (None, None));
+    }
+}
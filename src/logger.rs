@@ -1,27 +1,303 @@
 /// Collection of loggers that share a single System instance
-use sysinfo::{Components, Disks, Networks, ProcessesToUpdate, System};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::{Cli, channels::*};
+use foxglove::{Channel, ChannelBuilder, Context, TypedChannel};
+use schemars::JsonSchema;
+use serde::Serialize;
+use sysinfo::{
+    Components, CpuRefreshKind, Disk, DiskRefreshKind, Disks, LoadAvg, MemoryRefreshKind, Networks,
+    Pid, Process, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, RefreshKind, System,
+};
+
+use crate::alerts::{self, Thresholds, Transition};
+use crate::clock::LogClock;
+use crate::cpu_times::{self, CpuTimes};
+use crate::diskstats::{self, IoCounters};
+use crate::output::{Message, Outputs};
+use crate::output_queue::OutputQueue;
+use crate::parameters::RuntimeConfig;
+use crate::payload::{NanPolicy, Sequenced};
+use crate::physical_disks::PhysicalDiskFilter;
+use crate::process_history::ProcessHistory;
+use crate::process_io::IoSample;
+use crate::rfc3339::TimeZone;
+use crate::rollup::Rollup;
+use crate::temperature::TemperatureUnit;
+use crate::topology::{self, CoreTopology};
+use crate::{
+    Cli, cgroup, channels::*, entropy, gpu, inodes, link, payload, power, pressure, process_io,
+    process_tree, procfs, rfc3339, scheduling, services, smart, sockets, throttling,
+};
+
+/// Lists of the items counted on /counts
+struct CountedLists {
+    disks: Disks,
+    networks: Networks,
+    components: Components,
+}
 
 pub struct LoggerCollection {
     system: System,
     cpu_enabled: bool,
+    /// Log the vendor and brand once on the cpu when every core has the same
+    compact_cpu: bool,
+    /// Settings changed at runtime through the websocket parameters
+    runtime_config: Arc<RuntimeConfig>,
+    /// Pid of the monitor when its usage is subtracted from the global cpu usage
+    exclude_self_pid: Option<Pid>,
+    /// Only log this many of the busiest cores
+    max_cores: Option<usize>,
+    /// Skip the cpu frequencies, see `minimal`
+    minimal: bool,
     memory_enabled: bool,
+    /// Parts of the memory info refreshed on each sample
+    memory_refresh: MemoryRefreshKind,
+    /// Read the memory and load averages from /proc when sysinfo reports zeros
+    proc_fallback: bool,
     temperature: Option<Components>,
     disks: Option<Disks>,
+    /// Leaves the pseudo filesystems out of the disks, `None` to log every disk
+    physical_disks: Option<PhysicalDiskFilter>,
     networks: Option<Networks>,
     processes_enabled: bool,
+    /// Whether every process was refreshed with its cpu and memory during this iteration
+    all_processes_refreshed: bool,
+    compact_processes: bool,
+    /// Disk usage of each process at the previous sample and the rates since the one before, by
+    /// pid
+    process_io: HashMap<u32, (IoSample, (f64, f64))>,
+    /// Kept between iterations so the list of processes isn't reallocated on every sample, which
+    /// adds up with tens of thousands of processes
+    process_buffer: Vec<ProcessStats>,
+    /// Only log this process and its descendants
+    follow_pid: Option<u32>,
+    /// History of the processes given with --track-pid, by pid
+    tracked_processes: BTreeMap<u32, ProcessHistory>,
+    /// Divide the process cpu usage by the number of logical cpus
+    process_cpu_normalized: bool,
+    process_sockets: bool,
+    /// Read the priority and nice value of each logged process, one more file per process
+    process_scheduling: bool,
+    min_process_age: Option<u64>,
+    max_process_age: Option<u64>,
+    min_process_memory_kb: Option<u64>,
+    /// Only log the processes when a process started or exited since the previous sample
+    processes_on_change: bool,
+    /// Set when every process is also logged on /processes_full at this slower interval
+    full_snapshot_every: Option<Duration>,
+    last_full_snapshot: Option<Instant>,
+    /// Values accumulated for --summary-interval
+    rollup: Option<Rollup>,
+    /// Pids of the processes at the previous sample, kept when `processes_on_change` is set
+    process_pids: Option<HashSet<u32>>,
     system_enabled: bool,
+    count_only: bool,
+    /// Lists counted on /counts, kept between iterations so only the lists are refreshed
+    counted: Option<CountedLists>,
+    /// The static system facts are logged once on /info instead of on /system
+    info: bool,
+    temp_unit: TemperatureUnit,
+    cgroup_enabled: bool,
+    pressure_enabled: bool,
+    services_enabled: bool,
+    power_enabled: bool,
+    gpu_processes_enabled: bool,
+    blocked_processes_enabled: bool,
+    smart_enabled: bool,
+    /// Pid of the monitor when its own usage is logged
+    self_pid: Option<Pid>,
+    /// Package and core of each logical cpu, empty where it is not exposed
+    core_topology: Vec<CoreTopology>,
+    /// Maximum cpu frequency in MHz, `None` where it is not exposed
+    max_frequency_mhz: Option<u64>,
+    /// Throttling events reported by the kernel at the previous cpu sample
+    throttle_count: Option<u64>,
+    /// Usage alert thresholds in percent, alerts are off when `None`
+    memory_alert: Option<Thresholds>,
+    disk_alert: Option<Thresholds>,
+    /// Thresholds of the disks given with --disk-alert, by mount point
+    disk_alert_overrides: HashMap<PathBuf, Thresholds>,
+    /// Whether each usage alert is currently firing, by alert metric
+    alert_states: HashMap<String, bool>,
+    /// Skip the messages of collections that list no items
+    omit_empty: bool,
+    /// Boot time in seconds since the Unix epoch at the previous iteration
+    boot_time: u64,
+    /// Log the cumulative idle and busy time of each core
+    cpu_raw_counters: bool,
+    /// Cpu times at the previous cpu sample, used to compute the steal time
+    cpu_times: Option<CpuTimes>,
+    /// Temperature of the hottest component at the previous temperature sample
+    max_temperature: Option<f32>,
+    /// Set when deduplication is enabled, forces a log after this much time without one
+    dedupe_keepalive: Option<Duration>,
+    /// Hash of the last logged payload and when it was logged, by topic
+    last_hashes: HashMap<String, (u64, Instant)>,
+    /// Topics that are logged, every topic when empty
+    topics: HashSet<String>,
+    /// Number of samples logged, by topic
+    sample_counts: BTreeMap<String, u64>,
+    /// Number of initial iterations that are collected but not logged
+    skip_first: u64,
+    /// Iterations before this instant are collected but not logged, to let the rates settle
+    warmup_until: Option<Instant>,
+    /// Whether the current iteration is collected but not logged, because of `skip_first` or
+    /// the warmup
+    discarding: bool,
+    /// Number of iterations that were collected but not logged
+    discarded_iterations: u64,
+    /// Number of times `log_all` has been called
+    iterations: u64,
+    /// Number of consecutive panics after which a metric is disabled
+    max_metric_failures: u32,
+    /// Number of times an implausibly empty process list is refreshed again
+    refresh_retries: u32,
+    /// Number of consecutive panics, by metric
+    metric_failures: HashMap<&'static str, u32>,
+    disabled_metrics: HashSet<&'static str>,
+    /// Number of samples after which a metric stops being logged, by metric
+    metric_max_samples: HashMap<&'static str, u64>,
+    /// Number of samples logged, by metric
+    metric_samples: HashMap<&'static str, u64>,
+    /// Number of messages written on any channel, to tell whether a metric logged anything
+    written_messages: u64,
+    /// Number of messages skipped by the deduplication, `omit_empty` or `processes_on_change`
+    skipped_messages: u64,
+    /// Sort the disks by mount point and the networks by interface name
+    sort: bool,
+    /// Report the cumulative disk and network counters relative to the first sample
+    relative_counters: bool,
+    /// Read and written kb totals of the first sample, by mount point
+    disk_baselines: HashMap<String, (u64, u64)>,
+    /// Io counters of the previous disks sample, by device name
+    disk_counters: HashMap<String, IoCounters>,
+    /// Received and transmitted totals of the first sample, by interface name
+    network_baselines: HashMap<String, (u64, u64)>,
+    /// Log the sum of all interfaces instead of each interface
+    network_aggregate: bool,
+    network_include_loopback: bool,
+    clock: LogClock,
+    /// Number of decimal places float fields are rounded to
+    round_digits: Option<u32>,
+    /// Pretty-print the encoded messages
+    json_pretty: bool,
+    /// Time zone of the human readable timestamps
+    time_zone: TimeZone,
+    nan_policy: NanPolicy,
+    /// Number of iterations buffered before they are logged together, batching is off below 2
+    batch_size: u64,
+    /// Buffered messages as channel, encoded payload and log time in nanoseconds
+    pending: Vec<(Arc<Channel>, Vec<u8>, u64)>,
+    status_enabled: bool,
+    interval: Duration,
+    /// Start times of the most recent iterations, used to compute the achieved sampling rate
+    iteration_times: VecDeque<Instant>,
+    /// Set when the messages are written by a separate thread through a bounded queue
+    output_queue: Option<OutputQueue>,
+    /// Outputs given every logged message
+    outputs: Outputs,
+}
+
+/// Number of iterations the achieved sampling rate is averaged over
+const SAMPLING_RATE_WINDOW: usize = 10;
+/// Number of messages the output queue holds before the backpressure policy applies
+const OUTPUT_QUEUE_CAPACITY: usize = 1024;
+/// Delay before refreshing an implausibly empty list again
+const REFRESH_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Why the loggers couldn't be set up
+#[derive(Debug)]
+pub enum MonitorError {
+    /// The cpu metric is enabled but sysinfo found no cpu
+    NoCpus,
+    /// The thread writing the messages for --on-backpressure couldn't be started
+    OutputThread(std::io::Error),
+}
+
+impl fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorError::NoCpus => write!(f, "no cpu was found"),
+            MonitorError::OutputThread(e) => write!(f, "failed to start the output thread: {e}"),
+        }
+    }
+}
+
+/// What an enabled metric found at startup, for --probe
+pub struct ProbeResult {
+    pub metric: &'static str,
+    /// Kind of the counted items, e.g. `sensors`
+    pub items: &'static str,
+    /// Number of items found, or why the metric can't read them
+    pub found: Result<usize, String>,
 }
 
 impl LoggerCollection {
-    pub fn new(args: &Cli) -> Self {
-        let system = System::new_all();
+    pub fn new(args: &Cli, outputs: Outputs) -> Result<Self, MonitorError> {
+        let system = if args.minimal {
+            System::new_with_specifics(RefreshKind::nothing().with_cpu(CpuRefreshKind::nothing()))
+        } else {
+            System::new_all()
+        };
+        if args.cpu && system.cpus().is_empty() {
+            return Err(MonitorError::NoCpus);
+        }
+        let core_topology = topology::read_topology(system.cpus().len());
+        // Before any temperature channel is registered with the unit in its metadata
+        args.temp_unit.select();
+        let topics: HashSet<String> = args.topic.iter().cloned().collect();
+        let produced = produced_topics(args);
+        for topic in &topics {
+            if !produced.contains(&topic.as_str()) {
+                log::warn!("Topic {topic} is not produced by any enabled metric");
+            }
+        }
+
+        let output_queue = args
+            .on_backpressure
+            .map(|policy| {
+                let outputs = outputs.clone();
+                OutputQueue::start(
+                    OUTPUT_QUEUE_CAPACITY,
+                    policy,
+                    move |channel, payload, log_time| {
+                        outputs.write(&Message::new(channel, payload, log_time))
+                    },
+                )
+            })
+            .transpose()
+            .map_err(MonitorError::OutputThread)?;
 
-        Self {
+        Ok(Self {
             system,
             cpu_enabled: args.cpu,
+            compact_cpu: args.compact_cpu,
+            runtime_config: Arc::default(),
+            exclude_self_pid: if args.exclude_self {
+                sysinfo::get_current_pid()
+                    .inspect_err(|e| log::warn!("Not excluding the monitor's cpu usage: {e}"))
+                    .ok()
+            } else {
+                None
+            },
+            max_cores: args.max_cores,
+            minimal: args.minimal,
             memory_enabled: args.memory,
+            proc_fallback: args.proc_fallback,
+            memory_refresh: if args.memory_no_swap || (args.minimal && !args.swap_only) {
+                MemoryRefreshKind::nothing().with_ram()
+            } else if args.swap_only {
+                MemoryRefreshKind::nothing().with_swap()
+            } else {
+                MemoryRefreshKind::everything()
+            },
             temperature: if args.temperature {
                 Some(Components::new_with_refreshed_list())
             } else {
@@ -32,44 +308,523 @@ impl LoggerCollection {
             } else {
                 None
             },
+            physical_disks: args
+                .physical_disks_only
+                .then(|| PhysicalDiskFilter::new(args.exclude_fs.clone())),
             networks: if args.networks {
                 Some(Networks::new_with_refreshed_list())
             } else {
                 None
             },
             processes_enabled: args.processes,
+            all_processes_refreshed: false,
+            compact_processes: args.compact_processes,
+            process_io: HashMap::new(),
+            process_buffer: Vec::new(),
+            follow_pid: args.follow_pid,
+            tracked_processes: args
+                .track_pid
+                .iter()
+                .map(|&pid| (pid, ProcessHistory::new(args.track_history)))
+                .collect(),
+            process_cpu_normalized: args.process_cpu_normalized,
+            process_sockets: args.process_sockets,
+            process_scheduling: args.process_scheduling,
+            min_process_age: args.min_process_age,
+            max_process_age: args.max_process_age,
+            min_process_memory_kb: args.min_process_memory_kb,
+            processes_on_change: args.processes_on_change,
+            process_pids: None,
+            full_snapshot_every: args.full_snapshot_every.map(Duration::from_secs),
+            last_full_snapshot: None,
+            rollup: args
+                .summary_interval
+                .map(|seconds| Rollup::new(Duration::from_secs(seconds))),
             system_enabled: args.system,
+            count_only: args.count_only,
+            counted: args.count_only.then(|| CountedLists {
+                disks: Disks::new_with_refreshed_list_specifics(DiskRefreshKind::nothing()),
+                networks: Networks::new_with_refreshed_list(),
+                components: Components::new_with_refreshed_list(),
+            }),
+            info: args.info,
+            temp_unit: args.temp_unit,
+            cgroup_enabled: args.cgroup,
+            pressure_enabled: args.pressure,
+            services_enabled: args.services,
+            power_enabled: args.power,
+            gpu_processes_enabled: args.gpu_processes,
+            blocked_processes_enabled: args.blocked_processes,
+            smart_enabled: args.smart,
+            self_pid: if args.self_monitor {
+                sysinfo::get_current_pid()
+                    .inspect_err(|e| log::warn!("Disabling self monitoring: {e}"))
+                    .ok()
+            } else {
+                None
+            },
+            core_topology,
+            max_frequency_mhz: throttling::max_frequency_mhz(),
+            throttle_count: throttling::throttle_count(),
+            memory_alert: Thresholds::new(args.mem_alert_high, args.mem_alert_low),
+            disk_alert: Thresholds::new(args.disk_alert_high, args.disk_alert_low),
+            disk_alert_overrides: args
+                .disk_alert
+                .iter()
+                .filter_map(|(mount_point, high)| {
+                    Some((
+                        mount_point.clone(),
+                        Thresholds::new(Some(*high), args.disk_alert_low)?,
+                    ))
+                })
+                .collect(),
+            alert_states: HashMap::new(),
+            omit_empty: args.omit_empty,
+            boot_time: System::boot_time(),
+            cpu_raw_counters: args.cpu_raw_counters,
+            cpu_times: cpu_times::read_stat()
+                .as_deref()
+                .and_then(cpu_times::parse_stat),
+            max_temperature: None,
+            dedupe_keepalive: args
+                .dedupe
+                .then(|| Duration::from_secs(args.dedupe_keepalive)),
+            last_hashes: HashMap::new(),
+            topics,
+            sample_counts: BTreeMap::new(),
+            skip_first: args.skip_first,
+            warmup_until: args
+                .warmup_seconds
+                .map(|seconds| Instant::now() + Duration::from_secs(seconds)),
+            discarding: false,
+            discarded_iterations: 0,
+            iterations: 0,
+            max_metric_failures: args.max_metric_failures,
+            refresh_retries: args.refresh_retries,
+            metric_failures: HashMap::new(),
+            disabled_metrics: HashSet::new(),
+            metric_max_samples: [
+                ("processes", args.processes_max_samples),
+                ("system", args.system_max_samples),
+                ("services", args.services_max_samples),
+            ]
+            .into_iter()
+            .filter_map(|(metric, max_samples)| Some((metric, max_samples?)))
+            .collect(),
+            metric_samples: HashMap::new(),
+            written_messages: 0,
+            skipped_messages: 0,
+            sort: !args.no_sort,
+            relative_counters: args.relative_counters,
+            disk_baselines: HashMap::new(),
+            disk_counters: HashMap::new(),
+            network_baselines: HashMap::new(),
+            network_aggregate: args.network_aggregate,
+            network_include_loopback: args.network_include_loopback,
+            clock: if args.wall_clock_timestamps {
+                LogClock::wall_clock()
+            } else {
+                LogClock::monotonic()
+            }
+            .with_offset(args.timestamp_offset_ns),
+            round_digits: args.round,
+            json_pretty: args.json_pretty,
+            time_zone: args.timezone.clone(),
+            nan_policy: args.nan_policy,
+            batch_size: args.batch,
+            pending: Vec::new(),
+            status_enabled: args.status,
+            interval: Duration::from_secs(args.interval),
+            iteration_times: VecDeque::with_capacity(SAMPLING_RATE_WINDOW),
+            output_queue,
+            outputs,
+        })
+    }
+
+    /// Counts the items each enabled metric finds, reading the external sources once
+    pub fn probe(&self) -> Vec<ProbeResult> {
+        let mut results = Vec::new();
+        let mut add = |metric, items, found| {
+            results.push(ProbeResult {
+                metric,
+                items,
+                found,
+            })
+        };
+        if self.cpu_enabled {
+            add("cpu", "cpus", Ok(self.system.cpus().len()));
+        }
+        if let Some(components) = &self.temperature {
+            add("temperature", "sensors", Ok(components.len()));
+        }
+        if let Some(disks) = &self.disks {
+            let included = disks
+                .iter()
+                .filter(|d| {
+                    self.physical_disks
+                        .as_ref()
+                        .is_none_or(|filter| filter.includes(d.file_system(), d.name()))
+                })
+                .count();
+            add("disks", "disks", Ok(included));
+        }
+        if let Some(networks) = &self.networks {
+            add("networks", "interfaces", Ok(networks.len()));
+        }
+        if self.processes_enabled {
+            add("processes", "processes", Ok(self.system.processes().len()));
+        }
+        if self.blocked_processes_enabled {
+            let blocked = self
+                .system
+                .processes()
+                .values()
+                .filter(|process| process.status() == ProcessStatus::UninterruptibleDiskSleep)
+                .count();
+            add("blocked_processes", "processes", Ok(blocked));
+        }
+        if self.cgroup_enabled {
+            let found = cgroup::read_limits().map(|_| 1).ok_or("no cgroup found");
+            add("cgroup", "cgroups", found.map_err(str::to_string));
+        }
+        if self.pressure_enabled {
+            let found = pressure::read_pressure()
+                .map(|_| 1)
+                .ok_or("pressure stall information is not available");
+            add("pressure", "sources", found.map_err(str::to_string));
+        }
+        if self.services_enabled {
+            let found = services::list_services()
+                .map(|services| services.len())
+                .ok_or("services can't be listed on this platform");
+            add("services", "services", found.map_err(str::to_string));
         }
+        if self.gpu_processes_enabled {
+            let found = gpu::read_gpu_processes()
+                .map(|processes| processes.len())
+                .ok_or("nvidia-smi is not available");
+            add("gpu_processes", "processes", found.map_err(str::to_string));
+        }
+        if self.smart_enabled {
+            let found = match smart::read_smart() {
+                Ok(Some(devices)) => Ok(devices.len()),
+                Ok(None) => Err("smartctl is not available".to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            add("smart", "devices", found);
+        }
+        if self.power_enabled {
+            let found = match power::read_power() {
+                Ok(Some(_)) => Ok(1),
+                Ok(None) => Err("power can't be read on this platform".to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            add("power", "sources", found);
+        }
+        results
     }
 
     pub fn log_all(&mut self) {
+        self.iterations += 1;
+        let warming_up = self
+            .warmup_until
+            .is_some_and(|warmup_until| Instant::now() < warmup_until);
+        if !warming_up && self.warmup_until.take().is_some() {
+            log::info!("Warmup done, publishing samples");
+        }
+        self.discarding = self.iterations <= self.skip_first || warming_up;
+        if self.discarding {
+            self.discarded_iterations += 1;
+        }
+        if self.iteration_times.len() == SAMPLING_RATE_WINDOW {
+            self.iteration_times.pop_front();
+        }
+        self.iteration_times.push_back(Instant::now());
+
+        self.refresh_processes();
         if self.cpu_enabled {
-            self.log_cpu();
+            self.guarded("cpu", Self::log_cpu);
         }
         if self.memory_enabled {
-            self.log_memory();
+            self.guarded("memory", Self::log_memory);
         }
         if let Some(components) = self.temperature.take() {
-            self.log_temperature(components);
+            self.guarded("temperature", |s| s.log_temperature(components));
         }
         if let Some(disks) = self.disks.take() {
-            self.log_disks(disks);
+            self.guarded("disks", |s| s.log_disks(disks));
         }
         if let Some(networks) = self.networks.take() {
-            self.log_networks(networks);
+            self.guarded("networks", |s| s.log_networks(networks));
         }
         if self.processes_enabled {
-            self.log_processes();
+            self.guarded("processes", Self::log_processes);
+        }
+        if self.blocked_processes_enabled {
+            self.guarded("blocked_processes", Self::log_blocked_processes);
         }
         if self.system_enabled {
-            self.log_system();
+            self.guarded("system", Self::log_system);
+        }
+        if self.count_only {
+            self.guarded("counts", Self::log_counts);
+        }
+        if self.cgroup_enabled {
+            self.guarded("cgroup", Self::log_cgroup);
+        }
+        if self.pressure_enabled {
+            self.guarded("pressure", Self::log_pressure);
+        }
+        if self.services_enabled {
+            self.guarded("services", Self::log_services);
+        }
+        if self.power_enabled {
+            self.guarded("power", Self::log_power);
+        }
+        if self.gpu_processes_enabled {
+            self.guarded("gpu_processes", Self::log_gpu_processes);
+        }
+        if self.smart_enabled {
+            self.guarded("smart", Self::log_smart);
+        }
+        if self.self_pid.is_some() {
+            self.guarded("self", Self::log_self);
+        }
+        if !self.tracked_processes.is_empty() {
+            self.guarded("tracked_processes", Self::log_tracked_processes);
+        }
+        self.check_boot_time(System::boot_time());
+        if self.status_enabled {
+            self.log_status();
+        }
+        if self.rollup.is_some() {
+            self.log_rollup();
+        }
+        if self.batch_size > 1 && self.iterations.is_multiple_of(self.batch_size) {
+            self.flush();
+        }
+        self.outputs.flush();
+    }
+
+    /// Reads the settings that can change at runtime from `config` instead of the defaults
+    pub fn use_runtime_config(&mut self, config: Arc<RuntimeConfig>) {
+        self.runtime_config = config;
+    }
+
+    /// Logs the buffered messages, with the time at which each was collected
+    pub fn flush(&mut self) {
+        for (channel, payload, log_time) in std::mem::take(&mut self.pending) {
+            self.output(channel, payload, log_time);
+        }
+    }
+
+    /// Logs the buffered messages, waits until the queued ones are written and closes the
+    /// outputs, called on shutdown. Returns the files written by the outputs.
+    pub fn close(&mut self) -> std::io::Result<Vec<PathBuf>> {
+        self.flush();
+        if let Some(queue) = &mut self.output_queue {
+            queue.close();
+        }
+        self.outputs.close()
+    }
+
+    /// Writes an encoded message to the outputs, through the output queue if there is one
+    fn output(&self, channel: Arc<Channel>, payload: Vec<u8>, log_time: u64) {
+        match &self.output_queue {
+            Some(queue) => queue.push(channel, payload, log_time),
+            None => self
+                .outputs
+                .write(&Message::new(&channel, &payload, log_time)),
+        }
+    }
+
+    /// Runs the logger for a metric, catching panics so one failing metric doesn't stop the
+    /// others. The metric is disabled after `max_metric_failures` consecutive panics. Metrics
+    /// that own their sysinfo source (temperature, disks, networks) lose it on a panic, so they
+    /// are effectively disabled after the first one.
+    fn guarded(&mut self, metric: &'static str, logger: impl FnOnce(&mut Self)) {
+        if !self.is_due(metric) {
+            return;
+        }
+
+        let written_before = self.written_messages;
+        if catch_unwind(AssertUnwindSafe(|| logger(self))).is_ok() {
+            self.metric_failures.remove(metric);
+            // A sample only counts when it was written, not when it was skipped as a duplicate,
+            // unchanged, empty or on a disallowed topic
+            if self.written_messages > written_before {
+                *self.metric_samples.entry(metric).or_default() += 1;
+            }
+            return;
+        }
+
+        let failures = self.metric_failures.entry(metric).or_default();
+        *failures += 1;
+        log::error!("Failed to log {metric} ({failures} consecutive failures)");
+        if *failures >= self.max_metric_failures {
+            log::error!("Disabling {metric} after {failures} consecutive failures");
+            self.disabled_metrics.insert(metric);
+        }
+    }
+
+    /// Whether `guarded` runs the metric, which it doesn't once it is disabled or has logged its
+    /// maximum number of samples
+    fn is_due(&self, metric: &str) -> bool {
+        let samples = self.metric_samples.get(metric).copied().unwrap_or_default();
+        !self.disabled_metrics.contains(metric)
+            && self
+                .metric_max_samples
+                .get(metric)
+                .is_none_or(|&max_samples| samples < max_samples)
+    }
+
+    /// Logs the message on the channel if its topic is allowed, registering the channel on
+    /// first use
+    fn log<T: Serialize + JsonSchema>(&mut self, channel: &LazyChannel<T>, msg: &T) {
+        if self.is_topic_allowed(channel.topic()) {
+            self.log_on(channel, msg);
+        }
+    }
+
+    /// Like `log`, but skips the message when it lists no items and `--omit-empty` is set
+    fn log_collection<T: Serialize + JsonSchema + Collection>(
+        &mut self,
+        channel: &LazyChannel<T>,
+        msg: &T,
+    ) {
+        if self.omit_empty && msg.is_empty() {
+            self.skip();
+        } else {
+            self.log(channel, msg);
+        }
+    }
+
+    fn is_topic_allowed(&self, topic: &str) -> bool {
+        self.topics.is_empty() || self.topics.contains(topic)
+    }
+
+    /// Logs the message on the channel, unless deduplication is enabled and the message is
+    /// identical to the previous one and the keepalive has not elapsed yet
+    fn log_on<T: Serialize + JsonSchema>(&mut self, channel: &TypedChannel<T>, msg: &T) {
+        if self.discarding {
+            return;
+        }
+
+        if let Some(keepalive) = self.dedupe_keepalive {
+            // Hashed without the `seq`, which changes on every iteration
+            let payload =
+                payload::encode(msg, self.round_digits, self.nan_policy, self.json_pretty);
+            let mut hasher = DefaultHasher::new();
+            payload.hash(&mut hasher);
+            let hash = hasher.finish();
+
+            let now = Instant::now();
+            if let Some((last_hash, last_logged)) = self.last_hashes.get(channel.topic())
+                && *last_hash == hash
+                && now.duration_since(*last_logged) < keepalive
+            {
+                self.skip();
+                return;
+            }
+            self.last_hashes
+                .insert(channel.topic().to_string(), (hash, now));
+        }
+
+        let payload = payload::encode(
+            &Sequenced {
+                seq: self.iterations,
+                message: msg,
+            },
+            self.round_digits,
+            self.nan_policy,
+            self.json_pretty,
+        );
+        let log_time = self.clock.now_nanos();
+        let registered = Context::get_default()
+            .get_channel_by_topic(channel.topic())
+            .expect("Typed channels are registered in the default context");
+        if self.batch_size > 1 {
+            self.pending.push((registered, payload, log_time));
+        } else {
+            self.output(registered, payload, log_time);
         }
+        *self
+            .sample_counts
+            .entry(channel.topic().to_string())
+            .or_default() += 1;
+        self.written_messages += 1;
+    }
+
+    /// Counts a message skipped on purpose, unless the whole iteration is discarded
+    fn skip(&mut self) {
+        if !self.discarding {
+            self.skipped_messages += 1;
+        }
+    }
+
+    /// Number of `log_all` iterations that were logged, excluding the skipped and warmup ones
+    pub fn logged_iterations(&self) -> u64 {
+        self.iterations - self.discarded_iterations
+    }
+
+    pub fn sample_counts(&self) -> &BTreeMap<String, u64> {
+        &self.sample_counts
     }
 
     pub fn log_cpu(&mut self) {
-        self.system.refresh_cpu_all();
-        CPU.log(&CpuStats {
-            usage: self.system.global_cpu_usage(),
+        if self.minimal {
+            self.system.refresh_cpu_usage();
+        } else {
+            self.system.refresh_cpu_all();
+        }
+        // Startup fails without cpus, but a container can still hide them later on. A sample
+        // without cores would report a misleading zero usage and frequency.
+        if self.system.cpus().is_empty() {
+            log::warn!("Disabling cpu metrics: no cpu is reported anymore");
+            self.cpu_enabled = false;
+            return;
+        }
+        let cpus = self.system.cpus();
+        let frequency_mhz =
+            cpus.iter().map(|c| c.frequency()).sum::<u64>() / cpus.len().max(1) as u64;
+        let throttle_count = throttling::throttle_count();
+        let throttle_events = matches!(
+            (self.throttle_count, throttle_count),
+            (Some(previous), Some(current)) if current > previous
+        );
+        self.throttle_count = throttle_count;
+        let stat = cpu_times::read_stat();
+        let cpu_times = stat.as_deref().and_then(cpu_times::parse_stat);
+        let core_times = match (&stat, self.cpu_raw_counters) {
+            (Some(stat), true) => cpu_times::parse_core_times(stat),
+            _ => HashMap::new(),
+        };
+        let steal_percent = match (cpu_times, self.cpu_times) {
+            (Some(current), Some(previous)) => current.steal_percent_since(&previous),
+            _ => 0.0,
+        };
+        self.cpu_times = cpu_times;
+        let mut usage = self.system.global_cpu_usage();
+        if let Some(process) = self
+            .exclude_self_pid
+            .and_then(|pid| self.system.process(pid))
+        {
+            usage = exclude_usage(usage, process.cpu_usage(), self.system.cpus().len());
+        }
+        if !self.discarding
+            && let Some(rollup) = &mut self.rollup
+        {
+            rollup.cpu_usage.add(f64::from(usage));
+        }
+        let thermal_throttling = throttle_events
+            || throttling::is_throttling(
+                frequency_mhz,
+                self.max_frequency_mhz,
+                self.max_temperature,
+            );
+
+        let mut stats = CpuStats {
+            usage,
             physical_cores: System::physical_core_count(&self.system)
                 .map(|c| c.to_string())
                 .unwrap_or_else(|| "Unknown".to_owned())
@@ -80,30 +835,152 @@ impl LoggerCollection {
                 .system
                 .cpus()
                 .iter()
-                .map(|c| CoreStats {
-                    usage: c.cpu_usage(),
-                    frequency_mhz: c.frequency(),
-                    vendor_id: c.vendor_id().to_string(),
-                    brand: c.brand().to_string(),
+                .enumerate()
+                .map(|(index, c)| {
+                    let topology = self.core_topology.get(index).copied().unwrap_or_default();
+                    CoreStats {
+                        index: index as u32,
+                        usage: c.cpu_usage(),
+                        frequency_mhz: c.frequency(),
+                        vendor_id: Some(c.vendor_id().to_string()),
+                        brand: Some(c.brand().to_string()),
+                        package_id: topology.package_id,
+                        core_id: topology.core_id,
+                        idle_time_ms: core_times.get(c.name()).map(|t| t.idle_ms),
+                        busy_time_ms: core_times.get(c.name()).map(|t| t.busy_ms),
+                    }
                 })
                 .collect(),
-        });
+            cores_truncated: false,
+            vendor_id: None,
+            brand: None,
+            thermal_throttling,
+            steal_percent,
+        };
+        if self.compact_cpu {
+            hoist_cpu_identity(&mut stats);
+        }
+        if let Some(max_cores) = self.max_cores {
+            keep_busiest_cores(&mut stats, max_cores);
+        }
+        if self.runtime_config.cpu_include_cores() {
+            self.log_collection(&CPU, &stats);
+        } else {
+            stats.cores.clear();
+            stats.cores_truncated = true;
+            // The cores are left out on purpose and the sample still holds the global usage, so it
+            // isn't an empty collection
+            self.log(&CPU, &stats);
+        }
     }
 
     pub fn log_memory(&mut self) {
-        self.system.refresh_memory();
-        MEMORY.log(&MemoryStats {
+        self.system.refresh_memory_specifics(self.memory_refresh);
+        let mut stats = MemoryStats {
             total_kb: self.system.total_memory(),
             available_kb: self.system.available_memory(),
+            free_kb: self.system.free_memory(),
             used_kb: self.system.used_memory(),
             swap_total_kb: self.system.total_swap(),
             swap_used_kb: self.system.used_swap(),
-        });
+        };
+        if self.proc_fallback {
+            self.apply_meminfo_fallback(&mut stats);
+        }
+        self.log(&MEMORY, &stats);
+        if !self.discarding
+            && let Some(rollup) = &mut self.rollup
+        {
+            rollup.memory_used_kb.add(stats.used_kb as f64 / 1024.0);
+        }
+        if let Some(thresholds) = self.memory_alert
+            && stats.total_kb > 0
+        {
+            let usage = stats.used_kb as f64 * 100.0 / stats.total_kb as f64;
+            self.check_alert("memory".to_string(), usage, thresholds);
+        }
+    }
+
+    /// Replaces the refreshed memory or swap fields that sysinfo reported as zero with the values
+    /// of /proc/meminfo
+    fn apply_meminfo_fallback(&self, stats: &mut MemoryStats) {
+        let ram_missing = self.memory_refresh.ram() && stats.total_kb == 0;
+        let swap_missing = self.memory_refresh.swap() && stats.swap_total_kb == 0;
+        if !ram_missing && !swap_missing {
+            return;
+        }
+        let Some(meminfo) = procfs::read_meminfo() else {
+            return;
+        };
+        // The fields of /memory are in bytes
+        if ram_missing {
+            log::debug!("sysinfo reported no memory, using /proc/meminfo");
+            stats.total_kb = meminfo.total_kb * 1024;
+            stats.available_kb = meminfo.available_kb * 1024;
+            stats.free_kb = meminfo.free_kb * 1024;
+            stats.used_kb = meminfo.total_kb.saturating_sub(meminfo.available_kb) * 1024;
+        }
+        if swap_missing {
+            stats.swap_total_kb = meminfo.swap_total_kb * 1024;
+            stats.swap_used_kb = meminfo.swap_total_kb.saturating_sub(meminfo.swap_free_kb) * 1024;
+        }
+    }
+
+    /// Load averages from sysinfo, or from /proc/loadavg with --proc-fallback when sysinfo
+    /// reports zeros
+    fn load_average(&self) -> LoadAvg {
+        let load = System::load_average();
+        if self.proc_fallback && load.one == 0.0 && load.five == 0.0 && load.fifteen == 0.0 {
+            return procfs::read_loadavg().unwrap_or(load);
+        }
+        load
+    }
+
+    /// Logs a reboot_detected event when `boot_time`, in seconds since the Unix epoch, moved since
+    /// the previous iteration. The kernel derives it from the wall clock, so adjusting the clock
+    /// also moves it.
+    fn check_boot_time(&mut self, boot_time: u64) {
+        // The boot time is rounded to the second, allow it to flip between neighbouring seconds
+        if boot_time.abs_diff(self.boot_time) <= 1 {
+            return;
+        }
+        let detail = format!("Boot time changed from {} to {boot_time}", self.boot_time);
+        log::warn!("{detail}");
+        self.boot_time = boot_time;
+        self.log(
+            &EVENTS,
+            &EventStats {
+                kind: "reboot_detected".to_string(),
+                detail,
+            },
+        );
+    }
+
+    /// Updates the state of a usage alert, logging an alert when it fires or clears
+    fn check_alert(&mut self, metric: String, usage: f64, thresholds: Thresholds) {
+        let active = self.alert_states.entry(metric.clone()).or_default();
+        let Some(transition) = alerts::update(active, usage, thresholds) else {
+            return;
+        };
+        let threshold_percent = match transition {
+            Transition::Fired => thresholds.high,
+            Transition::Cleared => thresholds.low,
+        };
+        log::warn!("Alert {} on {metric} at {usage:.1}%", transition.as_str());
+        self.log(
+            &ALERTS,
+            &AlertStats {
+                metric,
+                state: transition.as_str().to_string(),
+                value_percent: usage,
+                threshold_percent,
+            },
+        );
     }
 
     pub fn log_temperature(&mut self, mut components: Components) {
         components.refresh(true);
-        COMPONENTS.log(&ComponentsStats {
+        let mut stats = ComponentsStats {
             components: components
                 .iter()
                 .map(|c| ComponentStats {
@@ -111,83 +988,1657 @@ impl LoggerCollection {
                     temperature: c.temperature().unwrap_or(0.0),
                 })
                 .collect(),
-        });
+            unit: self.temp_unit,
+        };
+        let summary = summarize_temperatures(&stats.components, self.temp_unit);
+        // Kept in Celsius for the throttling check
+        self.max_temperature = stats
+            .components
+            .iter()
+            .map(|c| c.temperature)
+            .filter(|temperature| temperature.is_finite())
+            .max_by(f32::total_cmp);
+        for component in &mut stats.components {
+            component.temperature = self.temp_unit.convert(component.temperature);
+        }
+        if let Some(summary) = summary {
+            self.log(&TEMPERATURE_SUMMARY, &summary);
+        }
+        self.log_collection(&COMPONENTS, &stats);
         self.temperature = Some(components);
     }
 
     pub fn log_disks(&mut self, mut disks: Disks) {
         disks.refresh(true);
-        DISKS.log(&DisksStats {
+        let counters = diskstats::read_diskstats();
+        let included = |d: &&Disk| {
+            self.physical_disks
+                .as_ref()
+                .is_none_or(|filter| filter.includes(d.file_system(), d.name()))
+        };
+        let mut stats = DisksStats {
             disks: disks
                 .iter()
-                .map(|d| DiskStats {
-                    name: d.name().to_str().unwrap_or("Unknown").to_string(),
-                    mount_point: d.mount_point().to_str().unwrap_or("Unknown").to_string(),
-                    total_read_kb: d.usage().total_read_bytes / 1024,
-                    total_written_kb: d.usage().total_written_bytes / 1024,
-                    read_kb: d.usage().read_bytes / 1024,
-                    written_kb: d.usage().written_bytes / 1024,
+                .filter(included)
+                .map(|d| {
+                    let inodes = inodes::read_inodes(d.mount_point());
+                    let device = d.name().to_string_lossy();
+                    let device = device.strip_prefix("/dev/").unwrap_or(&device);
+                    let (read_latency_ms, write_latency_ms) =
+                        match (counters.get(device), self.disk_counters.get(device)) {
+                            (Some(current), Some(previous)) => current.latency_since(previous),
+                            _ => (None, None),
+                        };
+                    DiskStats {
+                        name: d.name().to_str().unwrap_or("Unknown").to_string(),
+                        mount_point: d.mount_point().to_str().unwrap_or("Unknown").to_string(),
+                        total_read_kb: d.usage().total_read_bytes / 1024,
+                        total_written_kb: d.usage().total_written_bytes / 1024,
+                        read_kb: d.usage().read_bytes / 1024,
+                        written_kb: d.usage().written_bytes / 1024,
+                        inodes_total: inodes.map(|i| i.total),
+                        inodes_used: inodes.map(|i| i.used),
+                        inodes_free: inodes.map(|i| i.free),
+                        read_latency_ms,
+                        write_latency_ms,
+                    }
                 })
                 .collect(),
-        });
+        };
+        if self.sort {
+            sort_disks(&mut stats.disks);
+        }
+        if self.relative_counters {
+            for disk in &mut stats.disks {
+                (disk.total_read_kb, disk.total_written_kb) = relative_to_baseline(
+                    &mut self.disk_baselines,
+                    &disk.mount_point,
+                    (disk.total_read_kb, disk.total_written_kb),
+                );
+            }
+        }
+        self.disk_counters = counters;
+        if self.disk_alert.is_some() || !self.disk_alert_overrides.is_empty() {
+            let usages: Vec<_> = disks
+                .iter()
+                .filter(|d| d.total_space() > 0 && included(d))
+                .filter_map(|disk| {
+                    let thresholds = self
+                        .disk_alert_overrides
+                        .get(disk.mount_point())
+                        .copied()
+                        .or(self.disk_alert)?;
+                    let used = disk.total_space().saturating_sub(disk.available_space());
+                    let usage = used as f64 * 100.0 / disk.total_space() as f64;
+                    let metric = format!("disk:{}", disk.mount_point().display());
+                    Some((metric, usage, thresholds))
+                })
+                .collect();
+            for (metric, usage, thresholds) in usages {
+                self.check_alert(metric, usage, thresholds);
+            }
+        }
+        self.log_collection(&DISKS, &stats);
         self.disks = Some(disks);
     }
 
     pub fn log_networks(&mut self, mut networks: Networks) {
         networks.refresh(true);
-        NETWORKS.log(&NetworksStats {
+        let mut stats = NetworksStats {
             networks: networks
                 .iter()
-                .map(|(interface_name, data)| NetworkStats {
-                    interface_name: interface_name.to_string(),
-                    mac_address: data.mac_address().to_string(),
-                    received: data.received(),
-                    transmitted: data.transmitted(),
-                    total_received: data.total_received(),
-                    total_transmitted: data.total_transmitted(),
+                .map(|(interface_name, data)| {
+                    let link = link::read_link(interface_name);
+                    NetworkStats {
+                        interface_name: interface_name.to_string(),
+                        mac_address: data.mac_address().to_string(),
+                        received: data.received(),
+                        transmitted: data.transmitted(),
+                        total_received: data.total_received(),
+                        total_transmitted: data.total_transmitted(),
+                        link_speed_mbps: link.speed_mbps,
+                        mtu: link.mtu,
+                    }
                 })
                 .collect(),
-        });
+        };
+        if self.sort {
+            sort_networks(&mut stats.networks);
+        }
+        if self.relative_counters {
+            for network in &mut stats.networks {
+                (network.total_received, network.total_transmitted) = relative_to_baseline(
+                    &mut self.network_baselines,
+                    &network.interface_name,
+                    (network.total_received, network.total_transmitted),
+                );
+            }
+        }
+
+        if self.network_aggregate {
+            let loopback: HashSet<&str> = networks
+                .iter()
+                .filter(|(interface_name, data)| {
+                    interface_name.as_str() == "lo"
+                        || data.ip_networks().iter().any(|ip| ip.addr.is_loopback())
+                })
+                .map(|(interface_name, _)| interface_name.as_str())
+                .collect();
+            let total = sum_networks(stats.networks.iter().filter(|network| {
+                self.network_include_loopback || !loopback.contains(network.interface_name.as_str())
+            }));
+            self.log(&NETWORK_TOTAL, &total);
+        } else {
+            self.log_collection(&NETWORKS, &stats);
+        }
         self.networks = Some(networks);
     }
 
+    /// Refreshes the processes read by the metrics of this iteration, once and before any of them
+    /// runs. Refreshing a process a second time in the same iteration would measure its cpu usage
+    /// over the few microseconds since the first refresh. Every process is refreshed when the
+    /// processes are logged. Otherwise the list is refreshed without details when the blocked
+    /// processes or the counts need it, and the details of the tracked processes and of the
+    /// monitor itself when its usage is read.
+    fn refresh_processes(&mut self) {
+        self.all_processes_refreshed = self.processes_enabled && self.is_due("processes");
+        if self.all_processes_refreshed {
+            self.refresh_all_processes(|system| {
+                system.refresh_processes(ProcessesToUpdate::All, true);
+            });
+            return;
+        }
+        if (self.blocked_processes_enabled && self.is_due("blocked_processes"))
+            || (self.count_only && self.is_due("counts"))
+        {
+            self.refresh_all_processes(|system| {
+                system.refresh_processes_specifics(
+                    ProcessesToUpdate::All,
+                    true,
+                    ProcessRefreshKind::nothing(),
+                );
+            });
+        }
+        let mut pids: Vec<Pid> = self
+            .self_pid
+            .into_iter()
+            .chain(self.exclude_self_pid)
+            .chain(self.tracked_processes.keys().map(|&pid| Pid::from_u32(pid)))
+            .collect();
+        pids.sort();
+        pids.dedup();
+        if !pids.is_empty() {
+            // Removes the tracked processes that exited, so they are reported as not running
+            self.system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&pids),
+                true,
+                ProcessRefreshKind::nothing().with_cpu().with_memory(),
+            );
+        }
+    }
+
+    /// Refreshes every process, retrying after a short delay while the list comes back empty,
+    /// which can't be right since the monitor itself runs. Whatever the last refresh found is
+    /// kept, with a warning.
+    fn refresh_all_processes(&mut self, refresh: impl Fn(&mut System)) {
+        for attempt in 0..=self.refresh_retries {
+            if attempt > 0 {
+                std::thread::sleep(REFRESH_RETRY_DELAY);
+            }
+            refresh(&mut self.system);
+            if !self.system.processes().is_empty() {
+                if attempt > 0 {
+                    log::debug!("Process list refreshed after {attempt} retries");
+                }
+                return;
+            }
+        }
+        log::warn!(
+            "The process list is still empty after {} retries",
+            self.refresh_retries
+        );
+    }
+
+    /// Logs the processes refreshed at the start of the iteration
     pub fn log_processes(&mut self) {
-        self.system.refresh_processes(ProcessesToUpdate::All, true);
-        PROCESSES.log(&ProcessesStats {
-            processes: self
+        self.update_process_io();
+        if self.is_full_snapshot_due() {
+            self.log_full_snapshot();
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let followed = self.follow_pid.map(|root| {
+            process_tree::descendants(
+                root,
+                self.system
+                    .processes()
+                    .iter()
+                    .map(|(pid, process)| (pid.as_u32(), process.parent().map(Pid::as_u32))),
+            )
+        });
+        let followed = followed.as_ref();
+        if self.processes_on_change {
+            let pids: HashSet<u32> = self
                 .system
                 .processes()
                 .iter()
-                .map(|(pid, process)| ProcessStats {
-                    pid: pid.as_u32(),
-                    parent_pid: match process.parent() {
-                        Some(parent) => parent.as_u32().to_string(),
-                        None => "Unknown".to_string(),
-                    },
-                    name: process.name().to_string_lossy().to_string(),
-                    status: process.status().to_string(),
-                    cpu_usage: process.cpu_usage(),
-                    memory_usage_kb: process.memory() / 1024,
-                    start_time_seconds: process.start_time(),
-                    run_time_seconds: process.run_time(),
+                .filter(|(pid, process)| self.is_process_logged(pid, process, followed, now))
+                .map(|(pid, _)| pid.as_u32())
+                .collect();
+            if !self.process_set_changed(pids) {
+                self.skip();
+                return;
+            }
+        }
+
+        if self.compact_processes {
+            let stats = CompactProcessesStats {
+                processes: self
+                    .system
+                    .processes()
+                    .iter()
+                    .filter(|(pid, process)| self.is_process_logged(pid, process, followed, now))
+                    .map(|(pid, process)| CompactProcessStats {
+                        pid: pid.as_u32(),
+                        name: process.name().to_string_lossy().to_string(),
+                        cpu_usage: self.process_cpu_usage(process),
+                        memory_usage_kb: process.memory() / 1024,
+                    })
+                    .collect(),
+            };
+            self.log_collection(&PROCESSES_COMPACT, &stats);
+            return;
+        }
+
+        let mut buffer = std::mem::take(&mut self.process_buffer);
+        buffer.clear();
+        buffer.extend(
+            self.system
+                .processes()
+                .iter()
+                .filter(|(pid, process)| self.is_process_logged(pid, process, followed, now))
+                .map(|(pid, process)| self.process_stats(pid.as_u32(), process)),
+        );
+        let stats = ProcessesStats { processes: buffer };
+        self.log_collection(&PROCESSES, &stats);
+        self.process_buffer = stats.processes;
+    }
+
+    /// Whether `pids` differs from the processes of the previous sample, which it replaces
+    fn process_set_changed(&mut self, pids: HashSet<u32>) -> bool {
+        if self.process_pids.as_ref() == Some(&pids) {
+            return false;
+        }
+        self.process_pids = Some(pids);
+        true
+    }
+
+    /// Whether the full snapshot is logged at this sample, starting the next interval when it is.
+    /// The first sample always logs one.
+    fn is_full_snapshot_due(&mut self) -> bool {
+        let due = self.full_snapshot_every.is_some_and(|every| {
+            self.last_full_snapshot
+                .is_none_or(|last| last.elapsed() >= every)
+        });
+        if due {
+            self.last_full_snapshot = Some(Instant::now());
+        }
+        due
+    }
+
+    /// Logs every process on the full snapshot channel, ignoring the processes filters
+    fn log_full_snapshot(&mut self) {
+        let mut buffer = std::mem::take(&mut self.process_buffer);
+        buffer.clear();
+        buffer.reserve(self.system.processes().len());
+        buffer.extend(
+            self.system
+                .processes()
+                .iter()
+                .map(|(pid, process)| self.process_stats(pid.as_u32(), process)),
+        );
+        let stats = ProcessesStats { processes: buffer };
+        self.log_collection(&PROCESSES_FULL, &stats);
+        self.process_buffer = stats.processes;
+    }
+
+    /// Records the disk usage of every process, keeping the previous sample to compute its rates
+    fn update_process_io(&mut self) {
+        let now = Instant::now();
+        let mut process_io = HashMap::with_capacity(self.system.processes().len());
+        for (pid, process) in self.system.processes() {
+            let pid = pid.as_u32();
+            let usage = process.disk_usage();
+            let sample = IoSample {
+                start_time: process.start_time(),
+                total_read_bytes: usage.total_read_bytes,
+                total_written_bytes: usage.total_written_bytes,
+                at: now,
+            };
+            let previous = self.process_io.get(&pid).map(|(sample, _)| sample);
+            let rates = process_io::rates(previous, &sample);
+            process_io.insert(pid, (sample, rates));
+        }
+        self.process_io = process_io;
+    }
+
+    /// Cpu usage of a process in percent of a core, or of the whole machine when normalized
+    fn process_cpu_usage(&self, process: &Process) -> f32 {
+        if self.process_cpu_normalized {
+            normalize_cpu_usage(process.cpu_usage(), self.system.cpus().len())
+        } else {
+            process.cpu_usage()
+        }
+    }
+
+    fn process_stats(&self, pid: u32, process: &Process) -> ProcessStats {
+        let (read_rate, write_rate) = self
+            .process_io
+            .get(&pid)
+            .map(|(_, rates)| *rates)
+            .unwrap_or_default();
+        let scheduling = self
+            .process_scheduling
+            .then(|| scheduling::read_scheduling(pid))
+            .flatten();
+        ProcessStats {
+            pid,
+            parent_pid: match process.parent() {
+                Some(parent) => parent.as_u32().to_string(),
+                None => "Unknown".to_string(),
+            },
+            name: process.name().to_string_lossy().to_string(),
+            status: process.status().to_string(),
+            cpu_usage: self.process_cpu_usage(process),
+            memory_usage_kb: process.memory() / 1024,
+            start_time_seconds: process.start_time(),
+            start_time_iso: rfc3339::format_epoch_seconds(process.start_time(), &self.time_zone),
+            run_time_seconds: process.run_time(),
+            open_sockets: self
+                .process_sockets
+                .then(|| sockets::count_sockets(pid))
+                .flatten(),
+            read_bytes_per_second: read_rate,
+            written_bytes_per_second: write_rate,
+            priority: scheduling.map(|scheduling| scheduling.priority),
+            nice: scheduling.map(|scheduling| scheduling.nice),
+        }
+    }
+
+    /// Whether a process passes the processes filters, `followed` being the descendants of the
+    /// followed process
+    fn is_process_logged(
+        &self,
+        pid: &Pid,
+        process: &Process,
+        followed: Option<&HashSet<u32>>,
+        now: u64,
+    ) -> bool {
+        followed.is_none_or(|followed| followed.contains(&pid.as_u32()))
+            && self.is_process_age_in_range(process.start_time(), process.run_time(), now)
+            && self.has_min_process_memory(process.memory())
+    }
+
+    /// Checks the process resident memory in bytes against `min_process_memory_kb`, a process at
+    /// the threshold is kept
+    fn has_min_process_memory(&self, memory: u64) -> bool {
+        self.min_process_memory_kb
+            .is_none_or(|min| memory / 1024 >= min)
+    }
+
+    /// Checks the process run time against the age filters, both bounds are inclusive
+    fn is_process_age_in_range(&self, start_time: u64, run_time: u64, now: u64) -> bool {
+        // A process that started in the future due to clock skew is treated as just started
+        let age = if start_time > now { 0 } else { run_time };
+        self.min_process_age.is_none_or(|min| age >= min)
+            && self.max_process_age.is_none_or(|max| age <= max)
+    }
+
+    pub fn log_system(&mut self) {
+        let identity = |value: Option<String>| {
+            (!self.info).then(|| value.unwrap_or_else(|| "<unknown>".to_owned()))
+        };
+        let load = self.load_average();
+        self.log(
+            &SYSTEM,
+            &SystemStats {
+                name: identity(System::name()),
+                kernel_version: identity(System::kernel_version()),
+                os_version: identity(System::os_version()),
+                os_long_version: identity(System::long_os_version()),
+                host_name: identity(System::host_name()),
+                kernel: identity(System::kernel_version()),
+                boot_time_seconds: System::boot_time(),
+                uptime_seconds: System::uptime(),
+                load_avg_one: load.one,
+                load_avg_five: load.five,
+                load_avg_fifteen: load.fifteen,
+                entropy_available: entropy::read_entropy(),
+            },
+        );
+    }
+
+    /// Logs the processes in uninterruptible sleep, from the process list refreshed at the start
+    /// of the iteration
+    pub fn log_blocked_processes(&mut self) {
+        let stats = blocked_processes(self.system.processes().iter().map(|(pid, process)| {
+            (
+                pid.as_u32(),
+                process.status(),
+                process.name().to_string_lossy(),
+                process.run_time(),
+            )
+        }));
+        self.log_collection(&BLOCKED_PROCESSES, &stats);
+    }
+
+    /// Logs the number of items of each metric, refreshing their lists without reading the
+    /// details of the items. The processes are listed at the start of the iteration.
+    pub fn log_counts(&mut self) {
+        let counted = self
+            .counted
+            .as_mut()
+            .expect("Lists created with --count-only");
+        counted
+            .disks
+            .refresh_specifics(true, DiskRefreshKind::nothing());
+        counted.networks.refresh(true);
+        counted.components.refresh(true);
+        let stats = CountsStats {
+            cpu_count: self.system.cpus().len(),
+            disk_count: counted.disks.len(),
+            network_count: counted.networks.len(),
+            process_count: self.system.processes().len(),
+            sensor_count: counted.components.len(),
+        };
+        self.log(&COUNTS, &stats);
+    }
+
+    /// Logs the static system facts once on /info
+    pub fn log_info(&mut self) {
+        let unknown = |value: Option<String>| value.unwrap_or_else(|| "<unknown>".to_owned());
+        let stats = InfoStats {
+            name: unknown(System::name()),
+            kernel_version: unknown(System::kernel_version()),
+            os_version: unknown(System::os_version()),
+            os_long_version: unknown(System::long_os_version()),
+            host_name: unknown(System::host_name()),
+            cpu_arch: System::cpu_arch(),
+            physical_cores: System::physical_core_count(&self.system),
+            logical_cores: self.system.cpus().len(),
+        };
+        self.log(&INFO, &stats);
+    }
+
+    /// Logs the run tags once, also attaching them to the channel metadata so they are stored in
+    /// the mcap channel record
+    pub fn log_run_info(&mut self, tags: BTreeMap<String, String>) {
+        if !self.is_topic_allowed(RUN_INFO_TOPIC) {
+            return;
+        }
+        let channel = ChannelBuilder::new(RUN_INFO_TOPIC)
+            .metadata(tags.clone())
+            .schema(json_schema::<RunInfoStats>())
+            .build_typed::<RunInfoStats>()
+            .expect("Failed to create run info channel");
+        self.log_on(&channel, &RunInfoStats { tags });
+    }
+
+    pub fn log_cgroup(&mut self) {
+        if let Some(stats) = cgroup::read_limits() {
+            self.log(&CGROUP, &stats);
+        }
+    }
+
+    pub fn log_pressure(&mut self) {
+        if let Some(stats) = pressure::read_pressure() {
+            self.log(&PRESSURE, &stats);
+        }
+    }
+
+    pub fn log_services(&mut self) {
+        if let Some(services) = services::list_services() {
+            self.log_collection(&SERVICES, &ServicesStats { services });
+        }
+    }
+
+    pub fn log_gpu_processes(&mut self) {
+        let Some(gpu_processes) = gpu::read_gpu_processes() else {
+            log::warn!("Disabling GPU processes: nvidia-smi is not available");
+            self.gpu_processes_enabled = false;
+            return;
+        };
+        let pids: Vec<Pid> = gpu_processes
+            .iter()
+            .map(|process| Pid::from_u32(process.pid))
+            .collect();
+        self.system
+            .refresh_processes(ProcessesToUpdate::Some(&pids), false);
+        let stats = GpuProcessesStats {
+            processes: gpu_processes
+                .into_iter()
+                .map(|process| GpuProcessStats {
+                    name: self
+                        .system
+                        .process(Pid::from_u32(process.pid))
+                        .map(|p| p.name().to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    pid: process.pid,
+                    gpu_uuid: process.gpu_uuid,
+                    used_memory_bytes: process.used_memory_bytes,
                 })
                 .collect(),
+        };
+        self.log_collection(&GPU_PROCESSES, &stats);
+    }
+
+    pub fn log_smart(&mut self) {
+        match smart::read_smart() {
+            Ok(Some(mut devices)) => {
+                for device in &mut devices {
+                    device.temperature = device
+                        .temperature
+                        .map(|temperature| self.temp_unit.convert(temperature));
+                }
+                let stats = SmartStats {
+                    devices,
+                    unit: self.temp_unit,
+                };
+                self.log_collection(&SMART, &stats);
+            }
+            Ok(None) => {
+                log::warn!("Disabling SMART metrics: smartctl is not available");
+                self.smart_enabled = false;
+            }
+            Err(e) => {
+                log::warn!("Disabling SMART metrics: {e}");
+                self.smart_enabled = false;
+            }
+        }
+    }
+
+    pub fn log_power(&mut self) {
+        match power::read_power() {
+            Ok(Some(stats)) => self.log(&POWER, &stats),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("Disabling power metrics: {e}");
+                self.power_enabled = false;
+            }
+        }
+    }
+
+    /// Logs the usage of the monitor process, as refreshed at the start of the iteration
+    pub fn log_self(&mut self) {
+        let Some(pid) = self.self_pid else {
+            return;
+        };
+        let Some(process) = self.system.process(pid) else {
+            return;
+        };
+        let stats = SelfStats {
+            pid: pid.as_u32(),
+            cpu_usage: process.cpu_usage(),
+            memory_usage_kb: process.memory() / 1024,
+            virtual_memory_kb: process.virtual_memory() / 1024,
+        };
+        self.log(&SELF, &stats);
+    }
+
+    /// Adds a sample to the history of each tracked process and logs the histories, reading the
+    /// processes as refreshed at the start of the iteration
+    pub fn log_tracked_processes(&mut self) {
+        let pids: Vec<Pid> = self
+            .tracked_processes
+            .keys()
+            .map(|&pid| Pid::from_u32(pid))
+            .collect();
+        let timestamp_seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut processes = Vec::with_capacity(pids.len());
+        for pid in pids {
+            let process = self.system.process(pid);
+            let sample = process.map(|process| TrackedSampleStats {
+                timestamp_seconds,
+                cpu_usage: self.process_cpu_usage(process),
+                memory_usage_kb: process.memory() / 1024,
+            });
+            let history = self
+                .tracked_processes
+                .get_mut(&pid.as_u32())
+                .expect("Tracked pids come from the histories");
+            if let (Some(process), Some(sample)) = (process, sample) {
+                history.record(
+                    process.start_time(),
+                    &process.name().to_string_lossy(),
+                    sample,
+                );
+            }
+            processes.push(TrackedProcessStats {
+                pid: pid.as_u32(),
+                name: history.name().to_string(),
+                running: process.is_some(),
+                start_time_seconds: history.start_time(),
+                history: history.samples(),
+            });
+        }
+        self.log_collection(&TRACKED_PROCESSES, &TrackedProcessesStats { processes });
+    }
+
+    /// Adds the load to the rollup and logs it once the summary interval has elapsed
+    pub fn log_rollup(&mut self) {
+        let load = self.load_average();
+        let Some(rollup) = &mut self.rollup else {
+            return;
+        };
+        if !self.discarding {
+            rollup.load_avg_one.add(load.one);
+        }
+        if let Some(stats) = rollup.take_if_due() {
+            self.log(&ROLLUP, &stats);
+        }
+    }
+
+    pub fn log_status(&mut self) {
+        let stats = StatusStats {
+            samples_per_second: sampling_rate(&self.iteration_times),
+            requested_samples_per_second: 1.0 / self.interval.as_secs_f64(),
+            dropped_samples: self
+                .output_queue
+                .as_ref()
+                .map(OutputQueue::dropped)
+                .unwrap_or_default(),
+            skipped_samples: self.skipped_messages,
+        };
+        self.log(&STATUS, &stats);
+    }
+}
+
+/// Keeps the `max_cores` busiest cores, in their logical order
+fn keep_busiest_cores(stats: &mut CpuStats, max_cores: usize) {
+    if stats.cores.len() <= max_cores {
+        return;
+    }
+    stats.cores.sort_by(|a, b| b.usage.total_cmp(&a.usage));
+    stats.cores.truncate(max_cores);
+    stats.cores.sort_by_key(|core| core.index);
+    stats.cores_truncated = true;
+}
+
+/// Lists the processes in uninterruptible sleep, the D state, by pid, from the pid, status, name
+/// and run time of every process
+fn blocked_processes<'a>(
+    processes: impl Iterator<Item = (u32, ProcessStatus, Cow<'a, str>, u64)>,
+) -> BlockedProcessesStats {
+    let mut processes: Vec<BlockedProcessStats> = processes
+        .filter(|(_, status, _, _)| *status == ProcessStatus::UninterruptibleDiskSleep)
+        .map(|(pid, _, name, run_time_seconds)| BlockedProcessStats {
+            pid,
+            name: name.into_owned(),
+            run_time_seconds,
+        })
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    BlockedProcessesStats { processes }
+}
+
+/// Converts a cpu usage in percent of a core to percent of the whole machine. It is capped at
+/// 100%, which the sampling jitter between the process and cpu times can exceed.
+fn normalize_cpu_usage(usage: f32, cpu_count: usize) -> f32 {
+    (usage / cpu_count.max(1) as f32).min(100.0)
+}
+
+/// Subtracts the usage of a process, in percent of one cpu, from the global usage, in percent of
+/// all `cpu_count` cpus, without going below zero since both are sampled at slightly different
+/// times
+fn exclude_usage(global_usage: f32, process_usage: f32, cpu_count: usize) -> f32 {
+    (global_usage - process_usage / cpu_count.max(1) as f32).max(0.0)
+}
+
+/// Moves the vendor and brand of the cores to the cpu when every core has the same, e.g. not on
+/// big.LITTLE cpus
+fn hoist_cpu_identity(stats: &mut CpuStats) {
+    let Some(first) = stats.cores.first() else {
+        return;
+    };
+    if stats
+        .cores
+        .iter()
+        .any(|core| core.vendor_id != first.vendor_id || core.brand != first.brand)
+    {
+        return;
+    }
+    stats.vendor_id = first.vendor_id.clone();
+    stats.brand = first.brand.clone();
+    for core in &mut stats.cores {
+        core.vendor_id = None;
+        core.brand = None;
+    }
+}
+
+/// Topics of the channels the enabled metrics log on
+pub fn produced_topics(args: &Cli) -> Vec<&'static str> {
+    let metrics = [
+        (args.cpu, vec![CPU.topic()]),
+        (args.memory, vec![MEMORY.topic()]),
+        (
+            args.temperature,
+            vec![TEMPERATURE_SUMMARY.topic(), COMPONENTS.topic()],
+        ),
+        (args.disks, vec![DISKS.topic()]),
+        (
+            args.networks,
+            vec![if args.network_aggregate {
+                NETWORK_TOTAL.topic()
+            } else {
+                NETWORKS.topic()
+            }],
+        ),
+        (
+            args.processes,
+            [
+                Some(if args.compact_processes {
+                    PROCESSES_COMPACT.topic()
+                } else {
+                    PROCESSES.topic()
+                }),
+                args.full_snapshot_every.map(|_| PROCESSES_FULL.topic()),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+        ),
+        (args.system, vec![SYSTEM.topic()]),
+        (args.count_only, vec![COUNTS.topic()]),
+        (args.info, vec![INFO.topic()]),
+        (args.cgroup, vec![CGROUP.topic()]),
+        (args.pressure, vec![PRESSURE.topic()]),
+        (args.services, vec![SERVICES.topic()]),
+        (args.power, vec![POWER.topic()]),
+        (args.gpu_processes, vec![GPU_PROCESSES.topic()]),
+        (args.blocked_processes, vec![BLOCKED_PROCESSES.topic()]),
+        (args.smart, vec![SMART.topic()]),
+        (args.self_monitor, vec![SELF.topic()]),
+        (!args.track_pid.is_empty(), vec![TRACKED_PROCESSES.topic()]),
+        (args.status, vec![STATUS.topic()]),
+        (args.summary_interval.is_some(), vec![ROLLUP.topic()]),
+        (
+            (args.memory && args.mem_alert_high.is_some())
+                || (args.disks && (args.disk_alert_high.is_some() || !args.disk_alert.is_empty())),
+            vec![ALERTS.topic()],
+        ),
+        (true, vec![EVENTS.topic()]),
+        (
+            !args.label.is_empty() || args.tags_file.is_some(),
+            vec![RUN_INFO_TOPIC],
+        ),
+    ];
+    metrics
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .flat_map(|(_, topics)| topics)
+        .collect()
+}
+
+/// Sums the traffic of the given interfaces
+fn sum_networks<'a>(networks: impl Iterator<Item = &'a NetworkStats>) -> NetworkTotalStats {
+    networks.fold(
+        NetworkTotalStats {
+            interfaces: 0,
+            received: 0,
+            transmitted: 0,
+            total_received: 0,
+            total_transmitted: 0,
+        },
+        |mut total, network| {
+            total.interfaces += 1;
+            total.received += network.received;
+            total.transmitted += network.transmitted;
+            total.total_received += network.total_received;
+            total.total_transmitted += network.total_transmitted;
+            total
+        },
+    )
+}
+
+/// Sorts the disks by mount point, and by device name for a filesystem mounted from several
+/// devices, so the order doesn't depend on the order the kernel lists them in
+fn sort_disks(disks: &mut [DiskStats]) {
+    disks.sort_by(|a, b| {
+        a.mount_point
+            .cmp(&b.mount_point)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Sorts the networks by interface name, which is unique on a host
+fn sort_networks(networks: &mut [NetworkStats]) {
+    networks.sort_by(|a, b| a.interface_name.cmp(&b.interface_name));
+}
+
+/// Pair of cumulative counters relative to their first sample, which becomes the baseline. A
+/// counter below its baseline was reset, e.g. by an interface coming back up, and the reset value
+/// becomes the new baseline.
+fn relative_to_baseline(
+    baselines: &mut HashMap<String, (u64, u64)>,
+    key: &str,
+    totals: (u64, u64),
+) -> (u64, u64) {
+    let baseline = baselines.entry(key.to_string()).or_insert(totals);
+    if totals.0 < baseline.0 || totals.1 < baseline.1 {
+        *baseline = totals;
+    }
+    (totals.0 - baseline.0, totals.1 - baseline.1)
+}
+
+/// Computes the samples per second achieved over the given iteration start times, zero until
+/// there are at least two iterations
+fn sampling_rate(iteration_times: &VecDeque<Instant>) -> f64 {
+    match (iteration_times.front(), iteration_times.back()) {
+        (Some(first), Some(last)) if last > first => {
+            (iteration_times.len() - 1) as f64 / last.duration_since(*first).as_secs_f64()
+        }
+        _ => 0.0,
+    }
+}
+
+/// Computes the hottest and average temperature, leaving out the sensors that report NaN or an
+/// infinite value. `None` when no sensor reports a finite temperature.
+fn summarize_temperatures(
+    components: &[ComponentStats],
+    unit: TemperatureUnit,
+) -> Option<TemperatureSummaryStats> {
+    let readings = || components.iter().filter(|c| c.temperature.is_finite());
+    let hottest = readings().max_by(|a, b| a.temperature.total_cmp(&b.temperature))?;
+    let (sum, count) =
+        readings().fold((0.0, 0), |(sum, count), c| (sum + c.temperature, count + 1));
+
+    Some(TemperatureSummaryStats {
+        max_temperature: unit.convert(hottest.temperature),
+        max_label: hottest.label.clone(),
+        avg_temperature: unit.convert(sum / count as f32),
+        unit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use clap::Parser;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::output::OutputSink;
+
+    /// Keeps the messages written on each topic
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<(String, Value)>>>);
+
+    impl Capture {
+        fn messages(&self, topic: &str) -> Vec<Value> {
+            let messages = self.0.lock().unwrap();
+            messages
+                .iter()
+                .filter(|(message_topic, _)| message_topic == topic)
+                .map(|(_, message)| message.clone())
+                .collect()
+        }
+    }
+
+    impl OutputSink for Capture {
+        fn write(&mut self, message: &Message) {
+            let json = message.json().cloned().unwrap_or_default();
+            self.0
+                .lock()
+                .unwrap()
+                .push((message.topic().to_string(), json));
+        }
+    }
+
+    fn collection(args: &[&str]) -> (LoggerCollection, Capture) {
+        let args = Cli::parse_from(["fox-monitor"].iter().chain(args));
+        let outputs = Outputs::new();
+        let capture = Capture::default();
+        outputs.add(Box::new(capture.clone()));
+        let collection = LoggerCollection::new(&args, outputs).unwrap();
+        (collection, capture)
+    }
+
+    /// Carries the same messages as /counts on another topic
+    static COUNTS_DEDUPE_TEST: LazyChannel<CountsStats> =
+        LazyChannel::new("/counts_dedupe_test", || {
+            ChannelBuilder::new("/counts_dedupe_test")
+                .schema(json_schema::<CountsStats>())
+                .build_typed()
+                .unwrap()
         });
+
+    fn counts(process_count: usize) -> CountsStats {
+        CountsStats {
+            cpu_count: 1,
+            disk_count: 0,
+            network_count: 0,
+            process_count,
+            sensor_count: 0,
+        }
     }
 
-    pub fn log_system(&self) {
-        SYSTEM.log(&SystemStats {
-            name: System::name().unwrap_or_else(|| "<unknown>".to_owned()),
-            kernel_version: System::kernel_version().unwrap_or_else(|| "<unknown>".to_owned()),
-            os_version: System::os_version().unwrap_or_else(|| "<unknown>".to_owned()),
-            os_long_version: System::long_os_version().unwrap_or_else(|| "<unknown>".to_owned()),
-            host_name: System::host_name().unwrap_or_else(|| "<unknown>".to_owned()),
-            kernel: System::kernel_version().unwrap_or_else(|| "<unknown>".to_owned()),
-            boot_time_seconds: System::boot_time(),
-            uptime_seconds: System::uptime(),
-            load_avg_one: System::load_average().one,
-            load_avg_five: System::load_average().five,
-            load_avg_fifteen: System::load_average().fifteen,
+    #[test]
+    fn skipped_samples_leave_seq_gaps() {
+        let (mut collection, capture) = collection(&["--status", "--dedupe", "--omit-empty"]);
+        for process_count in [10, 10, 10, 11] {
+            collection.iterations += 1;
+            collection.log(&COUNTS, &counts(process_count));
+        }
+        collection.log_collection(
+            &BLOCKED_PROCESSES,
+            &BlockedProcessesStats {
+                processes: Vec::new(),
+            },
+        );
+        collection.log_status();
+
+        let seqs: Vec<u64> = capture
+            .messages(COUNTS.topic())
+            .iter()
+            .map(|message| message["seq"].as_u64().unwrap())
+            .collect();
+        assert_eq!(seqs, [1, 4]);
+        assert!(capture.messages(BLOCKED_PROCESSES.topic()).is_empty());
+        let status = &capture.messages(STATUS.topic())[0];
+        assert_eq!(status["skipped_samples"], 3);
+    }
+
+    #[test]
+    fn skipped_first_iterations_are_not_logged() {
+        let (mut collection, capture) = collection(&["--status", "--skip-first", "2"]);
+        for _ in 0..3 {
+            collection.log_all();
+        }
+        let statuses = capture.messages(STATUS.topic());
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0]["seq"], 3);
+        assert_eq!(collection.logged_iterations(), 1);
+    }
+
+    #[test]
+    fn dedupe_drops_identical_messages_per_topic() {
+        let (mut collection, capture) = collection(&["--count-only", "--dedupe"]);
+        collection.log(&COUNTS, &counts(10));
+        collection.log(&COUNTS, &counts(10));
+        collection.log(&COUNTS_DEDUPE_TEST, &counts(10));
+        collection.log(&COUNTS, &counts(11));
+        collection.log(&COUNTS, &counts(10));
+        let process_counts: Vec<u64> = capture
+            .messages(COUNTS.topic())
+            .iter()
+            .map(|message| message["process_count"].as_u64().unwrap())
+            .collect();
+        assert_eq!(process_counts, [10, 11, 10]);
+        assert_eq!(capture.messages(COUNTS_DEDUPE_TEST.topic()).len(), 1);
+    }
+
+    #[test]
+    fn dedupe_logs_again_after_the_keepalive() {
+        let (mut collection, capture) = collection(&["--count-only", "--dedupe"]);
+        collection.log(&COUNTS, &counts(10));
+        let (_, logged) = collection.last_hashes.get_mut(COUNTS.topic()).unwrap();
+        *logged -= Duration::from_secs(60);
+        collection.log(&COUNTS, &counts(10));
+        assert_eq!(capture.messages(COUNTS.topic()).len(), 2);
+    }
+
+    #[test]
+    fn temperature_summary_ignores_missing_readings() {
+        let components =
+            [("cpu", 60.0), ("gpu", f32::NAN), ("nvme", 40.0)].map(|(label, temperature)| {
+                ComponentStats {
+                    label: label.to_string(),
+                    temperature,
+                }
+            });
+        let summary = summarize_temperatures(&components, TemperatureUnit::Celsius).unwrap();
+        assert_eq!(summary.max_temperature, 60.0);
+        assert_eq!(summary.max_label, "cpu");
+        assert_eq!(summary.avg_temperature, 50.0);
+
+        let summary = summarize_temperatures(&components, TemperatureUnit::Fahrenheit).unwrap();
+        assert_eq!(summary.max_temperature, 140.0);
+        assert_eq!(summary.avg_temperature, 122.0);
+    }
+
+    #[test]
+    fn temperature_summary_needs_a_reading() {
+        let components = [ComponentStats {
+            label: "gpu".to_string(),
+            temperature: f32::NAN,
+        }];
+        assert!(summarize_temperatures(&components, TemperatureUnit::Celsius).is_none());
+        assert!(summarize_temperatures(&[], TemperatureUnit::Celsius).is_none());
+    }
+
+    #[test]
+    fn metric_stops_after_its_max_samples() {
+        let (mut collection, capture) =
+            collection(&["--status", "--system", "--system-max-samples", "2"]);
+        for _ in 0..4 {
+            collection.log_all();
+        }
+        assert_eq!(capture.messages(SYSTEM.topic()).len(), 2);
+        assert_eq!(capture.messages(STATUS.topic()).len(), 4);
+        assert!(!collection.is_due("system"));
+        assert!(collection.is_due("status"));
+    }
+
+    fn core(index: u32, usage: f32, brand: &str) -> CoreStats {
+        CoreStats {
+            index,
+            usage,
+            frequency_mhz: 0,
+            vendor_id: Some("GenuineIntel".to_string()),
+            brand: Some(brand.to_string()),
+            package_id: None,
+            core_id: None,
+            idle_time_ms: None,
+            busy_time_ms: None,
+        }
+    }
+
+    fn cpu(cores: Vec<CoreStats>) -> CpuStats {
+        CpuStats {
+            usage: 0.0,
+            physical_cores: 0,
+            cores,
+            cores_truncated: false,
+            vendor_id: None,
+            brand: None,
+            thermal_throttling: false,
+            steal_percent: 0.0,
+        }
+    }
+
+    #[test]
+    fn shared_cpu_identity_moves_to_the_cpu() {
+        let mut stats = cpu(vec![core(0, 0.0, "i7"), core(1, 0.0, "i7")]);
+        hoist_cpu_identity(&mut stats);
+        assert_eq!(stats.brand.as_deref(), Some("i7"));
+        assert_eq!(stats.vendor_id.as_deref(), Some("GenuineIntel"));
+        assert!(stats.cores.iter().all(|core| core.brand.is_none()));
+    }
+
+    #[test]
+    fn differing_cpu_identity_stays_on_the_cores() {
+        let mut stats = cpu(vec![core(0, 0.0, "Cortex-A76"), core(1, 0.0, "Cortex-A55")]);
+        hoist_cpu_identity(&mut stats);
+        assert_eq!(stats.brand, None);
+        assert_eq!(stats.cores[1].brand.as_deref(), Some("Cortex-A55"));
+    }
+
+    #[test]
+    fn keeps_the_busiest_cores_in_index_order() {
+        let mut stats = cpu([10.0, 90.0, 50.0, 70.0]
+            .into_iter()
+            .zip(0..)
+            .map(|(usage, index)| core(index, usage, "i7"))
+            .collect());
+        keep_busiest_cores(&mut stats, 2);
+        let indexes: Vec<u32> = stats.cores.iter().map(|core| core.index).collect();
+        assert_eq!(indexes, [1, 3]);
+        assert!(stats.cores_truncated);
+
+        let mut stats = cpu(vec![core(0, 10.0, "i7")]);
+        keep_busiest_cores(&mut stats, 2);
+        assert_eq!(stats.cores.len(), 1);
+        assert!(!stats.cores_truncated);
+    }
+
+    #[test]
+    fn only_the_listed_topics_are_logged() {
+        let (mut collection, capture) =
+            collection(&["--status", "--count-only", "--topic", "/counts"]);
+        collection.log_all();
+        assert_eq!(capture.messages(COUNTS.topic()).len(), 1);
+        assert!(capture.messages(STATUS.topic()).is_empty());
+    }
+
+    #[test]
+    fn sample_counts_follow_the_iterations() {
+        let (mut collection, _capture) = collection(&["--status", "--count-only", "--system"]);
+        let iterations = 3;
+        for _ in 0..iterations {
+            collection.log_all();
+        }
+        let expected: BTreeMap<String, u64> = [COUNTS.topic(), STATUS.topic(), SYSTEM.topic()]
+            .into_iter()
+            .map(|topic| (topic.to_string(), iterations))
+            .collect();
+        assert_eq!(collection.sample_counts(), &expected);
+        assert_eq!(
+            collection.sample_counts().values().sum::<u64>(),
+            iterations * expected.len() as u64
+        );
+    }
+
+    #[test]
+    fn process_age_bounds_are_inclusive() {
+        let (collection, _capture) = collection(&[
+            "--processes",
+            "--min-process-age",
+            "10",
+            "--max-process-age",
+            "20",
+        ]);
+        let now = 1_000;
+        let in_range =
+            |run_time: u64| collection.is_process_age_in_range(now - run_time, run_time, now);
+        assert!(!in_range(9));
+        assert!(in_range(10));
+        assert!(in_range(20));
+        assert!(!in_range(21));
+    }
+
+    #[test]
+    fn process_started_in_the_future_is_just_started() {
+        let (max_age, _capture) = collection(&["--processes", "--max-process-age", "5"]);
+        assert!(max_age.is_process_age_in_range(2_000, 600, 1_000));
+        let (min_age, _capture) = collection(&["--processes", "--min-process-age", "1"]);
+        assert!(!min_age.is_process_age_in_range(2_000, 600, 1_000));
+    }
+
+    #[test]
+    fn failing_metric_doesnt_stop_the_others() {
+        let (mut collection, capture) =
+            collection(&["--count-only", "--system", "--max-metric-failures", "2"]);
+        for _ in 0..3 {
+            collection.iterations += 1;
+            collection.guarded("failing", |_| panic!("injected failure"));
+            collection.guarded("counts", LoggerCollection::log_counts);
+            collection.guarded("system", LoggerCollection::log_system);
+        }
+        assert_eq!(capture.messages(COUNTS.topic()).len(), 3);
+        assert_eq!(capture.messages(SYSTEM.topic()).len(), 3);
+        assert!(!collection.is_due("failing"));
+        assert_eq!(collection.metric_failures["failing"], 2);
+    }
+
+    #[test]
+    fn sampling_rate_is_averaged_over_the_iterations() {
+        let start = Instant::now();
+        let times: VecDeque<Instant> = (0..5)
+            .map(|i| start + Duration::from_millis(500) * i)
+            .collect();
+        assert_eq!(sampling_rate(&times), 2.0);
+        assert_eq!(sampling_rate(&VecDeque::from([start])), 0.0);
+        assert_eq!(sampling_rate(&VecDeque::new()), 0.0);
+    }
+
+    #[test]
+    fn sampling_rate_window_keeps_the_latest_iterations() {
+        let (mut collection, _capture) = collection(&["--status"]);
+        for _ in 0..SAMPLING_RATE_WINDOW + 3 {
+            collection.log_all();
+        }
+        assert_eq!(collection.iteration_times.len(), SAMPLING_RATE_WINDOW);
+    }
+
+    #[test]
+    fn relative_counters_start_from_the_first_sample() {
+        let mut baselines = HashMap::new();
+        assert_eq!(
+            relative_to_baseline(&mut baselines, "eth0", (100, 50)),
+            (0, 0)
+        );
+        assert_eq!(
+            relative_to_baseline(&mut baselines, "eth0", (150, 70)),
+            (50, 20)
+        );
+        assert_eq!(
+            relative_to_baseline(&mut baselines, "wlan0", (10, 10)),
+            (0, 0)
+        );
+        assert_eq!(
+            relative_to_baseline(&mut baselines, "eth0", (180, 70)),
+            (80, 20)
+        );
+    }
+
+    #[test]
+    fn reset_counter_becomes_the_new_baseline() {
+        let mut baselines = HashMap::new();
+        relative_to_baseline(&mut baselines, "eth0", (100, 50));
+        assert_eq!(
+            relative_to_baseline(&mut baselines, "eth0", (5, 60)),
+            (0, 0)
+        );
+        assert_eq!(
+            relative_to_baseline(&mut baselines, "eth0", (25, 65)),
+            (20, 5)
+        );
+    }
+
+    #[test]
+    fn batched_messages_are_held_until_the_batch_is_full() {
+        let (mut collection, capture) = collection(&["--count-only", "--batch", "3"]);
+        for process_count in [1, 2] {
+            collection.iterations += 1;
+            collection.log(&COUNTS, &counts(process_count));
+        }
+        assert!(capture.messages(COUNTS.topic()).is_empty());
+        assert_eq!(collection.pending.len(), 2);
+
+        collection.flush();
+        let process_counts: Vec<u64> = capture
+            .messages(COUNTS.topic())
+            .iter()
+            .map(|message| message["process_count"].as_u64().unwrap())
+            .collect();
+        assert_eq!(process_counts, [1, 2]);
+        assert!(collection.pending.is_empty());
+    }
+
+    #[test]
+    fn batch_is_flushed_every_batch_size_iterations() {
+        let (mut collection, capture) = collection(&["--count-only", "--batch", "2"]);
+        collection.log_all();
+        assert!(capture.messages(COUNTS.topic()).is_empty());
+        collection.log_all();
+        let seqs: Vec<u64> = capture
+            .messages(COUNTS.topic())
+            .iter()
+            .map(|message| message["seq"].as_u64().unwrap())
+            .collect();
+        assert_eq!(seqs, [1, 2]);
+    }
+
+    fn network(interface_name: &str, received: u64, total_received: u64) -> NetworkStats {
+        NetworkStats {
+            interface_name: interface_name.to_string(),
+            mac_address: "00:00:00:00:00:00".to_string(),
+            received,
+            transmitted: received / 2,
+            total_received,
+            total_transmitted: total_received / 2,
+            link_speed_mbps: None,
+            mtu: None,
+        }
+    }
+
+    #[test]
+    fn network_totals_sum_every_interface() {
+        let networks = [network("eth0", 100, 1_000), network("wlan0", 40, 600)];
+        let total = sum_networks(networks.iter());
+        assert_eq!(total.interfaces, 2);
+        assert_eq!(total.received, 140);
+        assert_eq!(total.transmitted, 70);
+        assert_eq!(total.total_received, 1_600);
+        assert_eq!(total.total_transmitted, 800);
+
+        let total = sum_networks([].iter());
+        assert_eq!(total.interfaces, 0);
+        assert_eq!(total.total_received, 0);
+    }
+
+    #[test]
+    fn memory_modes_refresh_only_their_part() {
+        let refreshed = |args: &[&str]| {
+            let (collection, _capture) = collection(args);
+            let refresh = collection.memory_refresh;
+            (refresh.ram(), refresh.swap())
+        };
+        assert_eq!(refreshed(&["--memory"]), (true, true));
+        assert_eq!(refreshed(&["--memory", "--memory-no-swap"]), (true, false));
+        assert_eq!(refreshed(&["--memory", "--swap-only"]), (false, true));
+        assert_eq!(refreshed(&["--memory", "--minimal"]), (true, false));
+    }
+
+    #[test]
+    fn memory_sample_keeps_every_field() {
+        let (mut collection, capture) = collection(&["--memory", "--swap-only"]);
+        collection.log_memory();
+        let memory = &capture.messages(MEMORY.topic())[0];
+        for field in [
+            "total_kb",
+            "available_kb",
+            "free_kb",
+            "used_kb",
+            "swap_total_kb",
+            "swap_used_kb",
+        ] {
+            assert!(memory[field].is_u64(), "{field}");
+        }
+    }
+
+    #[test]
+    fn process_at_the_memory_threshold_is_kept() {
+        let (filtered, _capture) = collection(&["--processes", "--min-process-memory-kb", "100"]);
+        assert!(filtered.has_min_process_memory(100 * 1024));
+        assert!(filtered.has_min_process_memory(200 * 1024));
+        assert!(!filtered.has_min_process_memory(100 * 1024 - 1));
+        assert!(!filtered.has_min_process_memory(0));
+
+        let (unfiltered, _capture) = collection(&["--processes"]);
+        assert!(unfiltered.has_min_process_memory(0));
+    }
+
+    #[test]
+    fn unchanged_process_set_is_skipped() {
+        let (mut collection, _capture) = collection(&["--processes", "--processes-on-change"]);
+        assert!(collection.process_set_changed(HashSet::from([1, 2, 3])));
+        assert!(!collection.process_set_changed(HashSet::from([3, 2, 1])));
+        // A process exited
+        assert!(collection.process_set_changed(HashSet::from([1, 2])));
+        // Another one started with the same count
+        assert!(collection.process_set_changed(HashSet::from([1, 4])));
+        assert!(!collection.process_set_changed(HashSet::from([1, 4])));
+    }
+
+    fn disk(name: &str, mount_point: &str) -> DiskStats {
+        DiskStats {
+            name: name.to_string(),
+            mount_point: mount_point.to_string(),
+            total_read_kb: 0,
+            total_written_kb: 0,
+            read_kb: 0,
+            written_kb: 0,
+            inodes_total: None,
+            inodes_used: None,
+            inodes_free: None,
+            read_latency_ms: None,
+            write_latency_ms: None,
+        }
+    }
+
+    #[test]
+    fn disks_are_sorted_by_mount_point_then_name() {
+        let mut disks = vec![
+            disk("sdb1", "/home"),
+            disk("overlay", "/"),
+            disk("sda2", "/boot"),
+            disk("sda1", "/home"),
+        ];
+        sort_disks(&mut disks);
+        let order: Vec<(&str, &str)> = disks
+            .iter()
+            .map(|disk| (disk.mount_point.as_str(), disk.name.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("/", "overlay"),
+                ("/boot", "sda2"),
+                ("/home", "sda1"),
+                ("/home", "sdb1")
+            ]
+        );
+    }
+
+    #[test]
+    fn networks_are_sorted_by_interface_name() {
+        let mut networks = vec![
+            network("wlan0", 0, 0),
+            network("eth1", 0, 0),
+            network("eth0", 0, 0),
+            network("lo", 0, 0),
+        ];
+        sort_networks(&mut networks);
+        let names: Vec<&str> = networks
+            .iter()
+            .map(|network| network.interface_name.as_str())
+            .collect();
+        assert_eq!(names, ["eth0", "eth1", "lo", "wlan0"]);
+    }
+
+    #[test]
+    fn full_snapshot_is_logged_every_interval() {
+        let (mut every_minute, _capture) =
+            collection(&["--processes", "--full-snapshot-every", "60"]);
+        assert!(every_minute.is_full_snapshot_due());
+        assert!(!every_minute.is_full_snapshot_due());
+        *every_minute.last_full_snapshot.as_mut().unwrap() -= Duration::from_secs(59);
+        assert!(!every_minute.is_full_snapshot_due());
+        *every_minute.last_full_snapshot.as_mut().unwrap() -= Duration::from_secs(1);
+        assert!(every_minute.is_full_snapshot_due());
+        assert!(!every_minute.is_full_snapshot_due());
+
+        let (mut disabled, _capture) = collection(&["--processes"]);
+        assert!(!disabled.is_full_snapshot_due());
+    }
+
+    #[test]
+    fn boot_time_change_is_reported_once() {
+        let (mut collection, capture) = collection(&["--status"]);
+        let boot_time = collection.boot_time;
+        collection.check_boot_time(boot_time + 1);
+        assert!(capture.messages(EVENTS.topic()).is_empty());
+
+        collection.check_boot_time(boot_time + 3_600);
+        collection.check_boot_time(boot_time + 3_600);
+        let events = capture.messages(EVENTS.topic());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["kind"], "reboot_detected");
+        assert_eq!(collection.boot_time, boot_time + 3_600);
+    }
+
+    #[test]
+    fn self_monitor_reports_its_own_process() {
+        let (mut monitored, capture) = collection(&["--self-monitor"]);
+        monitored.log_all();
+        let samples = capture.messages(SELF.topic());
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0]["pid"], std::process::id());
+        assert!(samples[0]["memory_usage_kb"].as_u64().unwrap() > 0);
+
+        let (excluding, _capture) = collection(&["--cpu", "--self-monitor", "--exclude-self"]);
+        let own_pid = Some(Pid::from_u32(std::process::id()));
+        assert_eq!(excluding.self_pid, own_pid);
+        assert_eq!(excluding.exclude_self_pid, own_pid);
+    }
+
+    #[test]
+    fn normalized_process_cpu_usage_is_a_share_of_the_machine() {
+        assert_eq!(normalize_cpu_usage(200.0, 4), 50.0);
+        assert_eq!(normalize_cpu_usage(50.0, 1), 50.0);
+        // Sampling jitter can report more than every core busy
+        assert_eq!(normalize_cpu_usage(410.0, 4), 100.0);
+        assert_eq!(normalize_cpu_usage(30.0, 0), 30.0);
+    }
+
+    #[test]
+    fn warmup_iterations_are_not_logged() {
+        let (mut warming_up, capture) = collection(&["--status", "--warmup-seconds", "60"]);
+        warming_up.log_all();
+        warming_up.log_all();
+        assert!(capture.messages(STATUS.topic()).is_empty());
+
+        warming_up.warmup_until = Some(Instant::now() - Duration::from_millis(1));
+        warming_up.log_all();
+        let statuses = capture.messages(STATUS.topic());
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0]["seq"], 3);
+        assert_eq!(warming_up.logged_iterations(), 1);
+        assert_eq!(warming_up.warmup_until, None);
+    }
+
+    #[test]
+    fn info_is_logged_once_and_left_out_of_system() {
+        let (mut with_info, capture) = collection(&["--info", "--system"]);
+        with_info.log_info();
+        for _ in 0..3 {
+            with_info.log_all();
+        }
+        let infos = capture.messages(INFO.topic());
+        assert_eq!(infos.len(), 1);
+        assert!(infos[0]["logical_cores"].as_u64().unwrap() > 0);
+        let systems = capture.messages(SYSTEM.topic());
+        assert_eq!(systems.len(), 3);
+        assert!(
+            systems
+                .iter()
+                .all(|system| system.get("host_name").is_none())
+        );
+    }
+
+    #[test]
+    fn monitor_errors_describe_their_cause() {
+        assert_eq!(MonitorError::NoCpus.to_string(), "no cpu was found");
+        let error = MonitorError::OutputThread(std::io::Error::new(
+            std::io::ErrorKind::WouldBlock,
+            "Resource temporarily unavailable",
+        ));
+        assert_eq!(
+            error.to_string(),
+            "failed to start the output thread: Resource temporarily unavailable"
+        );
+    }
+
+    #[test]
+    fn probe_reports_found_items_and_unavailable_sources() {
+        let (probing, _capture) = collection(&["--cpu", "--power"]);
+        let results = probing.probe();
+        let metrics: Vec<&str> = results.iter().map(|result| result.metric).collect();
+        assert_eq!(metrics, ["cpu", "power"]);
+        assert_eq!(results[0].items, "cpus");
+        assert_eq!(results[0].found, Ok(probing.system.cpus().len()));
+        if cfg!(not(target_os = "macos")) {
+            assert_eq!(
+                results[1].found,
+                Err("power can't be read on this platform".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn monitor_usage_is_subtracted_from_the_global_usage() {
+        // 40% of one core out of 4 cores is 10% of the machine
+        assert_eq!(exclude_usage(25.0, 40.0, 4), 15.0);
+        assert_eq!(exclude_usage(25.0, 0.0, 4), 25.0);
+        // Sampled at slightly different times, the monitor can seem busier than the machine
+        assert_eq!(exclude_usage(5.0, 40.0, 4), 0.0);
+        assert_eq!(exclude_usage(50.0, 20.0, 0), 30.0);
+    }
+
+    #[test]
+    fn runtime_parameter_changes_the_next_sample() {
+        let (mut with_cores, capture) = collection(&["--cpu"]);
+        let config = Arc::new(RuntimeConfig::default());
+        with_cores.use_runtime_config(config.clone());
+        with_cores.log_cpu();
+        config.set(&foxglove::websocket::Parameter {
+            name: "cpu.include_cores".to_string(),
+            r#type: None,
+            value: Some(foxglove::websocket::ParameterValue::Bool(false)),
         });
+        with_cores.log_cpu();
+
+        let samples = capture.messages(CPU.topic());
+        assert_eq!(samples.len(), 2);
+        let cores = |sample: &Value| sample["cores"].as_array().unwrap().len();
+        assert_eq!(cores(&samples[0]), with_cores.system.cpus().len());
+        assert!(!samples[0]["cores_truncated"].as_bool().unwrap());
+        assert_eq!(cores(&samples[1]), 0);
+        assert!(samples[1]["cores_truncated"].as_bool().unwrap());
+    }
+
+    #[test]
+    fn count_only_logs_only_the_counts() {
+        let (mut counting, capture) = collection(&["--count-only"]);
+        counting.log_all();
+        let topics: Vec<&String> = counting.sample_counts().keys().collect();
+        assert_eq!(topics, [COUNTS.topic()]);
+        let counts = &capture.messages(COUNTS.topic())[0];
+        let mut fields: Vec<&String> = counts.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            [
+                "cpu_count",
+                "disk_count",
+                "network_count",
+                "process_count",
+                "sensor_count",
+                "seq"
+            ]
+        );
+        assert!(counts["cpu_count"].as_u64().unwrap() > 0);
+        assert!(counts["process_count"].as_u64().unwrap() > 0);
+
+        let conflicting = Cli::try_parse_from(["fox-monitor", "--count-only", "--processes"]);
+        assert!(conflicting.is_err());
+    }
+
+    #[test]
+    fn empty_cpu_list_is_left_alone() {
+        let mut stats = cpu(Vec::new());
+        hoist_cpu_identity(&mut stats);
+        assert_eq!(stats.vendor_id, None);
+        assert_eq!(stats.brand, None);
+        keep_busiest_cores(&mut stats, 0);
+        assert!(stats.cores.is_empty());
+        assert!(!stats.cores_truncated);
+    }
+
+    #[test]
+    fn empty_process_list_is_retried_then_given_up() {
+        let (mut retrying, _capture) = collection(&["--processes", "--refresh-retries", "2"]);
+        retrying.system = System::new();
+        let attempts = std::cell::Cell::new(0);
+        retrying.refresh_all_processes(|_| attempts.set(attempts.get() + 1));
+        assert_eq!(attempts.get(), 3);
+        assert!(retrying.system.processes().is_empty());
+
+        let attempts = std::cell::Cell::new(0);
+        retrying.refresh_all_processes(|system| {
+            attempts.set(attempts.get() + 1);
+            system.refresh_processes(ProcessesToUpdate::All, true);
+        });
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn scheduling_is_only_read_when_requested() {
+        let pid = std::process::id();
+        let scheduling = |args: &[&str]| {
+            let (processes, _capture) = collection(args);
+            let process = processes.system.process(Pid::from_u32(pid)).unwrap();
+            let stats = processes.process_stats(pid, process);
+            (stats.priority, stats.nice)
+        };
+        assert_eq!(scheduling(&["--processes"]), (None, None));
+        let (priority, nice) = scheduling(&["--processes", "--process-scheduling"]);
+        if cfg!(target_os = "linux") {
+            assert!(priority.is_some());
+            assert!(nice.is_some_and(|nice| (-20..=19).contains(&nice)));
+        }
+    }
+
+    #[test]
+    fn processes_in_d_state_are_listed_by_pid() {
+        let stats = blocked_processes(
+            [
+                (30, ProcessStatus::UninterruptibleDiskSleep, "nfsd", 5),
+                (10, ProcessStatus::Run, "bash", 100),
+                (
+                    20,
+                    ProcessStatus::UninterruptibleDiskSleep,
+                    "jbd2/sda1-8",
+                    7,
+                ),
+                (40, ProcessStatus::Sleep, "sshd", 9),
+            ]
+            .into_iter()
+            .map(|(pid, status, name, run_time)| (pid, status, Cow::Borrowed(name), run_time)),
+        );
+        let blocked: Vec<(u32, &str, u64)> = stats
+            .processes
+            .iter()
+            .map(|process| (process.pid, process.name.as_str(), process.run_time_seconds))
+            .collect();
+        assert_eq!(blocked, [(20, "jbd2/sda1-8", 7), (30, "nfsd", 5)]);
+        assert!(blocked_processes(std::iter::empty()).processes.is_empty());
     }
 }
@@ -0,0 +1,81 @@
+// Sets up the diagnostics of the monitor itself, either as plain text or as one JSON object per
+// line for log pipelines.
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line with the level, target, timestamp and message
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRecord<'a> {
+    timestamp: String,
+    level: &'a str,
+    target: &'a str,
+    message: String,
+}
+
+/// Initializes the global logger, filtered by `RUST_LOG` and defaulting to debug
+pub fn init(format: LogFormat) {
+    let env = env_logger::Env::default().default_filter_or("debug");
+    let mut builder = env_logger::Builder::from_env(env);
+    if let LogFormat::Json = format {
+        builder.format(|buf, record| {
+            let timestamp = buf.timestamp().to_string();
+            write_json(buf, timestamp, record)
+        });
+    }
+    builder.init();
+}
+
+/// Writes `record` as a JSON object on its own line
+fn write_json(
+    out: &mut impl Write,
+    timestamp: String,
+    record: &log::Record,
+) -> std::io::Result<()> {
+    let json_record = JsonRecord {
+        timestamp,
+        level: record.level().as_str(),
+        target: record.target(),
+        message: record.args().to_string(),
+    };
+    serde_json::to_writer(&mut *out, &json_record).map_err(std::io::Error::other)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_line_has_the_level_target_and_message() {
+        let mut line = Vec::new();
+        write_json(
+            &mut line,
+            "2024-01-02T03:04:05Z".to_string(),
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("fox_monitor::logger")
+                .args(format_args!("Disabling {} after {} failures", "cpu", 3))
+                .build(),
+        )
+        .unwrap();
+
+        let line = String::from_utf8(line).unwrap();
+        assert_eq!(line.matches('\n').count(), 1);
+        assert!(line.ends_with('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["target"], "fox_monitor::logger");
+        assert_eq!(json["message"], "Disabling cpu after 3 failures");
+        assert_eq!(json["timestamp"], "2024-01-02T03:04:05Z");
+    }
+}
@@ -1,69 +1,872 @@
+mod adaptive;
+mod alerts;
+mod cgroup;
 mod channels;
+mod ci;
+mod clock;
+mod cpu_times;
+mod diskstats;
+mod duration;
+mod entropy;
+mod gpu;
+mod index;
+mod inodes;
+mod jsonl;
+mod kernel_fs;
+mod link;
 mod logger;
+mod logging;
+mod manifest;
+mod minimal;
+mod nonfinite;
+mod otlp;
+mod output;
+mod output_queue;
+mod parameters;
+mod payload;
+mod physical_disks;
+mod power;
+mod pressure;
+mod privileges;
+mod process_history;
+mod process_io;
+mod process_tree;
+mod procfs;
+mod profile;
+mod prometheus;
+mod push;
+mod recording;
+mod remote;
+mod replay;
+mod report;
+mod rfc3339;
+mod rollup;
+mod scheduling;
+mod services;
+mod smart;
+mod sockets;
+mod stall;
+mod statsd;
+mod tags;
+mod temperature;
+mod throttling;
+mod topology;
+mod verify;
+mod version;
+mod watchdog;
+mod websocket;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
 };
 
-use foxglove::McapWriter;
+use adaptive::{AdaptiveInterval, LoadThresholds};
+use jsonl::JsonlOutput;
 use logger::LoggerCollection;
+use logging::LogFormat;
+use otlp::OtlpExporter;
+use output::Outputs;
+use output_queue::BackpressurePolicy;
+use parameters::RuntimeConfig;
+use payload::NanPolicy;
+use privileges::Probes;
+use profile::Profile;
+use prometheus::PrometheusExporter;
+use push::Pusher;
+use recording::McapOutput;
+use report::RunReport;
+use rfc3339::TimeZone;
+use stall::Heartbeat;
+use statsd::StatsdSender;
+use temperature::TemperatureUnit;
+use version::VersionInfo;
+use websocket::WebsocketOutput;
 
-#[derive(Debug, Clone, clap::ValueEnum)]
+/// Default mcap file name, also used inside a directory passed as --path
+const DEFAULT_PATH: &str = "output.mcap";
+
+#[derive(Debug, Clone, PartialEq, clap::ValueEnum)]
 enum OutputFormat {
     Mcap,
     Websocket,
-    Both,
+    Jsonl,
+    Prometheus,
 }
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
+    /// Preset of metrics and interval, explicit flags take precedence
+    #[arg(long, value_enum, env = "FOX_MONITOR_PROFILE")]
+    profile: Option<Profile>,
     /// Log cpu info
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_CPU")]
     cpu: bool,
+    /// Log the cumulative idle and busy time of each core (Linux only)
+    #[arg(long, requires = "cpu", env = "FOX_MONITOR_CPU_RAW_COUNTERS")]
+    cpu_raw_counters: bool,
+    /// Log the vendor and brand once on /cpu instead of on every core, when every core has the
+    /// same
+    #[arg(long, requires = "cpu", env = "FOX_MONITOR_COMPACT_CPU")]
+    compact_cpu: bool,
+    /// Subtract the cpu usage of the monitor itself from the global usage on /cpu. This is an
+    /// approximation: the usage of the monitor is sampled right before the global one, and the
+    /// per-core usages are left as they are
+    #[arg(long, requires = "cpu", env = "FOX_MONITOR_EXCLUDE_SELF")]
+    exclude_self: bool,
+    /// Only log the N busiest cores on /cpu, the overall usage still covers every core
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        requires = "cpu",
+        env = "FOX_MONITOR_MAX_CORES"
+    )]
+    max_cores: Option<usize>,
+    /// Use the lightest refreshes and ignore the metrics that enumerate processes, disks,
+    /// networks or sensors, for small devices
+    #[arg(long, env = "FOX_MONITOR_MINIMAL")]
+    minimal: bool,
     /// Log memory info
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_MEMORY")]
     memory: bool,
+    /// Only refresh the RAM fields of the memory info, the swap fields keep their startup value
+    #[arg(
+        long,
+        requires = "memory",
+        conflicts_with = "swap_only",
+        env = "FOX_MONITOR_MEMORY_NO_SWAP"
+    )]
+    memory_no_swap: bool,
+    /// Only refresh the swap fields of the memory info, the RAM fields keep their startup value
+    #[arg(long, requires = "memory", env = "FOX_MONITOR_SWAP_ONLY")]
+    swap_only: bool,
+    /// Read the memory on /memory and the load averages on /system from /proc/meminfo and
+    /// /proc/loadavg when sysinfo reports zeros but the files have values, as in some restricted
+    /// containers
+    #[arg(long, env = "FOX_MONITOR_PROC_FALLBACK")]
+    proc_fallback: bool,
     /// Log components temperature
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_TEMPERATURE")]
     temperature: bool,
+    /// Unit of the logged temperatures, on /components, /temperature_summary and /smart
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TemperatureUnit::Celsius,
+        env = "FOX_MONITOR_TEMP_UNIT"
+    )]
+    temp_unit: TemperatureUnit,
     /// Log disks info
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_DISKS")]
     disks: bool,
+    /// Only log real block devices, leaving out pseudo filesystems like overlay, tmpfs or squashfs
+    #[arg(long, requires = "disks", env = "FOX_MONITOR_PHYSICAL_DISKS_ONLY")]
+    physical_disks_only: bool,
+    /// Also leave out disks with this filesystem, can be repeated
+    #[arg(
+        long,
+        value_name = "NAME",
+        requires = "physical_disks_only",
+        env = "FOX_MONITOR_EXCLUDE_FS",
+        value_delimiter = ','
+    )]
+    exclude_fs: Vec<String>,
     /// Log networks info
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_NETWORKS")]
     networks: bool,
+    /// Log the sum of all non-loopback interfaces on /network_total instead of each interface
+    #[arg(long, requires = "networks", env = "FOX_MONITOR_NETWORK_AGGREGATE")]
+    network_aggregate: bool,
+    /// Include the loopback interfaces in the network aggregate
+    #[arg(
+        long,
+        requires = "network_aggregate",
+        env = "FOX_MONITOR_NETWORK_INCLUDE_LOOPBACK"
+    )]
+    network_include_loopback: bool,
+    /// Log the disks and networks in the order reported by the system instead of sorted by mount
+    /// point and interface name
+    #[arg(long, env = "FOX_MONITOR_NO_SORT")]
+    no_sort: bool,
+    /// Report the disks and networks totals relative to the first sample instead of since boot
+    #[arg(long, env = "FOX_MONITOR_RELATIVE_COUNTERS")]
+    relative_counters: bool,
     /// Log processes info
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_PROCESSES")]
     processes: bool,
+    /// Log a compact version of the processes info (pid, name, cpu and memory usage only)
+    #[arg(long, requires = "processes", env = "FOX_MONITOR_COMPACT_PROCESSES")]
+    compact_processes: bool,
+    /// Only log this process and all its descendants, following children as they spawn and exit
+    #[arg(
+        long,
+        value_name = "PID",
+        requires = "processes",
+        env = "FOX_MONITOR_FOLLOW_PID"
+    )]
+    follow_pid: Option<u32>,
+    /// Log a history of the cpu and memory usage of this process on /tracked_processes, can be
+    /// repeated
+    #[arg(
+        long,
+        value_name = "PID",
+        env = "FOX_MONITOR_TRACK_PID",
+        value_delimiter = ','
+    )]
+    track_pid: Vec<u32>,
+    /// Number of samples kept in the history of each tracked process
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 60,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        env = "FOX_MONITOR_TRACK_HISTORY"
+    )]
+    track_history: usize,
+    /// Log the process cpu usage as a percentage of the whole machine instead of a single core,
+    /// where a process using several cores exceeds 100%
+    #[arg(
+        long,
+        requires = "processes",
+        env = "FOX_MONITOR_PROCESS_CPU_NORMALIZED"
+    )]
+    process_cpu_normalized: bool,
+    /// Count the open sockets of each logged process (Linux only)
+    #[arg(long, requires = "processes", env = "FOX_MONITOR_PROCESS_SOCKETS")]
+    process_sockets: bool,
+    /// Read the scheduling priority and nice value of each logged process (Linux only)
+    #[arg(long, requires = "processes", env = "FOX_MONITOR_PROCESS_SCHEDULING")]
+    process_scheduling: bool,
+    /// Only log processes that have been running for at least this many seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "processes",
+        env = "FOX_MONITOR_MIN_PROCESS_AGE"
+    )]
+    min_process_age: Option<u64>,
+    /// Only log processes that have been running for at most this many seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "processes",
+        env = "FOX_MONITOR_MAX_PROCESS_AGE"
+    )]
+    max_process_age: Option<u64>,
+    /// Only log processes using at least this many kb of resident memory
+    #[arg(
+        long,
+        value_name = "KB",
+        requires = "processes",
+        env = "FOX_MONITOR_MIN_PROCESS_MEMORY_KB"
+    )]
+    min_process_memory_kb: Option<u64>,
+    /// Only log the processes when a process started or exited since the previous sample
+    #[arg(long, requires = "processes", env = "FOX_MONITOR_PROCESSES_ON_CHANGE")]
+    processes_on_change: bool,
+    /// Also log every process, ignoring the processes filters, on /processes_full at this slower
+    /// interval
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "processes",
+        env = "FOX_MONITOR_FULL_SNAPSHOT_EVERY"
+    )]
+    full_snapshot_every: Option<u64>,
     /// Log system info
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_SYSTEM")]
     system: bool,
-    /// Interval between logs in seconds
-    #[arg(short, long, default_value_t = 1)]
+    /// Only log the number of cpus, disks, networks, processes and sensors on /counts, with the
+    /// lightest refreshes, as a cheap liveness metric
+    #[arg(
+        long,
+        conflicts_with_all = ["cpu", "temperature", "disks", "networks", "processes"],
+        env = "FOX_MONITOR_COUNT_ONLY"
+    )]
+    count_only: bool,
+    /// Log the static system facts (host name, OS, kernel, core count) once on /info at startup,
+    /// leaving only the dynamic values on /system
+    #[arg(long, env = "FOX_MONITOR_INFO")]
+    info: bool,
+    /// Log cgroup resource limits (Linux only)
+    #[arg(long, env = "FOX_MONITOR_CGROUP")]
+    cgroup: bool,
+    /// Log cpu, memory and io pressure stall information (Linux only)
+    #[arg(long, env = "FOX_MONITOR_PRESSURE")]
+    pressure: bool,
+    /// Log services info (Windows only)
+    #[arg(long, env = "FOX_MONITOR_SERVICES")]
+    services: bool,
+    /// Log the processes using an NVIDIA GPU and their video memory (requires nvidia-smi)
+    #[arg(long, env = "FOX_MONITOR_GPU_PROCESSES")]
+    gpu_processes: bool,
+    /// Log the processes in uninterruptible sleep (D state) on /blocked_processes, a sign of a
+    /// hung disk or network filesystem
+    #[arg(long, env = "FOX_MONITOR_BLOCKED_PROCESSES")]
+    blocked_processes: bool,
+    /// Log the SMART health, temperature and power on hours of the disks (requires smartctl and
+    /// usually root)
+    #[arg(long, env = "FOX_MONITOR_SMART")]
+    smart: bool,
+    /// Log the cpu and memory usage of the monitor itself on /self
+    #[arg(long, env = "FOX_MONITOR_SELF_MONITOR")]
+    self_monitor: bool,
+    /// Log cpu, gpu and package power draw (macOS only, requires root)
+    #[arg(long, env = "FOX_MONITOR_POWER")]
+    power: bool,
+    /// Log the monitor's own status, like the achieved sampling rate
+    #[arg(long, env = "FOX_MONITOR_STATUS")]
+    status: bool,
+    /// Log the min, average and max of the cpu usage, the used memory and the load on /rollup
+    /// every this many seconds, or a duration like 5m
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = duration::parse_seconds,
+        env = "FOX_MONITOR_SUMMARY_INTERVAL"
+    )]
+    summary_interval: Option<u64>,
+    /// Log an alert on /alerts when the memory usage reaches this percentage
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        requires = "memory",
+        env = "FOX_MONITOR_MEM_ALERT_HIGH"
+    )]
+    mem_alert_high: Option<f64>,
+    /// Clear the memory alert once the usage falls to this percentage, defaults to the high one
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        requires = "mem_alert_high",
+        env = "FOX_MONITOR_MEM_ALERT_LOW"
+    )]
+    mem_alert_low: Option<f64>,
+    /// Log an alert on /alerts when the usage of a disk reaches this percentage
+    #[arg(
+        long,
+        visible_alias = "disk-alert-percent",
+        value_name = "PERCENT",
+        requires = "disks",
+        env = "FOX_MONITOR_DISK_ALERT_HIGH"
+    )]
+    disk_alert_high: Option<f64>,
+    /// Alert when the usage of the disk mounted at this point reaches this percentage, overriding
+    /// --disk-alert-high for it, e.g. /var=95, can be repeated
+    #[arg(
+        long,
+        value_name = "MOUNT=PERCENT",
+        value_parser = alerts::parse_mount_threshold,
+        requires = "disks",
+        env = "FOX_MONITOR_DISK_ALERT",
+        value_delimiter = ','
+    )]
+    disk_alert: Vec<(PathBuf, f64)>,
+    /// Clear a disk alert once the usage falls to this percentage, for --disk-alert-high and the
+    /// --disk-alert mounts, defaults to the high one
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        requires = "disks",
+        env = "FOX_MONITOR_DISK_ALERT_LOW"
+    )]
+    disk_alert_low: Option<f64>,
+    /// Interval between logs, in seconds or as a duration like 30s, 5m or 1h
+    #[arg(
+        short,
+        long,
+        default_value = "1",
+        value_parser = duration::parse_seconds,
+        env = "FOX_MONITOR_INTERVAL"
+    )]
     interval: u64,
-    /// If provided, the program will exit after the timeout (in seconds)
-    #[arg(long)]
+    /// Double the interval, up to --adaptive-max-interval, while the machine is overloaded and go
+    /// back to --interval once it recovers
+    #[arg(long, env = "FOX_MONITOR_ADAPTIVE_INTERVAL")]
+    adaptive_interval: bool,
+    /// Longest interval in seconds the adaptive interval backs off to
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 60,
+        requires = "adaptive_interval",
+        env = "FOX_MONITOR_ADAPTIVE_MAX_INTERVAL"
+    )]
+    adaptive_max_interval: u64,
+    /// 1 minute load average per cpu above which the machine counts as overloaded
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        requires = "adaptive_interval",
+        env = "FOX_MONITOR_ADAPTIVE_LOAD_THRESHOLD"
+    )]
+    adaptive_load_threshold: f64,
+    /// Global cpu usage in percent above which the machine counts as overloaded
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = 90.0,
+        requires = "adaptive_interval",
+        env = "FOX_MONITOR_ADAPTIVE_CPU_THRESHOLD"
+    )]
+    adaptive_cpu_threshold: f32,
+    /// Timestamp each message with the wall clock, which can go backwards when it is adjusted,
+    /// instead of the monotonic time since startup
+    #[arg(long, env = "FOX_MONITOR_WALL_CLOCK_TIMESTAMPS")]
+    wall_clock_timestamps: bool,
+    /// Add this many nanoseconds, possibly negative, to every timestamp to line the capture up
+    /// with an external clock
+    #[arg(
+        long,
+        value_name = "NS",
+        default_value_t = 0,
+        allow_negative_numbers = true,
+        env = "FOX_MONITOR_TIMESTAMP_OFFSET_NS"
+    )]
+    timestamp_offset_ns: i64,
+    /// Round float fields to this many decimal places
+    #[arg(long, value_name = "DIGITS", env = "FOX_MONITOR_ROUND")]
+    round: Option<u32>,
+    /// Pretty-print the JSON messages written to the mcap file and the websocket, for human
+    /// inspection, instead of one compact line per message. Not available with --format jsonl,
+    /// which needs one line per message
+    #[arg(long, env = "FOX_MONITOR_JSON_PRETTY")]
+    json_pretty: bool,
+    /// Skip the samples of metrics that list no items, e.g. the disks or temperature sensors of a
+    /// host without them, instead of logging an empty list
+    #[arg(long, env = "FOX_MONITOR_OMIT_EMPTY")]
+    omit_empty: bool,
+    /// Time zone of the human readable timestamps: utc, local or an IANA name like Europe/Paris
+    #[arg(
+        long,
+        value_name = "TZ",
+        default_value = "utc",
+        value_parser = rfc3339::parse_time_zone,
+        env = "FOX_MONITOR_TIMEZONE"
+    )]
+    timezone: TimeZone,
+    /// How to encode NaN and infinite float fields
+    #[arg(long, value_enum, default_value_t = NanPolicy::Null, env = "FOX_MONITOR_NAN_POLICY")]
+    nan_policy: NanPolicy,
+    /// Buffer this many iterations in memory and log them together
+    #[arg(long, value_name = "N", default_value_t = 1, env = "FOX_MONITOR_BATCH")]
+    batch: u64,
+    /// If provided, the program will exit after the timeout, in seconds or as a duration like
+    /// 30s, 5m, 2h or 1d
+    #[arg(
+        long,
+        visible_alias = "duration",
+        value_parser = duration::parse_seconds,
+        env = "FOX_MONITOR_TIMEOUT"
+    )]
     timeout: Option<u64>,
-    /// Output format (mcap file, websocket server, or both)
-    #[arg(short, long, value_enum, default_value_t = OutputFormat::Both)]
-    format: OutputFormat,
-    /// Output path for mcap file
-    #[arg(long, default_value = "output.mcap")]
+    /// Exit with an error when no iteration completes within this many seconds, must be longer
+    /// than the interval
+    #[arg(long, value_name = "SECONDS", env = "FOX_MONITOR_STALL_TIMEOUT")]
+    stall_timeout: Option<u64>,
+    /// Disable a metric after it fails this many times in a row
+    #[arg(long, default_value_t = 3, env = "FOX_MONITOR_MAX_METRIC_FAILURES")]
+    max_metric_failures: u32,
+    /// Refresh the process list again up to this many times, after a short delay, when it comes
+    /// back empty, as can happen on a heavily loaded machine
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        env = "FOX_MONITOR_REFRESH_RETRIES"
+    )]
+    refresh_retries: u32,
+    /// Log how many items (cpus, sensors, disks, ...) each enabled metric finds at startup, and
+    /// why a metric can't read any, then run normally
+    #[arg(long, env = "FOX_MONITOR_PROBE")]
+    probe: bool,
+    /// Exit at startup when an enabled metric that needs privileges (temperature, SMART, power)
+    /// can't be read, instead of logging it empty or disabling it
+    #[arg(long, env = "FOX_MONITOR_FAIL_FAST")]
+    fail_fast: bool,
+    /// If provided, the program will exit after logging this many samples
+    #[arg(long, env = "FOX_MONITOR_MAX_SAMPLES")]
+    max_samples: Option<u64>,
+    /// Stop logging processes after this many samples, while other metrics continue
+    #[arg(long, value_name = "N", env = "FOX_MONITOR_PROCESSES_MAX_SAMPLES")]
+    processes_max_samples: Option<u64>,
+    /// Stop logging system information after this many samples, while other metrics continue
+    #[arg(long, value_name = "N", env = "FOX_MONITOR_SYSTEM_MAX_SAMPLES")]
+    system_max_samples: Option<u64>,
+    /// Stop logging services after this many samples, while other metrics continue
+    #[arg(long, value_name = "N", env = "FOX_MONITOR_SERVICES_MAX_SAMPLES")]
+    services_max_samples: Option<u64>,
+    /// Collect but don't log the first N samples, which often have bogus rates
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        env = "FOX_MONITOR_SKIP_FIRST"
+    )]
+    skip_first: u64,
+    /// Collect but don't log or publish the samples of the first N seconds, so connecting
+    /// dashboards only see settled values. Combined with --skip-first, a sample is skipped while
+    /// either applies.
+    #[arg(long, value_name = "N", env = "FOX_MONITOR_WARMUP_SECONDS")]
+    warmup_seconds: Option<u64>,
+    /// Outputs to write to at once, e.g. mcap,jsonl,prometheus (mcap file, websocket server, JSON
+    /// lines on stdout, Prometheus metrics endpoint)
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values = ["mcap", "websocket"],
+        env = "FOX_MONITOR_FORMAT"
+    )]
+    format: Vec<OutputFormat>,
+    /// Output path for mcap file, an existing directory gets the file named output.mcap inside it
+    #[arg(long, default_value = DEFAULT_PATH, env = "FOX_MONITOR_PATH")]
     path: PathBuf,
+    /// Write each channel to its own mcap file named after its topic, e.g. cpu.mcap, in the
+    /// directory of --path
+    #[arg(long, env = "FOX_MONITOR_SPLIT_FILES")]
+    split_files: bool,
+    /// Add the mcap files of each run to a JSON index next to --path, e.g. output.index.json,
+    /// with the time range and message count of each file
+    #[arg(long, env = "FOX_MONITOR_MERGE_RUNS")]
+    merge_runs: bool,
+    /// Start the websocket server even when running in CI, where it is left out when --format
+    /// lists other outputs
+    #[arg(long, env = "FOX_MONITOR_FORCE_WEBSOCKET")]
+    force_websocket: bool,
+    /// Write the messages from a separate thread through a bounded queue, and what to do when
+    /// the queue is full
+    #[arg(long, value_enum, env = "FOX_MONITOR_ON_BACKPRESSURE")]
+    on_backpressure: Option<BackpressurePolicy>,
+    /// Address the Prometheus metrics are served on, at /metrics
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        default_value = "127.0.0.1:9464",
+        env = "FOX_MONITOR_PROMETHEUS_ADDRESS"
+    )]
+    prometheus_address: SocketAddr,
+    /// Don't restart the websocket server when it stops responding
+    #[arg(long, env = "FOX_MONITOR_WS_NO_RESTART")]
+    ws_no_restart: bool,
     /// If set, overwrite an existing mcap file
-    #[arg(short, long)]
+    #[arg(short, long, env = "FOX_MONITOR_OVERWRITE")]
     overwrite: bool,
+    /// Fail when the directory of --path doesn't exist instead of creating it
+    #[arg(long, env = "FOX_MONITOR_NO_CREATE_DIRS")]
+    no_create_dirs: bool,
+    /// Skip logging a sample when it is identical to the previous one on the same channel
+    #[arg(long, env = "FOX_MONITOR_DEDUPE")]
+    dedupe: bool,
+    /// Log deduplicated channels at least once every this many seconds
+    #[arg(
+        long,
+        default_value_t = 60,
+        requires = "dedupe",
+        env = "FOX_MONITOR_DEDUPE_KEEPALIVE"
+    )]
+    dedupe_keepalive: u64,
+    /// Also POST the samples as JSON to this http:// collector url
+    #[arg(long, value_parser = push::parse_url, env = "FOX_MONITOR_PUSH_URL")]
+    push_url: Option<push::PushUrl>,
+    /// Seconds between two pushes to the collector
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 10,
+        requires = "push_url",
+        env = "FOX_MONITOR_PUSH_INTERVAL"
+    )]
+    push_interval: u64,
+    /// Also export the numeric fields of every sample as OpenTelemetry gauges to this OTLP/HTTP
+    /// endpoint, e.g. http://localhost:4318 (JSON encoding, http:// only)
+    #[arg(long, value_parser = push::parse_url, env = "FOX_MONITOR_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<push::PushUrl>,
+    /// Also send the numeric fields of every sample as StatsD gauges over UDP to this server
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        value_parser = statsd::parse_address,
+        env = "FOX_MONITOR_STATSD"
+    )]
+    statsd: Option<String>,
+    /// Prefix of the StatsD gauge names
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "fox",
+        requires = "statsd",
+        env = "FOX_MONITOR_STATSD_PREFIX"
+    )]
+    statsd_prefix: String,
+    /// Tag the StatsD gauges with the host name and the --label tags, DogStatsD style
+    #[arg(long, requires = "statsd", env = "FOX_MONITOR_STATSD_TAGS")]
+    statsd_tags: bool,
+    /// Reopen the mcap file after closing it and fail if it is corrupt or empty
+    #[arg(long, env = "FOX_MONITOR_VERIFY")]
+    verify: bool,
+    /// If provided, write a JSON summary of the run to this path on exit
+    #[arg(long, env = "FOX_MONITOR_RUN_REPORT")]
+    run_report: Option<PathBuf>,
+    /// Only register and log the channel with this topic, can be repeated
+    #[arg(
+        long,
+        value_name = "NAME",
+        env = "FOX_MONITOR_TOPIC",
+        value_delimiter = ','
+    )]
+    topic: Vec<String>,
+    /// Tag the run with a key=value pair, can be repeated
+    #[arg(long, value_parser = tags::parse_label, env = "FOX_MONITOR_LABEL", value_delimiter = ',')]
+    label: Vec<(String, String)>,
+    /// JSON or key=value file with tags for the run, --label entries take precedence
+    #[arg(long, env = "FOX_MONITOR_TAGS_FILE")]
+    tags_file: Option<PathBuf>,
+    /// Format of the monitor's own diagnostics
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, env = "FOX_MONITOR_LOG_FORMAT")]
+    log_format: LogFormat,
+    /// Publish the messages of a recorded mcap file again instead of logging metrics
+    #[arg(long, env = "FOX_MONITOR_REPLAY_FILE")]
+    replay_file: Option<PathBuf>,
+    /// Replay this many times faster than recorded
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        requires = "replay_file",
+        env = "FOX_MONITOR_REPLAY_SPEED"
+    )]
+    replay_speed: f64,
+    /// Log the metrics of a remote host instead of this one, running --remote-command there over
+    /// SSH
+    #[arg(
+        long,
+        value_name = "USER@HOST",
+        conflicts_with = "replay_file",
+        env = "FOX_MONITOR_REMOTE"
+    )]
+    remote: Option<String>,
+    /// Command that runs fox-monitor on the remote host, with the metric flags to collect there
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        default_value = "fox-monitor",
+        requires = "remote",
+        env = "FOX_MONITOR_REMOTE_COMMAND"
+    )]
+    remote_command: String,
+    /// Write the topic and schema name of every channel, and whether the other flags enable it,
+    /// as JSON to this path and exit
+    #[arg(long, value_name = "PATH", env = "FOX_MONITOR_CHANNELS_MANIFEST")]
+    channels_manifest: Option<PathBuf>,
+    /// Print the detected temperature sensors and their current temperature and exit
+    #[arg(long, env = "FOX_MONITOR_LIST_SENSORS")]
+    list_sensors: bool,
+    /// Print version and build information as JSON and exit
+    #[arg(long, env = "FOX_MONITOR_VERSION_JSON")]
+    version_json: bool,
+}
+
+impl Cli {
+    fn writes_mcap(&self) -> bool {
+        self.format.contains(&OutputFormat::Mcap)
+    }
+
+    fn serves_websocket(&self) -> bool {
+        self.format.contains(&OutputFormat::Websocket)
+    }
+
+    /// Rejects the flags that don't apply to the selected output formats, which clap can't express
+    /// since --format has a default
+    fn check_output_conflicts(&self) -> Result<(), clap::Error> {
+        let conflict =
+            |message| Err(Cli::command().error(clap::error::ErrorKind::ArgumentConflict, message));
+        if self.json_pretty && self.writes_jsonl() {
+            return conflict(
+                "--json-pretty can't be used with --format jsonl, which writes one message per line",
+            );
+        }
+        if self.verify && !self.writes_mcap() {
+            return conflict("--verify checks the mcap file, but --format doesn't write one");
+        }
+        Ok(())
+    }
+
+    /// Writes to `output.mcap` inside --path when it is an existing directory, returning whether
+    /// it is
+    fn write_inside_path_directory(&mut self) -> bool {
+        if !self.path.is_dir() {
+            return false;
+        }
+        self.path = self.path.join(DEFAULT_PATH);
+        true
+    }
+
+    /// Stops serving the websocket when `ci_variable` marks the environment as CI, where nobody
+    /// connects to it, unless it is the only output or --force-websocket is set. Returns the
+    /// variable when the websocket was dropped.
+    fn drop_websocket_on_ci(&mut self, ci_variable: Option<&'static str>) -> Option<&'static str> {
+        if !self.serves_websocket() || self.format.len() < 2 || self.force_websocket {
+            return None;
+        }
+        let variable = ci_variable?;
+        self.format
+            .retain(|format| *format != OutputFormat::Websocket);
+        Some(variable)
+    }
+
+    fn serves_prometheus(&self) -> bool {
+        self.format.contains(&OutputFormat::Prometheus)
+    }
+
+    fn writes_jsonl(&self) -> bool {
+        self.format.contains(&OutputFormat::Jsonl)
+    }
 }
 
 fn main() {
-    let env = env_logger::Env::default().default_filter_or("debug");
-    env_logger::init_from_env(env);
+    let matches = Cli::command().get_matches();
+    let mut args = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    logging::init(args.log_format);
+    if args.version_json {
+        println!(
+            "{}",
+            serde_json::to_string(&VersionInfo::current()).expect("Failed to serialize version")
+        );
+        return;
+    }
+    if args.list_sensors {
+        let sensors: Vec<(String, Option<f32>)> = sysinfo::Components::new_with_refreshed_list()
+            .iter()
+            .map(|component| (component.label().to_string(), component.temperature()))
+            .collect();
+        print!("{}", list_sensors(&sensors));
+        return;
+    }
+    if let Some(profile) = args.profile {
+        profile.apply(&mut args, &matches);
+    }
+    if args.minimal {
+        minimal::apply(&mut args);
+    }
+    if let Some(path) = &args.channels_manifest {
+        manifest::write(path, &args).expect("Failed to write channels manifest");
+        return;
+    }
+    if let Err(e) = args.check_output_conflicts() {
+        e.exit();
+    }
+    if args.disk_alert_low.is_some() && args.disk_alert_high.is_none() && args.disk_alert.is_empty()
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "--disk-alert-low needs --disk-alert-high or --disk-alert",
+            )
+            .exit();
+    }
+    if args.adaptive_interval && args.adaptive_max_interval < args.interval {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--adaptive-max-interval must not be shorter than --interval",
+            )
+            .exit();
+    }
+    if args
+        .stall_timeout
+        .is_some_and(|stall_timeout| stall_timeout <= args.interval)
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--stall-timeout must be longer than --interval",
+            )
+            .exit();
+    }
+    if args.adaptive_interval
+        && args
+            .stall_timeout
+            .is_some_and(|stall_timeout| stall_timeout <= args.adaptive_max_interval)
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--stall-timeout must be longer than --adaptive-max-interval",
+            )
+            .exit();
+    }
+
+    if args.write_inside_path_directory() {
+        log::info!("--path is a directory, writing to {}", args.path.display());
+    }
+    if !clock::offset_is_valid(args.timestamp_offset_ns) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--timestamp-offset-ns would move the timestamps before the Unix epoch",
+            )
+            .exit();
+    }
+    if args.replay_speed <= 0.0 {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                "--replay-speed must be positive",
+            )
+            .exit();
+    }
+    if let Some(variable) = args.drop_websocket_on_ci(ci::detect()) {
+        log::info!(
+            "Running in CI ({variable} is set), not starting the websocket server, use \
+             --force-websocket to start it anyway"
+        );
+    }
+    let tags = match tags::resolve(args.tags_file.as_deref(), &args.label) {
+        Ok(tags) => tags,
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if args.fail_fast && args.remote.is_none() && args.replay_file.is_none() {
+        let failures = privileges::probe(&Probes {
+            temperature: args.temperature,
+            smart: args.smart,
+            power: args.power,
+        });
+        for failure in &failures {
+            log::error!("Can't log {}: {}", failure.metric, failure.reason);
+        }
+        if !failures.is_empty() {
+            log::error!("Exiting because of --fail-fast");
+            std::process::exit(1);
+        }
+    }
 
-    let args = Cli::parse();
+    // Check the mcap directory before starting the websocket server, so an unwritable one doesn't
+    // leave the run half started
+    if args.writes_mcap() {
+        let directory = recording::directory_of(&args.path);
+        if !args.no_create_dirs
+            && let Err(e) = recording::create_directory_of(&args.path)
+        {
+            log::error!(
+                "Can't create the mcap directory {}: {e}",
+                directory.display()
+            );
+            std::process::exit(1);
+        }
+        if let Err(e) = recording::check_writable(directory) {
+            log::error!("Can't write the mcap file to {}: {e}", directory.display());
+            std::process::exit(1);
+        }
+    }
 
     let done = Arc::new(AtomicBool::default());
     ctrlc::set_handler({
@@ -74,43 +877,367 @@ fn main() {
     })
     .expect("Failed to set SIGINT handler");
 
-    // Start websocket server if format is Websocket or Both
-    if matches!(args.format, OutputFormat::Websocket | OutputFormat::Both) {
-        foxglove::WebSocketServer::new()
-            .start_blocking()
+    // Start the outputs. The websocket clients can change the runtime parameters.
+    let runtime_config = Arc::new(RuntimeConfig::default());
+    let outputs = Outputs::new();
+    // Create the mcap files and bind the Prometheus address first, so an existing file or a taken
+    // port doesn't leave the other outputs half started
+    if args.writes_mcap() {
+        let mcap = if args.split_files {
+            let topics: Vec<&str> = logger::produced_topics(&args)
+                .into_iter()
+                .filter(|topic| args.topic.is_empty() || args.topic.iter().any(|t| t == topic))
+                .collect();
+            McapOutput::split(recording::directory_of(&args.path), args.overwrite, &topics)
+        } else {
+            McapOutput::single(&args.path, args.overwrite).map_err(std::io::Error::other)
+        };
+        match mcap {
+            Ok(mcap) => outputs.add(Box::new(mcap)),
+            Err(e) => {
+                log::error!("Can't write the mcap file: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if args.serves_prometheus() {
+        match PrometheusExporter::bind(args.prometheus_address) {
+            Ok(exporter) => outputs.add(Box::new(exporter)),
+            Err(e) => {
+                log::error!(
+                    "Can't serve the Prometheus metrics on {}: {e}",
+                    args.prometheus_address
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if args.serves_websocket() {
+        let server = WebsocketOutput::start(&runtime_config, !args.ws_no_restart)
             .expect("Server failed to start");
+        outputs.add(Box::new(server));
     }
-
-    // Initialize mcap writer if format is Mcap or Both
-    let mcap = if matches!(args.format, OutputFormat::Mcap | OutputFormat::Both) {
-        if args.overwrite && args.path.exists() {
-            std::fs::remove_file(&args.path).expect("Failed to remove file");
+    if args.writes_jsonl() {
+        outputs.add(Box::new(JsonlOutput::default()));
+    }
+    if let Some(url) = &args.push_url {
+        outputs.add(Box::new(Pusher::new(
+            url.clone(),
+            std::time::Duration::from_secs(args.push_interval),
+            tags.clone(),
+        )));
+    }
+    if let Some(url) = &args.otlp_endpoint {
+        match OtlpExporter::start(url.clone(), tags.clone()) {
+            Ok(exporter) => outputs.add(Box::new(exporter)),
+            Err(e) => {
+                log::error!("Can't start the OTLP exporter: {e}");
+                std::process::exit(1);
+            }
         }
-        Some(
-            McapWriter::new()
-                .create_new_buffered_file(&args.path)
-                .expect("Failed to start mcap writer"),
+    }
+    if let Some(address) = &args.statsd {
+        let statsd = StatsdSender::new(
+            address,
+            args.statsd_prefix.clone(),
+            args.statsd_tags.then(|| tags.clone()),
         )
-    } else {
-        None
+        .expect("Failed to open StatsD socket");
+        outputs.add(Box::new(statsd));
+    }
+
+    if let Some(path) = &args.replay_file {
+        let replayed = replay::replay_file(
+            path,
+            args.replay_speed,
+            args.timestamp_offset_ns,
+            &outputs,
+            &done,
+        );
+        close_outputs(&outputs);
+        match replayed {
+            Ok(count) => log::info!("Replayed {count} messages from {}", path.display()),
+            Err(e) => {
+                log::error!("Failed to replay {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(destination) = &args.remote {
+        let transport = remote::Ssh {
+            destination: destination.clone(),
+        };
+        let ingested = remote::ingest(&transport, &args.remote_command, &outputs, &done);
+        close_outputs(&outputs);
+        match ingested {
+            Ok(count) => log::info!("Logged {count} messages from {destination}"),
+            Err(e) => {
+                log::error!("Failed to log the metrics of {destination}: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut logger_collection = match LoggerCollection::new(&args, outputs.clone()) {
+        Ok(logger_collection) => logger_collection,
+        Err(e) => {
+            log::error!("Failed to start monitoring: {e}");
+            close_outputs(&outputs);
+            std::process::exit(1);
+        }
     };
 
-    let mut logger_collection = LoggerCollection::new(&args);
+    if args.probe {
+        for result in logger_collection.probe() {
+            match result.found {
+                Ok(count) => log::info!("Probe {}: {count} {}", result.metric, result.items),
+                Err(reason) => log::warn!("Probe {}: {reason}", result.metric),
+            }
+        }
+    }
+
+    logger_collection.use_runtime_config(runtime_config);
+
+    if !tags.is_empty() {
+        logger_collection.log_run_info(tags);
+    }
+    if args.info {
+        logger_collection.log_info();
+    }
+
+    let heartbeat = Heartbeat::new();
+    if let Some(stall_timeout) = args.stall_timeout {
+        stall::spawn_watchdog(
+            heartbeat.clone(),
+            std::time::Duration::from_secs(stall_timeout),
+            outputs.clone(),
+        );
+    }
 
+    let mut adaptive_interval = args.adaptive_interval.then(|| {
+        AdaptiveInterval::new(
+            args.interval,
+            args.adaptive_max_interval,
+            LoadThresholds {
+                load_per_cpu: args.adaptive_load_threshold,
+                cpu_percent: args.adaptive_cpu_threshold,
+            },
+        )
+    });
     let mut elapsed_time_seconds: u64 = 0;
-    // Loop until the user closes the program or the timeout is reached
+    // Loop until the user closes the program or the timeout or max samples are reached
     while !done.load(Ordering::Relaxed)
         && args
             .timeout
-            .map_or(true, |timeout| elapsed_time_seconds < timeout)
+            .is_none_or(|timeout| elapsed_time_seconds < timeout)
+        && args
+            .max_samples
+            .is_none_or(|max_samples| logger_collection.logged_iterations() < max_samples)
     {
         logger_collection.log_all();
-        std::thread::sleep(std::time::Duration::from_millis(args.interval * 1000));
-        elapsed_time_seconds += args.interval;
+        heartbeat.beat();
+        let interval = adaptive_interval
+            .as_mut()
+            .map_or(args.interval, AdaptiveInterval::next_interval);
+        std::thread::sleep(std::time::Duration::from_millis(interval * 1000));
+        elapsed_time_seconds += interval;
+    }
+
+    let mcap_paths = match logger_collection.close() {
+        Ok(paths) => paths,
+        Err(e) => {
+            log::error!("Failed to close the outputs: {e}");
+            std::process::exit(1);
+        }
+    };
+    let mcap_size_bytes = args.writes_mcap().then(|| {
+        mcap_paths
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    });
+
+    if args.merge_runs && args.writes_mcap() {
+        let index_path = index::index_path(&args.path);
+        match index::update(&index_path, &mcap_paths) {
+            Ok(()) => log::info!("Updated index {}", index_path.display()),
+            Err(e) => log::error!("Failed to update index {}: {e}", index_path.display()),
+        }
+    }
+
+    let report = RunReport::new(logger_collection.sample_counts().clone(), mcap_size_bytes);
+    // Stdout carries the messages in jsonl format
+    if !args.writes_jsonl() {
+        report.print();
+    }
+    if let Some(path) = &args.run_report
+        && let Err(e) = report.write(path)
+    {
+        log::error!("Failed to write the run report to {}: {e}", path.display());
+        std::process::exit(1);
+    }
+
+    if args.verify {
+        for path in &mcap_paths {
+            match verify::verify_file(path) {
+                Ok(counts) => {
+                    let total: u64 = counts.values().sum();
+                    log::info!(
+                        "Verified {}: {total} messages on {} channels",
+                        path.display(),
+                        counts.len()
+                    );
+                    for (topic, count) in counts {
+                        log::debug!("{topic}: {count} messages");
+                    }
+                }
+                Err(e) => {
+                    log::error!("Verification of {} failed: {e}", path.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+/// Closes the outputs of a run that doesn't log metrics, exiting when they fail
+fn close_outputs(outputs: &Outputs) {
+    if let Err(e) = outputs.close() {
+        log::error!("Failed to close the outputs: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Lists the label and current temperature of each sensor, as used by --temperature, one line per
+/// sensor below a header
+fn list_sensors(sensors: &[(String, Option<f32>)]) -> String {
+    if sensors.is_empty() {
+        return "No temperature sensors found\n".to_string();
+    }
+    let width = sensors
+        .iter()
+        .map(|(label, _)| label.len())
+        .chain(["Sensor".len()])
+        .max()
+        .unwrap_or_default();
+    let mut listing = format!("{:<width$}  Temperature\n", "Sensor");
+    for (label, temperature) in sensors {
+        match temperature {
+            Some(temperature) => listing += &format!("{label:<width$}  {temperature:.1} °C\n"),
+            None => listing += &format!("{label:<width$}  unknown\n"),
+        }
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn env_vars_set_the_flags_and_the_command_line_overrides_them() {
+        // SAFETY: no other test reads these variables, and std serializes the environment
+        // accesses
+        unsafe {
+            std::env::set_var("FOX_MONITOR_CHANNELS_MANIFEST", "from-env.json");
+            std::env::set_var("FOX_MONITOR_LIST_SENSORS", "true");
+        }
+        let from_env = Cli::try_parse_from(["fox-monitor"]);
+        let overridden =
+            Cli::try_parse_from(["fox-monitor", "--channels-manifest", "from-flag.json"]);
+        unsafe {
+            std::env::remove_var("FOX_MONITOR_CHANNELS_MANIFEST");
+            std::env::remove_var("FOX_MONITOR_LIST_SENSORS");
+        }
+
+        let from_env = from_env.unwrap();
+        assert_eq!(
+            from_env.channels_manifest,
+            Some(PathBuf::from("from-env.json"))
+        );
+        assert!(from_env.list_sensors);
+        assert_eq!(
+            overridden.unwrap().channels_manifest,
+            Some(PathBuf::from("from-flag.json"))
+        );
+    }
+
+    #[test]
+    fn websocket_is_not_started_in_ci() {
+        let mut args = Cli::parse_from(["fox-monitor", "--format", "mcap,websocket"]);
+        assert_eq!(
+            args.drop_websocket_on_ci(Some("GITHUB_ACTIONS")),
+            Some("GITHUB_ACTIONS")
+        );
+        assert!(!args.serves_websocket());
+        assert!(args.writes_mcap());
+    }
+
+    #[test]
+    fn websocket_is_kept_outside_ci_forced_or_alone() {
+        let kept = |cli_args: &[&str], ci_variable| {
+            let mut args = Cli::parse_from(["fox-monitor"].iter().chain(cli_args));
+            args.drop_websocket_on_ci(ci_variable).is_none() && args.serves_websocket()
+        };
+        assert!(kept(&["--format", "mcap,websocket"], None));
+        assert!(kept(
+            &["--format", "mcap,websocket", "--force-websocket"],
+            Some("CI")
+        ));
+        assert!(kept(&["--format", "websocket"], Some("CI")));
+    }
+
+    #[test]
+    fn sensors_are_listed_in_aligned_columns() {
+        let sensors = [
+            ("coretemp Package id 0".to_string(), Some(45.25)),
+            ("nvme".to_string(), None),
+        ];
+        assert_eq!(
+            list_sensors(&sensors),
+            "Sensor                 Temperature\n\
+             coretemp Package id 0  45.2 °C\n\
+             nvme                   unknown\n"
+        );
+        assert_eq!(list_sensors(&[]), "No temperature sensors found\n");
+    }
+
+    #[test]
+    fn directory_path_writes_output_mcap_inside_it() {
+        let root = Fixture::new("main-path-directory");
+        let path = root.to_str().unwrap();
+        let mut args = Cli::parse_from(["fox-monitor", "--path", path]);
+        assert!(args.write_inside_path_directory());
+        assert_eq!(args.path, root.join("output.mcap"));
+
+        let file = root.join("run.mcap");
+        let mut args = Cli::parse_from(["fox-monitor", "--path", file.to_str().unwrap()]);
+        assert!(!args.write_inside_path_directory());
+        assert_eq!(args.path, file);
     }
 
-    // Close mcap writer if it was initialized
-    if let Some(writer) = mcap {
-        writer.close().expect("Failed to close mcap writer");
+    #[test]
+    fn json_pretty_is_rejected_with_jsonl() {
+        let conflicts = |cli_args: &[&str]| {
+            Cli::try_parse_from(["fox-monitor"].iter().chain(cli_args))
+                .unwrap()
+                .check_output_conflicts()
+                .map_err(|e| e.kind())
+        };
+        assert_eq!(
+            conflicts(&["--json-pretty", "--format", "jsonl"]),
+            Err(clap::error::ErrorKind::ArgumentConflict)
+        );
+        assert_eq!(
+            conflicts(&["--json-pretty", "--format", "mcap,jsonl"]),
+            Err(clap::error::ErrorKind::ArgumentConflict)
+        );
+        assert_eq!(conflicts(&["--json-pretty", "--format", "mcap"]), Ok(()));
+        assert_eq!(conflicts(&["--format", "jsonl"]), Ok(()));
     }
 }
@@ -0,0 +1,80 @@
+// Machine-readable list of the channels the monitor can log on, written by --channels-manifest
+// so tooling can build layouts without running a capture.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::Cli;
+use crate::channels;
+use crate::logger;
+
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub topic: &'static str,
+    pub schema_name: &'static str,
+    /// Whether the current flags log on the channel
+    pub enabled: bool,
+}
+
+/// Every channel, marked enabled when the flags in `args` log on it
+pub fn manifest(args: &Cli) -> Vec<ManifestEntry> {
+    let produced = logger::produced_topics(args);
+    channels::all_channels()
+        .into_iter()
+        .map(|(topic, schema_name)| ManifestEntry {
+            topic,
+            schema_name,
+            enabled: produced.contains(&topic),
+        })
+        .collect()
+}
+
+pub fn write(path: &Path, args: &Cli) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(&manifest(args)).map_err(std::io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn lists_every_channel_with_the_enabled_ones_marked() {
+        let args = Cli::parse_from(["fox-monitor", "--cpu"]);
+        let manifest = manifest(&args);
+        assert_eq!(manifest.len(), channels::all_channels().len());
+        let enabled: Vec<&str> = manifest
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.topic)
+            .collect();
+        assert!(enabled.contains(&"/cpu"));
+        assert!(!enabled.contains(&"/memory"));
+    }
+
+    #[test]
+    fn writes_the_manifest_as_json() {
+        let root = Fixture::new("manifest");
+        let path = root.join("channels.json");
+        write(&path, &Cli::parse_from(["fox-monitor", "--memory"])).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        let memory = manifest
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|entry| entry["topic"] == "/memory")
+            .unwrap();
+        assert_eq!(memory["enabled"], true);
+        assert!(
+            memory["schema_name"]
+                .as_str()
+                .unwrap()
+                .ends_with("MemoryStats")
+        );
+    }
+}
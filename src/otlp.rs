@@ -0,0 +1,336 @@
+// Exports the numeric fields of the logged messages as OpenTelemetry gauges to an OTLP/HTTP
+// receiver, using the JSON encoding of the OTLP protobuf messages. Each field becomes a metric
+// named after its topic and path, e.g. `fox_monitor.cpu.cores.usage`. The items of a list, like
+// the cores or the disks, are told apart by an `index` attribute plus their string fields, e.g.
+// `mount_point`.
+//
+// The gauges of an iteration are posted together at the end of it, from a separate thread so a
+// slow or unreachable receiver doesn't hold the sampling loop, with the same plain HTTP client as
+// `--push-url`. Each data point is timestamped with the log time of its message. A failed export
+// is dropped rather than retried, since the next iteration brings fresh values anyway, and the
+// exports are paused with an increasing backoff while the receiver keeps failing.
+//
+// This is a hand-rolled client rather than the `opentelemetry-otlp` crate, which isn't available
+// to this build.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use serde_json::{Map, Value, json};
+
+use crate::output::{Message, OutputSink};
+use crate::push::{self, INITIAL_BACKOFF, MAX_BACKOFF, PushUrl};
+
+/// Attributes of a data point as key and string value pairs
+pub type Attributes = Vec<(String, String)>;
+
+/// Exports waiting for the export thread, the new ones are dropped beyond this
+const MAX_PENDING_EXPORTS: usize = 16;
+
+/// A gauge data point waiting for the next export
+struct DataPoint {
+    name: String,
+    unit: String,
+    value: f64,
+    attributes: Attributes,
+    /// Log time of the message in nanoseconds since the Unix epoch
+    time: u64,
+}
+
+pub struct OtlpExporter {
+    /// Resource attributes identifying this host and run
+    resource: Attributes,
+    points: Vec<DataPoint>,
+    /// Encoded requests for the export thread, `None` once closed
+    exports: Option<SyncSender<Vec<u8>>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl OtlpExporter {
+    /// Exports to an OTLP/HTTP endpoint, a url without a path gets the standard `/v1/metrics`
+    pub fn start(mut url: PushUrl, labels: BTreeMap<String, String>) -> std::io::Result<Self> {
+        if url.path == "/" {
+            url.path = "/v1/metrics".to_string();
+        }
+        let host = sysinfo::System::host_name().unwrap_or_else(|| "<unknown>".to_owned());
+        let resource = [
+            ("service.name".to_string(), "fox-monitor".to_string()),
+            ("host.name".to_string(), host),
+        ]
+        .into_iter()
+        .chain(labels)
+        .collect();
+        let (exports, pending) = mpsc::sync_channel(MAX_PENDING_EXPORTS);
+        let worker = std::thread::Builder::new()
+            .name("otlp".to_string())
+            .spawn(move || export_all(&url, &pending))?;
+        Ok(Self {
+            resource,
+            points: Vec::new(),
+            exports: Some(exports),
+            worker: Some(worker),
+        })
+    }
+
+    /// Hands the data points recorded since the previous export to the export thread
+    fn export(&mut self) {
+        if self.points.is_empty() {
+            return;
+        }
+        let body = serde_json::to_vec(&self.request()).expect("Failed to serialize metrics");
+        self.points.clear();
+        if let Some(exports) = &self.exports
+            && let Err(TrySendError::Full(_)) = exports.try_send(body)
+        {
+            log::debug!("Dropping an OTLP export, the previous ones are still being sent");
+        }
+    }
+
+    /// An `ExportMetricsServiceRequest` with one gauge per metric name
+    fn request(&self) -> Value {
+        let mut metrics: BTreeMap<&str, (&str, Vec<Value>)> = BTreeMap::new();
+        for point in &self.points {
+            metrics
+                .entry(&point.name)
+                .or_insert_with(|| (&point.unit, Vec::new()))
+                .1
+                .push(json!({
+                    "timeUnixNano": point.time.to_string(),
+                    "asDouble": point.value,
+                    "attributes": attributes(&point.attributes),
+                }));
+        }
+        json!({
+            "resourceMetrics": [{
+                "resource": { "attributes": attributes(&self.resource) },
+                "scopeMetrics": [{
+                    "scope": { "name": "fox-monitor", "version": env!("CARGO_PKG_VERSION") },
+                    "metrics": metrics
+                        .into_iter()
+                        .map(|(name, (unit, data_points))| json!({
+                            "name": name,
+                            "unit": unit,
+                            "gauge": { "dataPoints": data_points },
+                        }))
+                        .collect::<Vec<_>>(),
+                }],
+            }],
+        })
+    }
+}
+
+impl OutputSink for OtlpExporter {
+    /// Turns the numeric fields of a message into data points, with the units from the
+    /// `<field>.unit` channel metadata
+    fn write(&mut self, message: &Message) {
+        let Some(json) = message.json() else {
+            return;
+        };
+        let prefix = format!(
+            "fox_monitor.{}",
+            message.topic().trim_start_matches('/').replace('/', ".")
+        );
+        let metadata = message.channel.metadata();
+        let mut points = Vec::new();
+        flatten(json, "", &[], &mut points);
+        // The sequence number identifies the sample, it is not a measurement
+        points.retain(|(path, _, _)| path != "seq");
+        self.points
+            .extend(points.into_iter().map(|(path, value, attributes)| {
+                DataPoint {
+                    unit: metadata
+                        .get(&format!("{path}.unit"))
+                        .cloned()
+                        .unwrap_or_default(),
+                    name: format!("{prefix}.{path}"),
+                    value,
+                    attributes,
+                    time: message.log_time,
+                }
+            }));
+    }
+
+    fn flush(&mut self) {
+        self.export();
+    }
+
+    /// Exports the remaining data points and waits for the export thread to send them
+    fn close(mut self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        self.export();
+        drop(self.exports.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// Posts the exports until the exporter is closed. After a failure the exports are dropped
+/// until the backoff has elapsed, and the backoff doubles while the receiver keeps failing.
+fn export_all(url: &PushUrl, pending: &Receiver<Vec<u8>>) {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut paused_until: Option<Instant> = None;
+    for body in pending {
+        if paused_until.is_some_and(|until| Instant::now() < until) {
+            continue;
+        }
+        match push::post(url, &body) {
+            Ok(()) => {
+                if paused_until.take().is_some() {
+                    log::info!("OTLP export recovered");
+                }
+                backoff = INITIAL_BACKOFF;
+            }
+            Err(e) => {
+                if paused_until.is_none() {
+                    log::warn!(
+                        "Failed to export metrics to {}:{}{}: {e}",
+                        url.host,
+                        url.port,
+                        url.path
+                    );
+                }
+                log::debug!("Pausing the OTLP exports for {backoff:?}");
+                paused_until = Some(Instant::now() + backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+fn attributes(attributes: &[(String, String)]) -> Vec<Value> {
+    attributes
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// Collects the numeric and boolean leaves of a message as (field path, value, attributes)
+pub fn flatten(
+    value: &Value,
+    path: &str,
+    attributes: &[(String, String)],
+    points: &mut Vec<(String, f64, Attributes)>,
+) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match value {
+        Value::Number(number) => {
+            if let Some(number) = number.as_f64() {
+                points.push((path.to_string(), number, attributes.to_vec()));
+            }
+        }
+        Value::Bool(flag) => points.push((
+            path.to_string(),
+            f64::from(u8::from(*flag)),
+            attributes.to_vec(),
+        )),
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                flatten(field, &join(key), attributes, points);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let mut attributes = attributes.to_vec();
+                attributes.push(("index".to_string(), index.to_string()));
+                if let Value::Object(fields) = item {
+                    attributes.extend(string_fields(fields));
+                }
+                flatten(item, path, &attributes, points);
+            }
+        }
+        Value::String(_) | Value::Null => {}
+    }
+}
+
+fn string_fields(fields: &Map<String, Value>) -> impl Iterator<Item = (String, String)> + '_ {
+    fields
+        .iter()
+        .filter_map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+
+    /// Accepts a single request and answers it, returning its JSON body
+    fn receive(listener: TcpListener) -> std::thread::JoinHandle<Value> {
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut chunk = [0; 4096];
+            let body = loop {
+                let n = stream.read(&mut chunk).unwrap();
+                request.extend_from_slice(&chunk[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length: usize = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Content-Length: "))
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    if body.len() == length {
+                        break body.to_string();
+                    }
+                }
+            };
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+            serde_json::from_str(&body).unwrap()
+        })
+    }
+
+    #[test]
+    fn data_points_have_the_log_time() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = push::parse_url(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let received = receive(listener);
+        let channel = ChannelBuilder::new("/otlp_test")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+
+        let mut exporter = OtlpExporter::start(url, BTreeMap::new()).unwrap();
+        exporter.write(&Message::new(
+            &channel,
+            br#"{"seq":1,"usage":12.5,"cores":[{"name":"a","usage":3}]}"#,
+            1_000_000_042,
+        ));
+        Box::new(exporter).close().unwrap();
+
+        let request = received.join().unwrap();
+        let metrics = &request["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        let names: Vec<&str> = metrics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|metric| metric["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "fox_monitor.otlp_test.cores.usage",
+                "fox_monitor.otlp_test.usage"
+            ]
+        );
+        let point = &metrics[0]["gauge"]["dataPoints"][0];
+        assert_eq!(point["timeUnixNano"], "1000000042");
+        assert_eq!(point["asDouble"], 3.0);
+        assert_eq!(point["attributes"][1]["key"], "name");
+        assert_eq!(point["attributes"][1]["value"]["stringValue"], "a");
+    }
+}
@@ -0,0 +1,284 @@
+// Outputs that get every logged message: the mcap files, the websocket server, the JSON lines on
+// stdout, --push-url, --otlp-endpoint and --statsd. They are kept in a single collection that the
+// logger, the replay and the remote ingestion write to, so a new output only has to implement
+// `OutputSink` and be added to it.
+//
+// The messages are passed encoded, with their channel for the topic, schema and metadata. The
+// outputs that need the fields decode the message once through `Message::json`, which is shared
+// between them.
+
+use std::cell::OnceCell;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant};
+
+use foxglove::Channel;
+use serde_json::Value;
+
+/// A logged message, as handed to every output
+pub struct Message<'a> {
+    pub channel: &'a Channel,
+    /// The message encoded as JSON
+    pub payload: &'a [u8],
+    /// Sequence number of the message on its channel
+    pub sequence: u32,
+    pub log_time: u64,
+    json: OnceCell<Option<Value>>,
+}
+
+impl<'a> Message<'a> {
+    /// Takes the next sequence number of the channel
+    pub fn new(channel: &'a Channel, payload: &'a [u8], log_time: u64) -> Self {
+        Self {
+            channel,
+            payload,
+            sequence: channel.next_sequence(),
+            log_time,
+            json: OnceCell::new(),
+        }
+    }
+
+    pub fn topic(&self) -> &str {
+        self.channel.topic()
+    }
+
+    /// The decoded message, `None` if the payload isn't valid JSON
+    pub fn json(&self) -> Option<&Value> {
+        self.json
+            .get_or_init(|| serde_json::from_slice(self.payload).ok())
+            .as_ref()
+    }
+}
+
+pub trait OutputSink: Send {
+    /// Records a logged message
+    fn write(&mut self, message: &Message);
+
+    /// Sends what was recorded, called at the end of every iteration
+    fn flush(&mut self) {}
+
+    /// Sends what is left, called once on shutdown. Returns the files written by the output.
+    fn close(self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Time between two flushes of the outputs when messages are written outside of the logger's
+/// iterations, as by the replay and the remote ingestion
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time between two attempts of `Outputs::try_close` to take the outputs
+const TRY_CLOSE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+type Sinks = Vec<Box<dyn OutputSink>>;
+
+/// The outputs of the run, shared between the logger, the output thread of --on-backpressure and
+/// the stall watchdog, which closes them before exiting
+#[derive(Clone)]
+pub struct Outputs {
+    /// `None` once the outputs are closed
+    sinks: Arc<Mutex<Option<Sinks>>>,
+}
+
+impl Outputs {
+    pub fn new() -> Self {
+        Self {
+            sinks: Arc::new(Mutex::new(Some(Vec::new()))),
+        }
+    }
+
+    pub fn add(&self, sink: Box<dyn OutputSink>) {
+        if let Some(sinks) = self.sinks.lock().expect("Outputs lock poisoned").as_mut() {
+            sinks.push(sink);
+        }
+    }
+
+    /// Gives a message to every output
+    pub fn write(&self, message: &Message) {
+        for sink in self
+            .sinks
+            .lock()
+            .expect("Outputs lock poisoned")
+            .iter_mut()
+            .flatten()
+        {
+            sink.write(message);
+        }
+    }
+
+    /// Flushes every output, called at the end of every iteration
+    pub fn flush(&self) {
+        for sink in self
+            .sinks
+            .lock()
+            .expect("Outputs lock poisoned")
+            .iter_mut()
+            .flatten()
+        {
+            sink.flush();
+        }
+    }
+
+    /// Closes every output, even when one fails, returning the files they wrote or the first
+    /// error. Later calls do nothing.
+    pub fn close(&self) -> std::io::Result<Vec<PathBuf>> {
+        let sinks = self
+            .sinks
+            .lock()
+            .map(|mut sinks| sinks.take())
+            .unwrap_or_default();
+        close_sinks(sinks)
+    }
+
+    /// Like `close`, but gives up and returns `None` when the outputs stay locked for `wait`, as
+    /// they are by a write stuck in an output. Used by the stall watchdog, which must exit even
+    /// then.
+    pub fn try_close(&self, wait: Duration) -> Option<std::io::Result<Vec<PathBuf>>> {
+        let deadline = Instant::now() + wait;
+        loop {
+            let sinks = match self.sinks.try_lock() {
+                Ok(mut sinks) => sinks.take(),
+                Err(TryLockError::Poisoned(_)) => None,
+                Err(TryLockError::WouldBlock) if Instant::now() >= deadline => return None,
+                Err(TryLockError::WouldBlock) => {
+                    std::thread::sleep(TRY_CLOSE_POLL_INTERVAL);
+                    continue;
+                }
+            };
+            return Some(close_sinks(sinks));
+        }
+    }
+}
+
+/// Closes every output, even when one fails, returning the files they wrote or the first error
+fn close_sinks(sinks: Option<Sinks>) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut error = None;
+    for sink in sinks.into_iter().flatten() {
+        match sink.close() {
+            Ok(written) => paths.extend(written),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+    error.map_or(Ok(paths), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+
+    /// Records the calls it gets in a log shared by every sink of a test
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        close_error: bool,
+    }
+
+    impl OutputSink for Recorder {
+        fn write(&mut self, message: &Message) {
+            let event = format!("{} write {}", self.name, message.sequence);
+            self.log.lock().unwrap().push(event);
+        }
+
+        fn flush(&mut self) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} flush", self.name));
+        }
+
+        fn close(self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} close", self.name));
+            if self.close_error {
+                return Err(std::io::Error::other(format!("{} failed", self.name)));
+            }
+            Ok(vec![PathBuf::from(format!("{}.mcap", self.name))])
+        }
+    }
+
+    fn outputs(sinks: &[(&'static str, bool)]) -> (Outputs, Arc<Mutex<Vec<String>>>) {
+        let outputs = Outputs::new();
+        let log = Arc::default();
+        for &(name, close_error) in sinks {
+            outputs.add(Box::new(Recorder {
+                name,
+                log: Arc::clone(&log),
+                close_error,
+            }));
+        }
+        (outputs, log)
+    }
+
+    #[test]
+    fn every_output_gets_the_messages() {
+        let (outputs, log) = outputs(&[("a", false), ("b", false)]);
+        let channel = ChannelBuilder::new("/outputs_write")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        outputs.write(&Message::new(&channel, b"{}", 0));
+        outputs.write(&Message::new(&channel, b"{}", 0));
+        outputs.flush();
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "a write 1",
+                "b write 1",
+                "a write 2",
+                "b write 2",
+                "a flush",
+                "b flush"
+            ]
+        );
+    }
+
+    #[test]
+    fn close_closes_every_output_once() {
+        let (outputs, log) = outputs(&[("a", false), ("b", true), ("c", true), ("d", false)]);
+        let error = outputs.close().unwrap_err();
+        assert_eq!(error.to_string(), "b failed");
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["a close", "b close", "c close", "d close"]
+        );
+
+        let channel = ChannelBuilder::new("/outputs_closed")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        outputs.write(&Message::new(&channel, b"{}", 0));
+        assert!(outputs.close().unwrap().is_empty());
+        assert_eq!(log.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn close_returns_the_written_files() {
+        let (outputs, _) = outputs(&[("a", false), ("b", false)]);
+        assert_eq!(
+            outputs.close().unwrap(),
+            [PathBuf::from("a.mcap"), PathBuf::from("b.mcap")]
+        );
+    }
+
+    #[test]
+    fn message_is_decoded_once_for_every_output() {
+        let channel = ChannelBuilder::new("/outputs_json")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let message = Message::new(&channel, br#"{"usage":1}"#, 0);
+        assert_eq!(message.json().unwrap()["usage"], 1);
+        assert!(std::ptr::eq(
+            message.json().unwrap(),
+            message.json().unwrap()
+        ));
+        assert!(Message::new(&channel, b"not json", 0).json().is_none());
+    }
+}
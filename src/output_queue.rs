@@ -0,0 +1,212 @@
+// Decouples collecting the metrics from writing them. Encoded messages go through a bounded
+// queue to a thread that writes them to the outputs, so a slow mcap disk only slows the loop
+// down when the policy says so. Otherwise a full queue drops the oldest or the newest message.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use foxglove::Channel;
+
+/// What to do with a new message when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum BackpressurePolicy {
+    /// Wait until the output catches up, slowing the loop down
+    Block,
+    /// Drop the oldest queued message to make room
+    DropOld,
+    /// Drop the new message
+    DropNew,
+}
+
+/// Encoded message as channel, payload and log time in nanoseconds
+type Message = (Arc<Channel>, Vec<u8>, u64);
+
+struct State {
+    messages: VecDeque<Message>,
+    closed: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Signaled when a message is queued or the queue is closed
+    not_empty: Condvar,
+    /// Signaled when a message is taken out of the queue
+    not_full: Condvar,
+    dropped: AtomicU64,
+}
+
+pub struct OutputQueue {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: BackpressurePolicy,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl OutputQueue {
+    /// Starts the thread that passes the queued messages to `output`, in order
+    pub fn start(
+        capacity: usize,
+        policy: BackpressurePolicy,
+        output: impl Fn(&Channel, &[u8], u64) + Send + 'static,
+    ) -> std::io::Result<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                messages: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            dropped: AtomicU64::new(0),
+        });
+        let worker = std::thread::Builder::new()
+            .name("output".to_string())
+            .spawn({
+                let shared = shared.clone();
+                move || {
+                    loop {
+                        let mut state = shared.state.lock().expect("Output queue lock poisoned");
+                        while state.messages.is_empty() && !state.closed {
+                            state = shared
+                                .not_empty
+                                .wait(state)
+                                .expect("Output queue lock poisoned");
+                        }
+                        let Some((channel, payload, log_time)) = state.messages.pop_front() else {
+                            return;
+                        };
+                        drop(state);
+                        shared.not_full.notify_one();
+                        output(&channel, &payload, log_time);
+                    }
+                }
+            })?;
+        Ok(Self {
+            shared,
+            capacity: capacity.max(1),
+            policy,
+            worker: Some(worker),
+        })
+    }
+
+    /// Queues a message, applying the policy when the queue is full
+    pub fn push(&self, channel: Arc<Channel>, payload: Vec<u8>, log_time: u64) {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .expect("Output queue lock poisoned");
+        if state.messages.len() >= self.capacity {
+            match self.policy {
+                BackpressurePolicy::Block => {
+                    while state.messages.len() >= self.capacity {
+                        state = self
+                            .shared
+                            .not_full
+                            .wait(state)
+                            .expect("Output queue lock poisoned");
+                    }
+                }
+                BackpressurePolicy::DropOld => {
+                    state.messages.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                BackpressurePolicy::DropNew => {
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+        state.messages.push_back((channel, payload, log_time));
+        drop(state);
+        self.shared.not_empty.notify_one();
+    }
+
+    /// Number of messages dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stops accepting messages and waits until the queued ones are written
+    pub fn close(&mut self) {
+        self.shared
+            .state
+            .lock()
+            .expect("Output queue lock poisoned")
+            .closed = true;
+        self.shared.not_empty.notify_one();
+        if let Some(worker) = self.worker.take()
+            && worker.join().is_err()
+        {
+            log::error!("Output thread panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+
+    /// Queues messages with log times 1 to 3 while the output is stuck writing message 0,
+    /// returning the log times written and the number of dropped messages
+    fn written_while_stuck(policy: BackpressurePolicy) -> (Vec<u64>, u64) {
+        let channel = ChannelBuilder::new(format!("/output_queue_{policy:?}"))
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let (written_tx, written_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let mut queue = OutputQueue::start(2, policy, move |_, _, log_time| {
+            written_tx.send(log_time).unwrap();
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+        queue.push(channel.clone(), Vec::new(), 0);
+        assert_eq!(written_rx.recv().unwrap(), 0);
+
+        if policy == BackpressurePolicy::Block {
+            queue.push(channel.clone(), Vec::new(), 1);
+            queue.push(channel.clone(), Vec::new(), 2);
+            std::thread::scope(|scope| {
+                // The third message waits for room in the queue
+                let pusher = scope.spawn(|| queue.push(channel.clone(), Vec::new(), 3));
+                drop(release_tx);
+                pusher.join().unwrap();
+            });
+        } else {
+            for log_time in 1..=3 {
+                queue.push(channel.clone(), Vec::new(), log_time);
+            }
+            drop(release_tx);
+        }
+        let dropped = queue.dropped();
+        queue.close();
+        (written_rx.try_iter().collect(), dropped)
+    }
+
+    #[test]
+    fn drop_old_keeps_the_newest_messages() {
+        let (written, dropped) = written_while_stuck(BackpressurePolicy::DropOld);
+        assert_eq!(written, [2, 3]);
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn drop_new_keeps_the_queued_messages() {
+        let (written, dropped) = written_while_stuck(BackpressurePolicy::DropNew);
+        assert_eq!(written, [1, 2]);
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn block_waits_for_room() {
+        let (written, dropped) = written_while_stuck(BackpressurePolicy::Block);
+        assert_eq!(written, [1, 2, 3]);
+        assert_eq!(dropped, 0);
+    }
+}
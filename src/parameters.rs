@@ -0,0 +1,138 @@
+// Settings that can be changed while running from the Foxglove app, through the parameters of the
+// websocket server, e.g. dropping the per-core detail of /cpu without restarting. The logger reads
+// them when it builds the next samples, so a change takes effect on the next iteration.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use foxglove::WebSocketServer;
+use foxglove::websocket::{Capability, Client, Parameter, ParameterValue, ServerListener};
+
+/// Whether /cpu lists the usage of each core
+const CPU_INCLUDE_CORES: &str = "cpu.include_cores";
+
+/// Current values of the runtime parameters, shared by the websocket server and the logger
+pub struct RuntimeConfig {
+    cpu_include_cores: AtomicBool,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            cpu_include_cores: AtomicBool::new(true),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn cpu_include_cores(&self) -> bool {
+        self.cpu_include_cores.load(Ordering::Relaxed)
+    }
+
+    /// Every parameter with its current value
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![bool_parameter(CPU_INCLUDE_CORES, self.cpu_include_cores())]
+    }
+
+    /// Applies a parameter set by a client, ignoring unknown names and values of the wrong type
+    pub fn set(&self, parameter: &Parameter) {
+        let flag = match parameter.name.as_str() {
+            CPU_INCLUDE_CORES => &self.cpu_include_cores,
+            name => {
+                log::warn!("Ignoring unknown parameter {name}");
+                return;
+            }
+        };
+        match parameter.value {
+            Some(ParameterValue::Bool(value)) => {
+                if flag.swap(value, Ordering::Relaxed) != value {
+                    log::info!("Parameter {} set to {value}", parameter.name);
+                }
+            }
+            _ => log::warn!("Ignoring parameter {}, expected a boolean", parameter.name),
+        }
+    }
+}
+
+fn bool_parameter(name: &str, value: bool) -> Parameter {
+    Parameter {
+        name: name.to_string(),
+        r#type: None,
+        value: Some(ParameterValue::Bool(value)),
+    }
+}
+
+/// Answers the parameter requests of the websocket clients from the shared config
+struct ParameterListener {
+    config: Arc<RuntimeConfig>,
+}
+
+impl ServerListener for ParameterListener {
+    fn on_get_parameters(
+        &self,
+        _client: Client,
+        param_names: Vec<String>,
+        _request_id: Option<&str>,
+    ) -> Vec<Parameter> {
+        self.config
+            .parameters()
+            .into_iter()
+            .filter(|parameter| param_names.is_empty() || param_names.contains(&parameter.name))
+            .collect()
+    }
+
+    fn on_set_parameters(
+        &self,
+        _client: Client,
+        parameters: Vec<Parameter>,
+        _request_id: Option<&str>,
+    ) -> Vec<Parameter> {
+        for parameter in &parameters {
+            self.config.set(parameter);
+        }
+        // The current values, so a rejected change shows the value still in use
+        self.config
+            .parameters()
+            .into_iter()
+            .filter(|current| parameters.iter().any(|set| set.name == current.name))
+            .collect()
+    }
+}
+
+/// Lets the clients of the server get and set the runtime parameters
+pub fn serve(server: WebSocketServer, config: &Arc<RuntimeConfig>) -> WebSocketServer {
+    server
+        .capabilities([Capability::Parameters])
+        .listener(Arc::new(ParameterListener {
+            config: config.clone(),
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_boolean_parameters() {
+        let config = RuntimeConfig::default();
+        assert!(config.cpu_include_cores());
+        config.set(&bool_parameter(CPU_INCLUDE_CORES, false));
+        assert!(!config.cpu_include_cores());
+        assert_eq!(
+            config.parameters()[0].value,
+            Some(ParameterValue::Bool(false))
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_parameters_and_wrong_types() {
+        let config = RuntimeConfig::default();
+        config.set(&bool_parameter("cpu.unknown", false));
+        config.set(&Parameter {
+            name: CPU_INCLUDE_CORES.to_string(),
+            r#type: None,
+            value: Some(ParameterValue::Number(0.0)),
+        });
+        assert!(config.cpu_include_cores());
+    }
+}
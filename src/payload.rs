@@ -0,0 +1,214 @@
+// Encodes the messages logged on the channels as JSON, applying the optional transformations
+// to the float fields.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::nonfinite::{self, NON_FINITE};
+
+/// How NaN and infinite floats, which JSON can't represent, are encoded
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum NanPolicy {
+    /// Encode them as `null`
+    Null,
+    /// Encode them as `0.0`
+    Zero,
+    /// Leave the field or list element out
+    Skip,
+}
+
+/// Encodes the message, rounding every float field to `round_digits` decimal places if set,
+/// encoding NaN and infinite floats according to `nan_policy`, and indenting it if `pretty`
+pub fn encode<T: Serialize>(
+    msg: &T,
+    round_digits: Option<u32>,
+    nan_policy: NanPolicy,
+    pretty: bool,
+) -> Vec<u8> {
+    // serde_json already encodes non-finite floats as null
+    if round_digits.is_none() && nan_policy == NanPolicy::Null {
+        return to_vec(msg, pretty);
+    }
+
+    let mut value = if nan_policy == NanPolicy::Null {
+        serde_json::to_value(msg)
+    } else {
+        nonfinite::to_value(msg)
+    }
+    .expect("Failed to serialize message");
+    replace_non_finite(&mut value, nan_policy);
+    if let Some(digits) = round_digits {
+        round_floats(&mut value, digits);
+    }
+    to_vec(&value, pretty)
+}
+
+fn to_vec<T: Serialize>(value: &T, pretty: bool) -> Vec<u8> {
+    if pretty {
+        serde_json::to_vec_pretty(value)
+    } else {
+        serde_json::to_vec(value)
+    }
+    .expect("Failed to serialize message")
+}
+
+/// A message with its `seq` field, the iteration it was logged in. Every
+/// message of an iteration shares the same `seq`, so a gap on a topic is an iteration without a
+/// message on it: either dropped by the output queue or skipped on purpose, which the /status
+/// channel counts.
+#[derive(Serialize)]
+pub struct Sequenced<'a, T> {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub message: &'a T,
+}
+
+/// Replaces the non-finite markers left by `nonfinite::to_value` according to the policy. A
+/// skipped marker that is not in a list or an object becomes `null`.
+pub fn replace_non_finite(value: &mut Value, policy: NanPolicy) {
+    let is_marker = |value: &Value| value.as_str() == Some(NON_FINITE);
+    match value {
+        Value::String(_) if is_marker(value) => {
+            *value = match policy {
+                NanPolicy::Zero => Value::from(0.0),
+                NanPolicy::Null | NanPolicy::Skip => Value::Null,
+            };
+        }
+        Value::Array(values) => {
+            if policy == NanPolicy::Skip {
+                values.retain(|v| !is_marker(v));
+            }
+            values
+                .iter_mut()
+                .for_each(|v| replace_non_finite(v, policy));
+        }
+        Value::Object(fields) => {
+            if policy == NanPolicy::Skip {
+                fields.retain(|_, v| !is_marker(v));
+            }
+            fields
+                .values_mut()
+                .for_each(|v| replace_non_finite(v, policy));
+        }
+        _ => {}
+    }
+}
+
+/// Rounds every float in the value to the given number of decimal places, leaving integers as is
+pub fn round_floats(value: &mut Value, digits: u32) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            let factor = 10f64.powi(digits as i32);
+            let rounded = number.as_f64().map(|n| (n * factor).round() / factor);
+            if let Some(rounded) = rounded.and_then(serde_json::Number::from_f64) {
+                *number = rounded;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| round_floats(v, digits)),
+        Value::Object(fields) => fields.values_mut().for_each(|v| round_floats(v, digits)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Sample {
+        usage: f32,
+    }
+
+    #[test]
+    fn sequenced_message_starts_with_seq() {
+        let sequenced = Sequenced {
+            seq: 7,
+            message: &Sample { usage: 1.5 },
+        };
+        let payload = encode(&sequenced, None, NanPolicy::Null, false);
+        assert_eq!(payload, br#"{"seq":7,"usage":1.5}"#);
+        let payload = encode(&sequenced, None, NanPolicy::Null, true);
+        assert_eq!(payload, b"{\n  \"seq\": 7,\n  \"usage\": 1.5\n}");
+    }
+
+    #[test]
+    fn sequenced_message_is_transformed() {
+        let sequenced = Sequenced {
+            seq: 3,
+            message: &Sample { usage: 1.26 },
+        };
+        let payload = encode(&sequenced, Some(1), NanPolicy::Null, false);
+        assert_eq!(payload, br#"{"seq":3,"usage":1.3}"#);
+    }
+
+    #[test]
+    fn rounds_nested_floats_only() {
+        let mut value = serde_json::json!({
+            "usage": 12.3456,
+            "count": 7,
+            "cores": [{"usage": 0.005}, {"usage": 99.994}],
+        });
+        round_floats(&mut value, 2);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "usage": 12.35,
+                "count": 7,
+                "cores": [{"usage": 0.01}, {"usage": 99.99}],
+            })
+        );
+        round_floats(&mut value, 0);
+        assert_eq!(value["usage"], 12.0);
+        assert!(value["count"].is_u64());
+    }
+
+    #[derive(Serialize)]
+    struct Readings {
+        temperature: f32,
+        load: Option<f64>,
+        cores: Vec<f32>,
+    }
+
+    fn readings() -> Readings {
+        Readings {
+            temperature: f32::NAN,
+            load: Some(f64::INFINITY),
+            cores: vec![1.0, f32::NEG_INFINITY, 2.0],
+        }
+    }
+
+    #[test]
+    fn non_finite_floats_are_null_by_default() {
+        let payload = encode(&readings(), None, NanPolicy::Null, false);
+        assert_eq!(
+            payload,
+            br#"{"temperature":null,"load":null,"cores":[1.0,null,2.0]}"#
+        );
+    }
+
+    #[test]
+    fn non_finite_floats_can_be_zero() {
+        let payload = encode(&readings(), None, NanPolicy::Zero, false);
+        assert_eq!(
+            payload,
+            br#"{"cores":[1.0,0.0,2.0],"load":0.0,"temperature":0.0}"#
+        );
+    }
+
+    #[test]
+    fn non_finite_floats_can_be_left_out() {
+        let payload = encode(&readings(), Some(1), NanPolicy::Skip, false);
+        assert_eq!(payload, br#"{"cores":[1.0,2.0]}"#);
+    }
+
+    #[test]
+    fn none_is_not_mistaken_for_a_non_finite_float() {
+        let readings = Readings {
+            temperature: 1.0,
+            load: None,
+            cores: Vec::new(),
+        };
+        let payload = encode(&readings, None, NanPolicy::Skip, false);
+        assert_eq!(payload, br#"{"cores":[],"load":null,"temperature":1.0}"#);
+    }
+}
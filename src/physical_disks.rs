@@ -0,0 +1,85 @@
+// Tells real block devices apart from the pseudo and virtual filesystems sysinfo also lists as
+// disks, like the overlay root of a container or the tmpfs mounts of /run. A disk is excluded when
+// its filesystem is a known pseudo filesystem, or on Linux when it isn't mounted from a device
+// under /dev.
+
+use std::ffi::OsStr;
+
+/// Filesystems that never live on a block device of their own
+const PSEUDO_FILE_SYSTEMS: &[&str] = &[
+    "autofs",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fuse.lxcfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "overlay",
+    "proc",
+    "pstore",
+    "ramfs",
+    "securityfs",
+    "squashfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+pub struct PhysicalDiskFilter {
+    /// Filesystems excluded with --exclude-fs on top of the pseudo filesystems
+    excluded: Vec<String>,
+}
+
+impl PhysicalDiskFilter {
+    pub fn new(excluded: Vec<String>) -> Self {
+        Self { excluded }
+    }
+
+    /// Whether a disk with this filesystem, mounted from the `device` source, is a real disk
+    pub fn includes(&self, file_system: &OsStr, device: &OsStr) -> bool {
+        let file_system = file_system.to_string_lossy();
+        if PSEUDO_FILE_SYSTEMS.contains(&file_system.as_ref())
+            || self
+                .excluded
+                .iter()
+                .any(|excluded| *excluded == file_system)
+        {
+            return false;
+        }
+        !cfg!(target_os = "linux") || device.to_string_lossy().starts_with("/dev/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn includes(filter: &PhysicalDiskFilter, file_system: &str, device: &str) -> bool {
+        filter.includes(OsStr::new(file_system), OsStr::new(device))
+    }
+
+    #[test]
+    fn excludes_pseudo_and_excluded_filesystems() {
+        let filter = PhysicalDiskFilter::new(vec!["vfat".to_string()]);
+        assert!(includes(&filter, "ext4", "/dev/sda1"));
+        assert!(!includes(&filter, "tmpfs", "/dev/shm"));
+        assert!(!includes(&filter, "overlay", "/dev/overlay"));
+        assert!(!includes(&filter, "vfat", "/dev/nvme0n1p1"));
+    }
+
+    #[test]
+    fn excludes_disks_not_mounted_from_a_device_on_linux() {
+        let filter = PhysicalDiskFilter::new(Vec::new());
+        assert_eq!(
+            includes(&filter, "nfs4", "server:/export"),
+            !cfg!(target_os = "linux")
+        );
+    }
+}
@@ -0,0 +1,108 @@
+// Samples the package power draw on macOS through `powermetrics`. `powermetrics` must run as
+// root, so a permission failure is reported separately to let the caller disable the metric.
+// Other platforms have no `powermetrics`, so `read_power` always returns `Ok(None)`.
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+use std::fmt;
+
+use crate::channels::PowerStats;
+
+#[derive(Debug)]
+pub enum PowerError {
+    PermissionDenied,
+    Failed(String),
+}
+
+impl fmt::Display for PowerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerError::PermissionDenied => write!(f, "powermetrics must be run as root"),
+            PowerError::Failed(reason) => write!(f, "powermetrics failed: {reason}"),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn read_power() -> Result<Option<PowerStats>, PowerError> {
+    let output = std::process::Command::new("powermetrics")
+        .args(["--samplers", "cpu_power,gpu_power", "-i", "200", "-n", "1"])
+        .output()
+        .map_err(|e| PowerError::Failed(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("superuser") || stderr.contains("root") {
+            return Err(PowerError::PermissionDenied);
+        }
+        return Err(PowerError::Failed(stderr.trim().to_string()));
+    }
+
+    Ok(Some(parse_powermetrics(&String::from_utf8_lossy(
+        &output.stdout,
+    ))))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn read_power() -> Result<Option<PowerStats>, PowerError> {
+    Ok(None)
+}
+
+/// Parses the text output of `powermetrics`, where each power line looks like
+/// `CPU Power: 1234 mW`. Missing lines are reported as zero.
+pub fn parse_powermetrics(output: &str) -> PowerStats {
+    let mut stats = PowerStats {
+        cpu_power_watts: 0.0,
+        gpu_power_watts: 0.0,
+        package_power_watts: 0.0,
+    };
+
+    for line in output.lines() {
+        let Some((label, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(watts) = parse_milliwatts(value) else {
+            continue;
+        };
+        match label.trim() {
+            "CPU Power" => stats.cpu_power_watts = watts,
+            "GPU Power" => stats.gpu_power_watts = watts,
+            // Apple Silicon reports the combined power, Intel reports the package power
+            label if label.starts_with("Combined Power") || label == "Package Power" => {
+                stats.package_power_watts = watts
+            }
+            _ => {}
+        }
+    }
+
+    stats
+}
+
+fn parse_milliwatts(value: &str) -> Option<f32> {
+    let milliwatts: f32 = value.trim().strip_suffix("mW")?.trim().parse().ok()?;
+    Some(milliwatts / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_apple_silicon_output() {
+        let output = "**** Processor usage ****\n\
+                      CPU Power: 1234 mW\n\
+                      GPU Power: 56 mW\n\
+                      Combined Power (CPU + GPU + ANE): 1290 mW\n";
+        let stats = parse_powermetrics(output);
+        assert_eq!(stats.cpu_power_watts, 1.234);
+        assert_eq!(stats.gpu_power_watts, 0.056);
+        assert_eq!(stats.package_power_watts, 1.29);
+    }
+
+    #[test]
+    fn missing_and_malformed_lines_are_zero() {
+        let stats = parse_powermetrics("Package Power: 5000 mW\nCPU Power: n/a\nGPU Power: 3 W\n");
+        assert_eq!(stats.package_power_watts, 5.0);
+        assert_eq!(stats.cpu_power_watts, 0.0);
+        assert_eq!(stats.gpu_power_watts, 0.0);
+    }
+}
@@ -0,0 +1,110 @@
+// Reads the pressure stall information (PSI) of the cpu, memory and io from `/proc/pressure`.
+//
+// Each file has a `some` line, the share of time at least one task was stalled on the resource,
+// and a `full` line, the share of time all non-idle tasks were stalled at once. The directory only
+// exists on kernels built with PSI and not booted with `psi=0`, so nothing is logged on /pressure
+// when `read_pressure` finds none of the files.
+
+use std::path::Path;
+
+use crate::channels::{PressureAverages, PressureStats, ResourcePressure};
+use crate::kernel_fs;
+
+pub fn read_pressure() -> Option<PressureStats> {
+    read_pressure_in(&kernel_fs::proc_root()?.join("pressure"))
+}
+
+/// Reads the pressure files in the directory at `root`, `None` when none of them is readable
+pub fn read_pressure_in(root: &Path) -> Option<PressureStats> {
+    let read = |resource: &str| {
+        std::fs::read_to_string(root.join(resource))
+            .ok()
+            .and_then(|contents| parse_pressure(&contents))
+    };
+    let stats = PressureStats {
+        cpu: read("cpu"),
+        memory: read("memory"),
+        io: read("io"),
+    };
+    (stats.cpu.is_some() || stats.memory.is_some() || stats.io.is_some()).then_some(stats)
+}
+
+/// Parses the contents of a pressure file, the `full` line is optional since older kernels
+/// don't report it for the cpu
+pub fn parse_pressure(contents: &str) -> Option<ResourcePressure> {
+    let mut some = None;
+    let mut full = None;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("some") => some = parse_averages(fields),
+            Some("full") => full = parse_averages(fields),
+            _ => {}
+        }
+    }
+    Some(ResourcePressure { some: some?, full })
+}
+
+/// Parses the `avg10=<f> avg60=<f> avg300=<f> total=<n>` fields of a pressure line
+fn parse_averages<'a>(fields: impl Iterator<Item = &'a str>) -> Option<PressureAverages> {
+    let (mut avg10, mut avg60, mut avg300) = (None, None, None);
+    for field in fields {
+        match field.split_once('=') {
+            Some(("avg10", value)) => avg10 = value.parse().ok(),
+            Some(("avg60", value)) => avg60 = value.parse().ok(),
+            Some(("avg300", value)) => avg300 = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(PressureAverages {
+        avg10: avg10?,
+        avg60: avg60?,
+        avg300: avg300?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    const CPU: &str = "some avg10=1.50 avg60=0.75 avg300=0.25 total=123456\n\
+                       full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n";
+
+    #[test]
+    fn parses_some_and_full() {
+        let pressure = parse_pressure(CPU).unwrap();
+        assert_eq!(pressure.some.avg10, 1.5);
+        assert_eq!(pressure.some.avg60, 0.75);
+        assert_eq!(pressure.some.avg300, 0.25);
+        assert_eq!(pressure.full.unwrap().avg10, 0.0);
+    }
+
+    #[test]
+    fn full_line_is_optional() {
+        let pressure = parse_pressure("some avg10=2.00 avg60=1.00 avg300=0.50 total=1\n").unwrap();
+        assert_eq!(pressure.some.avg10, 2.0);
+        assert!(pressure.full.is_none());
+    }
+
+    #[test]
+    fn some_line_is_required() {
+        assert!(parse_pressure("full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n").is_none());
+        assert!(parse_pressure("some avg10=1.00 avg60=oops avg300=0.00 total=0\n").is_none());
+    }
+
+    #[test]
+    fn reads_the_available_resources() {
+        let root = Fixture::new("pressure");
+        root.write("cpu", CPU);
+        let stats = read_pressure_in(&root).unwrap();
+        assert!(stats.cpu.is_some());
+        assert!(stats.memory.is_none());
+        assert!(stats.io.is_none());
+    }
+
+    #[test]
+    fn finds_nothing_without_psi() {
+        assert!(read_pressure_in(&Fixture::new("pressure-missing")).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+// Probes the metrics that need privileges once at startup, for --fail-fast. Without it they are
+// lenient: SMART and power are disabled with a warning on their first permission failure, and
+// temperature logs empty lists when no sensor can be read, which is easily mistaken for a bug.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+use crate::power::{self, PowerError};
+use crate::smart::{self, SmartError};
+
+/// Enabled metrics to probe
+pub struct Probes {
+    pub temperature: bool,
+    pub smart: bool,
+    pub power: bool,
+}
+
+/// A privileged metric that can't be read, with the reason
+pub struct ProbeFailure {
+    pub metric: &'static str,
+    pub reason: String,
+}
+
+/// Reads each enabled metric once, returning the ones that read nothing because of missing
+/// privileges. Other failures, e.g. a missing `smartctl`, are left to the metric itself.
+pub fn probe(probes: &Probes) -> Vec<ProbeFailure> {
+    probe_with(
+        probes,
+        kernel_fs::sys_root(),
+        smart::read_smart,
+        power::read_power,
+    )
+}
+
+/// Like `probe`, looking for the temperature sensors in the sys directory at `sys_root`, or
+/// through sysinfo where there is none, and reading SMART and power with the given readers
+fn probe_with<S, P>(
+    probes: &Probes,
+    sys_root: Option<&Path>,
+    read_smart: impl FnOnce() -> Result<S, SmartError>,
+    read_power: impl FnOnce() -> Result<P, PowerError>,
+) -> Vec<ProbeFailure> {
+    let mut failures = Vec::new();
+    if probes.temperature && !has_temperature_sensor(sys_root) {
+        failures.push(ProbeFailure {
+            metric: "temperature",
+            reason: "no temperature sensor could be read, the sensors may need more privileges"
+                .to_string(),
+        });
+    }
+    if probes.smart
+        && let Err(e @ SmartError::PermissionDenied) = read_smart()
+    {
+        failures.push(ProbeFailure {
+            metric: "smart",
+            reason: e.to_string(),
+        });
+    }
+    if probes.power
+        && let Err(e @ PowerError::PermissionDenied) = read_power()
+    {
+        failures.push(ProbeFailure {
+            metric: "power",
+            reason: e.to_string(),
+        });
+    }
+    failures
+}
+
+/// Whether any `class/hwmon/*/temp*_input` file of the sys directory at `sys_root` can be read,
+/// which is where sysinfo reads the temperatures from on Linux
+fn has_temperature_sensor(sys_root: Option<&Path>) -> bool {
+    let Some(sys_root) = sys_root else {
+        return !sysinfo::Components::new_with_refreshed_list().is_empty();
+    };
+    let Ok(monitors) = std::fs::read_dir(sys_root.join("class/hwmon")) else {
+        return false;
+    };
+    monitors
+        .flatten()
+        .filter_map(|monitor| std::fs::read_dir(monitor.path()).ok())
+        .flat_map(|files| files.flatten())
+        .any(|file| {
+            let name = file.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("temp")
+                && name.ends_with("_input")
+                && std::fs::read_to_string(file.path()).is_ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    const ALL: Probes = Probes {
+        temperature: true,
+        smart: true,
+        power: true,
+    };
+
+    fn failed_metrics(failures: &[ProbeFailure]) -> Vec<&'static str> {
+        failures.iter().map(|failure| failure.metric).collect()
+    }
+
+    #[test]
+    fn denied_metrics_are_reported() {
+        let root = Fixture::new("privileges-denied");
+        root.write("class/hwmon/hwmon0/name", "coretemp\n");
+        let failures = probe_with(
+            &ALL,
+            Some(&root),
+            || Err::<(), _>(SmartError::PermissionDenied),
+            || Err::<(), _>(PowerError::PermissionDenied),
+        );
+        assert_eq!(failed_metrics(&failures), ["temperature", "smart", "power"]);
+        assert_eq!(failures[1].reason, SmartError::PermissionDenied.to_string());
+    }
+
+    #[test]
+    fn allowed_metrics_pass() {
+        let root = Fixture::new("privileges-allowed");
+        root.write("class/hwmon/hwmon0/temp1_input", "45000\n");
+        let failures = probe_with(&ALL, Some(&root), || Ok(Some(3)), || Ok(None::<()>));
+        assert!(failures.is_empty());
+
+        // Failures other than missing privileges are left to the metric
+        let failures = probe_with(
+            &ALL,
+            Some(&root),
+            || Err::<(), _>(SmartError::Failed("no smartctl".to_string())),
+            || Err::<(), _>(PowerError::Failed("no powermetrics".to_string())),
+        );
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn disabled_metrics_are_not_probed() {
+        let root = Fixture::new("privileges-disabled");
+        let probes = Probes {
+            temperature: false,
+            smart: false,
+            power: false,
+        };
+        let failures = probe_with(
+            &probes,
+            Some(&root),
+            || -> Result<(), _> { panic!("smart read while disabled") },
+            || -> Result<(), _> { panic!("power read while disabled") },
+        );
+        assert!(failures.is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+// Bounded history of the cpu and memory usage of the processes given with --track-pid, so a leak
+// shows up as a trend in a single message. A pid reused by a new process starts a new history,
+// detected by its start time changing.
+
+use std::collections::VecDeque;
+
+use crate::channels::TrackedSampleStats;
+
+pub struct ProcessHistory {
+    /// Start time of the process the samples belong to, `None` before the first sample
+    start_time: Option<u64>,
+    /// Name of the process, kept once it exits
+    name: String,
+    samples: VecDeque<TrackedSampleStats>,
+    capacity: usize,
+}
+
+impl ProcessHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            start_time: None,
+            name: String::new(),
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds a sample of the process started at `start_time`, dropping the oldest one when full
+    /// and the whole history when the pid now belongs to another process
+    pub fn record(&mut self, start_time: u64, name: &str, sample: TrackedSampleStats) {
+        if self.start_time != Some(start_time) {
+            self.samples.clear();
+            self.start_time = Some(start_time);
+            self.name = name.to_string();
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn start_time(&self) -> Option<u64> {
+        self.start_time
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Samples from the oldest to the newest
+    pub fn samples(&self) -> Vec<TrackedSampleStats> {
+        self.samples.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp_seconds: u64) -> TrackedSampleStats {
+        TrackedSampleStats {
+            timestamp_seconds,
+            cpu_usage: 0.0,
+            memory_usage_kb: 0,
+        }
+    }
+
+    fn timestamps(history: &ProcessHistory) -> Vec<u64> {
+        history
+            .samples()
+            .iter()
+            .map(|sample| sample.timestamp_seconds)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_samples() {
+        let mut history = ProcessHistory::new(3);
+        assert_eq!(history.start_time(), None);
+        for timestamp in 1..=5 {
+            history.record(100, "server", sample(timestamp));
+        }
+        assert_eq!(timestamps(&history), [3, 4, 5]);
+        assert_eq!(history.start_time(), Some(100));
+        assert_eq!(history.name(), "server");
+    }
+
+    #[test]
+    fn reused_pid_starts_a_new_history() {
+        let mut history = ProcessHistory::new(3);
+        history.record(100, "server", sample(1));
+        history.record(100, "server", sample(2));
+        history.record(200, "worker", sample(3));
+        assert_eq!(timestamps(&history), [3]);
+        assert_eq!(history.start_time(), Some(200));
+        assert_eq!(history.name(), "worker");
+    }
+}
@@ -0,0 +1,75 @@
+// Computes the read and write rates of each process from the growth of its cumulative disk
+// usage over the measured time between two samples. A pid whose start time changed belongs to a
+// new process, so its previous counters are discarded instead of producing a bogus rate.
+
+use std::time::Instant;
+
+/// Cumulative disk usage of a process at one sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IoSample {
+    pub start_time: u64,
+    pub total_read_bytes: u64,
+    pub total_written_bytes: u64,
+    pub at: Instant,
+}
+
+/// Bytes per second read and written between the two samples, zero without a previous sample of
+/// the same process
+pub fn rates(previous: Option<&IoSample>, current: &IoSample) -> (f64, f64) {
+    let Some(previous) = previous.filter(|previous| previous.start_time == current.start_time)
+    else {
+        return (0.0, 0.0);
+    };
+    let elapsed = current.at.duration_since(previous.at).as_secs_f64();
+    if elapsed <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let rate = |current: u64, previous: u64| current.saturating_sub(previous) as f64 / elapsed;
+    (
+        rate(current.total_read_bytes, previous.total_read_bytes),
+        rate(current.total_written_bytes, previous.total_written_bytes),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn sample(start_time: u64, read: u64, written: u64, at: Instant) -> IoSample {
+        IoSample {
+            start_time,
+            total_read_bytes: read,
+            total_written_bytes: written,
+            at,
+        }
+    }
+
+    #[test]
+    fn rates_are_per_second_of_measured_time() {
+        let start = Instant::now();
+        let previous = sample(100, 1000, 0, start);
+        let current = sample(100, 5000, 2000, start + Duration::from_secs(2));
+        assert_eq!(rates(Some(&previous), &current), (2000.0, 1000.0));
+    }
+
+    #[test]
+    fn rates_are_zero_without_a_previous_sample_of_the_process() {
+        let start = Instant::now();
+        let current = sample(100, 5000, 2000, start + Duration::from_secs(2));
+        assert_eq!(rates(None, &current), (0.0, 0.0));
+        // The pid was reused by a new process
+        let previous = sample(50, 1000, 0, start);
+        assert_eq!(rates(Some(&previous), &current), (0.0, 0.0));
+        assert_eq!(rates(Some(&current), &current), (0.0, 0.0));
+    }
+
+    #[test]
+    fn decreasing_counters_give_a_zero_rate() {
+        let start = Instant::now();
+        let previous = sample(100, 5000, 0, start);
+        let current = sample(100, 1000, 0, start + Duration::from_secs(1));
+        assert_eq!(rates(Some(&previous), &current), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,54 @@
+// Follows a process and its descendants through the process table. The set is rebuilt from the
+// parent of each process on every sample, so children that spawn or exit between samples are
+// picked up or dropped.
+
+use std::collections::{HashMap, HashSet};
+
+/// The `root` pid and the pids of all its transitive children, from (pid, parent pid) pairs
+pub fn descendants(
+    root: u32,
+    processes: impl IntoIterator<Item = (u32, Option<u32>)>,
+) -> HashSet<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, parent) in processes {
+        if let Some(parent) = parent {
+            children.entry(parent).or_default().push(pid);
+        }
+    }
+    let mut followed = HashSet::from([root]);
+    let mut pending = vec![root];
+    while let Some(pid) = pending.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            // A pid reused by a process whose parent is already followed can't loop forever
+            if followed.insert(child) {
+                pending.push(child);
+            }
+        }
+    }
+    followed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_transitive_children_only() {
+        let processes = [
+            (1, None),
+            (10, Some(1)),
+            (11, Some(10)),
+            (12, Some(11)),
+            (13, Some(10)),
+            (20, Some(1)),
+        ];
+        assert_eq!(descendants(10, processes), HashSet::from([10, 11, 12, 13]));
+        assert_eq!(descendants(12, processes), HashSet::from([12]));
+    }
+
+    #[test]
+    fn parent_cycles_terminate() {
+        let processes = [(10, Some(11)), (11, Some(10))];
+        assert_eq!(descendants(10, processes), HashSet::from([10, 11]));
+    }
+}
@@ -0,0 +1,127 @@
+// Reads the memory and load averages straight from `/proc/meminfo` and `/proc/loadavg`, for
+// --proc-fallback. Some restricted containers make sysinfo report zeros for them while the files
+// are still readable. Where the files can't be read either, e.g. off Linux, the readers return
+// `None` and the values reported by sysinfo are kept.
+
+use std::path::Path;
+
+use sysinfo::LoadAvg;
+
+use crate::kernel_fs;
+
+/// Memory sizes from `/proc/meminfo`, in KiB
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Meminfo {
+    pub total_kb: u64,
+    pub free_kb: u64,
+    pub available_kb: u64,
+    pub swap_total_kb: u64,
+    pub swap_free_kb: u64,
+}
+
+pub fn read_meminfo() -> Option<Meminfo> {
+    read_meminfo_in(kernel_fs::proc_root()?)
+}
+
+/// Reads `meminfo` in the proc directory at `root`
+pub fn read_meminfo_in(root: &Path) -> Option<Meminfo> {
+    parse_meminfo(&std::fs::read_to_string(root.join("meminfo")).ok()?)
+}
+
+/// Parses the contents of `/proc/meminfo`, `None` when it has no total memory
+pub fn parse_meminfo(contents: &str) -> Option<Meminfo> {
+    let mut meminfo = Meminfo::default();
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Every size is in kB, which the kernel means as KiB
+        let Some(kb) = value
+            .split_whitespace()
+            .next()
+            .and_then(|kb| kb.parse().ok())
+        else {
+            continue;
+        };
+        match key {
+            "MemTotal" => meminfo.total_kb = kb,
+            "MemFree" => meminfo.free_kb = kb,
+            "MemAvailable" => meminfo.available_kb = kb,
+            "SwapTotal" => meminfo.swap_total_kb = kb,
+            "SwapFree" => meminfo.swap_free_kb = kb,
+            _ => {}
+        }
+    }
+    (meminfo.total_kb > 0).then_some(meminfo)
+}
+
+pub fn read_loadavg() -> Option<LoadAvg> {
+    read_loadavg_in(kernel_fs::proc_root()?)
+}
+
+/// Reads `loadavg` in the proc directory at `root`
+pub fn read_loadavg_in(root: &Path) -> Option<LoadAvg> {
+    parse_loadavg(&std::fs::read_to_string(root.join("loadavg")).ok()?)
+}
+
+/// Parses the contents of `/proc/loadavg`, e.g. `0.52 0.58 0.59 1/467 12345`
+pub fn parse_loadavg(contents: &str) -> Option<LoadAvg> {
+    let mut averages = contents.split_whitespace().map(|value| value.parse().ok());
+    Some(LoadAvg {
+        one: averages.next()??,
+        five: averages.next()??,
+        fifteen: averages.next()??,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    const MEMINFO: &str = "MemTotal:       16303472 kB\n\
+                           MemFree:         1234567 kB\n\
+                           MemAvailable:    8765432 kB\n\
+                           Buffers:          345678 kB\n\
+                           SwapTotal:       2097148 kB\n\
+                           SwapFree:        2000000 kB\n\
+                           HugePages_Total:       0\n";
+
+    #[test]
+    fn parses_meminfo() {
+        assert_eq!(
+            parse_meminfo(MEMINFO),
+            Some(Meminfo {
+                total_kb: 16303472,
+                free_kb: 1234567,
+                available_kb: 8765432,
+                swap_total_kb: 2097148,
+                swap_free_kb: 2000000,
+            })
+        );
+    }
+
+    #[test]
+    fn meminfo_needs_total_memory() {
+        assert_eq!(parse_meminfo("MemFree: 1234 kB\n"), None);
+        assert_eq!(parse_meminfo("MemTotal: 0 kB\n"), None);
+    }
+
+    #[test]
+    fn parses_loadavg() {
+        let load = parse_loadavg("0.52 0.58 0.59 1/467 12345\n").unwrap();
+        assert_eq!((load.one, load.five, load.fifteen), (0.52, 0.58, 0.59));
+        assert!(parse_loadavg("0.52 0.58").is_none());
+        assert!(parse_loadavg("0.52 high 0.59").is_none());
+    }
+
+    #[test]
+    fn reads_the_proc_files() {
+        let root = Fixture::new("procfs");
+        root.write("meminfo", MEMINFO)
+            .write("loadavg", "1.00 2.00 3.00 2/100 42\n");
+        assert_eq!(read_meminfo_in(&root).unwrap().total_kb, 16303472);
+        assert_eq!(read_loadavg_in(&root).unwrap().fifteen, 3.0);
+        assert!(read_meminfo_in(&Fixture::new("procfs-missing")).is_none());
+    }
+}
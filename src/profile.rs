@@ -0,0 +1,99 @@
+// Presets of metrics and interval for common use cases. A preset only fills in the options that
+// were left at their default, so explicit flags and environment variables always win.
+
+use clap::{ArgMatches, ValueEnum, parser::ValueSource};
+
+use crate::Cli;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Profile {
+    /// cpu, memory and system info every 5 seconds
+    Light,
+    /// All metrics every second
+    Full,
+    /// cpu and temperature every 2 seconds
+    Thermal,
+    /// Networks every second
+    Network,
+}
+
+impl Profile {
+    fn interval(self) -> u64 {
+        match self {
+            Profile::Light => 5,
+            Profile::Full | Profile::Network => 1,
+            Profile::Thermal => 2,
+        }
+    }
+
+    /// Returns the ids of the metric flags enabled by the profile
+    fn metrics(self) -> &'static [&'static str] {
+        match self {
+            Profile::Light => &["cpu", "memory", "system"],
+            Profile::Full => &[
+                "cpu",
+                "memory",
+                "temperature",
+                "disks",
+                "networks",
+                "processes",
+                "system",
+            ],
+            Profile::Thermal => &["cpu", "temperature"],
+            Profile::Network => &["networks"],
+        }
+    }
+
+    /// Expands the profile into the parsed arguments
+    pub fn apply(self, args: &mut Cli, matches: &ArgMatches) {
+        let is_default = |id: &str| matches.value_source(id) == Some(ValueSource::DefaultValue);
+
+        for &metric in self.metrics() {
+            if !is_default(metric) {
+                continue;
+            }
+            match metric {
+                "cpu" => args.cpu = true,
+                "memory" => args.memory = true,
+                "temperature" => args.temperature = true,
+                "disks" => args.disks = true,
+                "networks" => args.networks = true,
+                "processes" => args.processes = true,
+                "system" => args.system = true,
+                _ => unreachable!("unknown metric {metric}"),
+            }
+        }
+        if is_default("interval") {
+            args.interval = self.interval();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+
+    fn apply(args: &[&str]) -> Cli {
+        let matches = Cli::command().get_matches_from(["fox-monitor"].iter().chain(args));
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.profile.unwrap().apply(&mut cli, &matches);
+        cli
+    }
+
+    #[test]
+    fn enables_the_metrics_and_interval_of_the_profile() {
+        let cli = apply(&["--profile", "thermal"]);
+        assert!(cli.cpu && cli.temperature);
+        assert!(!cli.memory && !cli.processes);
+        assert_eq!(cli.interval, 2);
+    }
+
+    #[test]
+    fn explicit_flags_take_precedence() {
+        let cli = apply(&["--profile", "light", "--interval", "3", "--networks"]);
+        assert!(cli.cpu && cli.memory && cli.system && cli.networks);
+        assert_eq!(cli.interval, 3);
+    }
+}
@@ -0,0 +1,238 @@
+// Serves the numeric fields of the latest logged messages as Prometheus gauges on
+// `http://<address>/metrics`, for a Prometheus server to scrape. Each field becomes a gauge named
+// after its topic and path, e.g. `fox_monitor_cpu_cores_usage`, and the items of a list are told
+// apart by the same labels as the OTLP attributes: their `index` plus their string fields.
+//
+// Only the latest sample of each topic is served. A new sample replaces every gauge of its topic,
+// so the items that went away, like exited processes, are no longer reported.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::otlp;
+use crate::output::{Message, OutputSink};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest request head read from a scraper, the rest is ignored
+const MAX_REQUEST_BYTES: usize = 8192;
+
+struct Gauge {
+    name: String,
+    unit: String,
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+
+/// Latest gauges of each topic
+type Gauges = Arc<Mutex<BTreeMap<String, Vec<Gauge>>>>;
+
+pub struct PrometheusExporter {
+    /// Shared with the thread answering the scrapes
+    gauges: Gauges,
+}
+
+impl PrometheusExporter {
+    /// Listens on `address` and answers the scrapes from a separate thread
+    pub fn bind(address: SocketAddr) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let gauges = Gauges::default();
+        std::thread::Builder::new()
+            .name("prometheus".to_string())
+            .spawn({
+                let gauges = gauges.clone();
+                move || {
+                    for stream in listener.incoming().flatten() {
+                        if let Err(e) = answer(stream, &gauges) {
+                            log::debug!("Failed to answer a Prometheus scrape: {e}");
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { gauges })
+    }
+}
+
+impl OutputSink for PrometheusExporter {
+    /// Replaces the gauges of the message's topic, with the units from the `<field>.unit` channel
+    /// metadata
+    fn write(&mut self, message: &Message) {
+        let Some(json) = message.json() else {
+            return;
+        };
+        let prefix = format!(
+            "fox_monitor_{}",
+            sanitize(message.topic().trim_start_matches('/'))
+        );
+        let metadata = message.channel.metadata();
+        let mut points = Vec::new();
+        otlp::flatten(json, "", &[], &mut points);
+        let gauges = points
+            .into_iter()
+            // The sequence number identifies the sample, it is not a measurement
+            .filter(|(path, _, _)| path != "seq")
+            .map(|(path, value, labels)| Gauge {
+                name: format!("{prefix}_{}", sanitize(&path)),
+                unit: metadata
+                    .get(&format!("{path}.unit"))
+                    .cloned()
+                    .unwrap_or_default(),
+                labels,
+                value,
+            })
+            .collect();
+        self.gauges
+            .lock()
+            .expect("Prometheus gauges lock poisoned")
+            .insert(message.topic().to_string(), gauges);
+    }
+}
+
+/// Answers a single HTTP request, with the gauges on `/metrics` and a 404 anywhere else
+fn answer(mut stream: TcpStream, gauges: &Gauges) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut chunk = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n")
+        && request.len() < MAX_REQUEST_BYTES
+    {
+        match stream.read(&mut chunk)? {
+            0 => break,
+            n => request.extend_from_slice(&chunk[..n]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            render(&gauges.lock().expect("Prometheus gauges lock poisoned")),
+        ),
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Formats the gauges in the Prometheus text format, each metric with its series
+fn render(gauges: &BTreeMap<String, Vec<Gauge>>) -> String {
+    let mut metrics: BTreeMap<&str, Vec<&Gauge>> = BTreeMap::new();
+    for gauge in gauges.values().flatten() {
+        metrics.entry(&gauge.name).or_default().push(gauge);
+    }
+    let mut text = String::new();
+    for (name, series) in metrics {
+        if !series[0].unit.is_empty() {
+            let _ = writeln!(text, "# HELP {name} In {}", series[0].unit);
+        }
+        let _ = writeln!(text, "# TYPE {name} gauge");
+        for gauge in series {
+            let _ = writeln!(text, "{name}{} {}", labels(&gauge.labels), gauge.value);
+        }
+    }
+    text
+}
+
+/// Formats the labels of a series, e.g. `{index="0",name="sda"}`
+fn labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{value}\"", sanitize(key))
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Replaces the characters that aren't allowed in a metric or label name with `_`
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+
+    #[test]
+    fn sanitizes_metric_names() {
+        assert_eq!(sanitize("cpu/cores.usage-1"), "cpu_cores_usage_1");
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(labels(&[]), "");
+        let labels = labels(&[
+            ("index".to_string(), "0".to_string()),
+            ("mount.point".to_string(), "C:\\ \"data\"\n".to_string()),
+        ]);
+        assert_eq!(labels, r#"{index="0",mount_point="C:\\ \"data\"\n"}"#);
+    }
+
+    #[test]
+    fn renders_the_latest_sample_of_each_topic() {
+        let mut exporter = PrometheusExporter {
+            gauges: Gauges::default(),
+        };
+        let channel = ChannelBuilder::new("/prometheus_render")
+            .message_encoding("json")
+            .metadata(BTreeMap::from([(
+                "usage.unit".to_string(),
+                "%".to_string(),
+            )]))
+            .build()
+            .unwrap();
+        exporter.write(&Message::new(&channel, br#"{"seq":1,"usage":10}"#, 0));
+        exporter.write(&Message::new(&channel, br#"{"seq":2,"usage":20.5}"#, 0));
+        let text = render(&exporter.gauges.lock().unwrap());
+        assert_eq!(
+            text,
+            "# HELP fox_monitor_prometheus_render_usage In %\n\
+             # TYPE fox_monitor_prometheus_render_usage gauge\n\
+             fox_monitor_prometheus_render_usage 20.5\n"
+        );
+    }
+
+    #[test]
+    fn list_items_are_told_apart_by_labels() {
+        let mut exporter = PrometheusExporter {
+            gauges: Gauges::default(),
+        };
+        let channel = ChannelBuilder::new("/prometheus_labels")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let payload = br#"{"disks":[{"name":"sda","read":1},{"name":"sdb","read":2}]}"#;
+        exporter.write(&Message::new(&channel, payload, 0));
+        let text = render(&exporter.gauges.lock().unwrap());
+        assert!(
+            text.contains("fox_monitor_prometheus_labels_disks_read{index=\"0\",name=\"sda\"} 1\n")
+        );
+        assert!(
+            text.contains("fox_monitor_prometheus_labels_disks_read{index=\"1\",name=\"sdb\"} 2\n")
+        );
+    }
+}
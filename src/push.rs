@@ -0,0 +1,283 @@
+// Pushes the logged samples as JSON to a remote HTTP collector. Samples are buffered and posted
+// together every push interval. When the collector is down the buffer is kept, up to a bounded
+// number of samples, and the post is retried with an increasing backoff.
+//
+// Only plain `http://` urls are supported, which keeps the client down to a single request over
+// a `TcpStream`.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::output::{Message, OutputSink};
+
+/// Samples kept while the collector is unreachable, the oldest are dropped beyond this
+const MAX_BUFFERED_SAMPLES: usize = 10_000;
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Delay before retrying after a first failure, doubled after each consecutive one
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Address of the collector, parsed from an `http://host[:port][/path]` url
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushUrl {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+/// Parses a `--push-url` value
+pub fn parse_url(url: &str) -> Result<PushUrl, String> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(format!("only http:// urls are supported, got `{url}`"));
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("invalid port in `{url}`"))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(format!("missing host in `{url}`"));
+    }
+    Ok(PushUrl {
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct Sample {
+    topic: String,
+    log_time: u64,
+    message: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Batch<'a> {
+    host: &'a str,
+    labels: &'a BTreeMap<String, String>,
+    samples: &'a VecDeque<Sample>,
+}
+
+pub struct Pusher {
+    url: PushUrl,
+    interval: Duration,
+    host: String,
+    labels: BTreeMap<String, String>,
+    buffer: VecDeque<Sample>,
+    /// Number of samples dropped since the last warning because the buffer was full
+    dropped: u64,
+    /// Delay before the next attempt after a failure, doubled after each one
+    backoff: Duration,
+    next_push: Instant,
+}
+
+impl Pusher {
+    pub fn new(url: PushUrl, interval: Duration, labels: BTreeMap<String, String>) -> Self {
+        Self {
+            url,
+            interval,
+            host: sysinfo::System::host_name().unwrap_or_else(|| "<unknown>".to_owned()),
+            labels,
+            buffer: VecDeque::new(),
+            dropped: 0,
+            backoff: INITIAL_BACKOFF,
+            next_push: Instant::now() + interval,
+        }
+    }
+
+    /// Posts the buffered samples if the push interval or the backoff has elapsed
+    fn push_if_due(&mut self) {
+        let now = Instant::now();
+        if now < self.next_push {
+            return;
+        }
+        self.push();
+    }
+
+    /// Posts the buffered samples, keeping them for the next attempt when the post fails
+    fn push(&mut self) {
+        let now = Instant::now();
+        if self.dropped > 0 {
+            log::warn!(
+                "Dropped {} samples while the collector was unreachable",
+                self.dropped
+            );
+            self.dropped = 0;
+        }
+        if self.buffer.is_empty() {
+            self.next_push = now + self.interval;
+            return;
+        }
+
+        let body = serde_json::to_vec(&Batch {
+            host: &self.host,
+            labels: &self.labels,
+            samples: &self.buffer,
+        })
+        .expect("Failed to serialize samples");
+        match post(&self.url, &body) {
+            Ok(()) => {
+                self.buffer.clear();
+                self.backoff = INITIAL_BACKOFF;
+                self.next_push = now + self.interval;
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to push {} samples to {}:{}, retrying in {:?}: {e}",
+                    self.buffer.len(),
+                    self.url.host,
+                    self.url.port,
+                    self.backoff
+                );
+                self.next_push = now + self.backoff;
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+impl OutputSink for Pusher {
+    /// Buffers a message, dropping the oldest sample when the buffer is full
+    fn write(&mut self, message: &Message) {
+        let Some(json) = message.json() else {
+            return;
+        };
+        if self.buffer.len() == MAX_BUFFERED_SAMPLES {
+            self.buffer.pop_front();
+            self.dropped += 1;
+        }
+        self.buffer.push_back(Sample {
+            topic: message.topic().to_string(),
+            log_time: message.log_time,
+            message: json.clone(),
+        });
+    }
+
+    fn flush(&mut self) {
+        self.push_if_due();
+    }
+
+    /// Posts the samples that are still waiting for the next push
+    fn close(mut self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        self.push();
+        Ok(Vec::new())
+    }
+}
+
+/// Sends a JSON body in a single HTTP/1.1 request, succeeding on any 2xx status
+pub fn post(url: &PushUrl, body: &[u8]) -> std::io::Result<()> {
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::other("host did not resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        url.path,
+        url.host,
+        url.port,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    // Only the status line matters, the rest of the response is ignored
+    let mut response = [0; 64];
+    let mut read = 0;
+    while read < response.len() {
+        match stream.read(&mut response[read..])? {
+            0 => break,
+            n => read += n,
+        }
+        if response[..read].contains(&b'\n') {
+            break;
+        }
+    }
+    let status_line = String::from_utf8_lossy(&response[..read]);
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        Some(status) => Err(std::io::Error::other(format!(
+            "collector returned {status}"
+        ))),
+        None => Err(std::io::Error::other("invalid response from collector")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn parses_http_urls() {
+        let url = parse_url("http://collector:8080/ingest/v1").unwrap();
+        assert_eq!(url.host, "collector");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/ingest/v1");
+        let url = parse_url("http://collector").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+    }
+
+    #[test]
+    fn rejects_unsupported_urls() {
+        assert!(parse_url("https://collector").is_err());
+        assert!(parse_url("http://collector:http/").is_err());
+        assert!(parse_url("http://:8080/").is_err());
+    }
+
+    /// Answers a single request with `status`, returning the request it received
+    fn collector(status: &'static str) -> (PushUrl, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = parse_url(&format!("http://{}/ingest", listener.local_addr().unwrap())).unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut chunk = [0; 1024];
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut chunk).unwrap();
+                request.extend_from_slice(&chunk[..n]);
+            }
+            write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn posts_the_body() {
+        let (url, collector) = collector("204 No Content");
+        post(&url, br#"{"samples":[]}"#).unwrap();
+        let request = collector.join().unwrap();
+        assert!(request.starts_with("POST /ingest HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Length: 14\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"samples\":[]}"));
+    }
+
+    #[test]
+    fn fails_on_an_error_status() {
+        let (url, collector) = collector("503 Service Unavailable");
+        let error = post(&url, b"{}").unwrap_err();
+        assert_eq!(error.to_string(), "collector returned 503");
+        collector.join().unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+// Writes the logged channels to mcap files, either all of them to a single file or each channel
+// to its own file (`cpu.mcap`, `processes.mcap`, ...) so large captures can be partially loaded.
+//
+// The single file is created at startup. A split channel's file is created the first time the
+// channel logs a message, so only the channels that are actually logged get a file, but the files
+// of the expected topics are checked at startup so a leftover file fails the run right away. A
+// channel is added to its file, with its schema, on its first message.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::output::{Message, OutputSink};
+
+/// Directory the mcap files for `--path` are written to
+pub fn directory_of(path: &Path) -> &Path {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// Creates the directory the mcap files for `--path` are written to, with its missing parents
+pub fn create_directory_of(path: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory_of(path))
+}
+
+/// Checks that files can be created in `directory` by creating and removing an empty one, so an
+/// unwritable directory is reported before anything starts
+pub fn check_writable(directory: &Path) -> std::io::Result<()> {
+    let probe = directory.join(format!(".fox-monitor-{}.tmp", std::process::id()));
+    File::create_new(&probe)?;
+    std::fs::remove_file(&probe)
+}
+
+enum Layout {
+    /// Every channel in a single file
+    Single,
+    /// Each channel in its own file in this directory
+    Split(PathBuf),
+}
+
+struct McapFile {
+    writer: mcap::Writer<BufWriter<File>>,
+    path: PathBuf,
+}
+
+pub struct McapOutput {
+    layout: Layout,
+    overwrite: bool,
+    files: Vec<McapFile>,
+    /// Index in `files` and id in that file of each topic's channel
+    channels: HashMap<String, (usize, u16)>,
+    /// Topics whose file couldn't be created, which are not retried
+    failed: HashSet<String>,
+}
+
+impl McapOutput {
+    /// Starts recording every channel to the file at `path`
+    pub fn single(path: &Path, overwrite: bool) -> mcap::McapResult<Self> {
+        let file = create_file(path, overwrite)?;
+        Ok(Self {
+            layout: Layout::Single,
+            overwrite,
+            files: vec![file],
+            channels: HashMap::new(),
+            failed: HashSet::new(),
+        })
+    }
+
+    /// Starts recording each channel to its own file in `directory`, failing when the file of
+    /// one of the expected `topics` already exists and `overwrite` is not set
+    pub fn split(directory: &Path, overwrite: bool, topics: &[&str]) -> std::io::Result<Self> {
+        if !overwrite {
+            for topic in topics {
+                let path = directory.join(file_name(topic));
+                if path.exists() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", path.display()),
+                    ));
+                }
+            }
+        }
+        Ok(Self {
+            layout: Layout::Split(directory.to_path_buf()),
+            overwrite,
+            files: Vec::new(),
+            channels: HashMap::new(),
+            failed: HashSet::new(),
+        })
+    }
+
+    /// Index of the file and id of the channel of a message, adding the channel on first use
+    fn channel(&mut self, message: &Message) -> mcap::McapResult<(usize, u16)> {
+        if let Some(&channel) = self.channels.get(message.topic()) {
+            return Ok(channel);
+        }
+        let index = match &self.layout {
+            Layout::Single => 0,
+            Layout::Split(directory) => {
+                let path = directory.join(file_name(message.topic()));
+                let file = create_file(&path, self.overwrite).inspect_err(|_| {
+                    self.failed.insert(message.topic().to_string());
+                })?;
+                self.files.push(file);
+                self.files.len() - 1
+            }
+        };
+        let writer = &mut self.files[index].writer;
+        let channel = message.channel;
+        let schema_id = match channel.schema() {
+            Some(schema) => writer.add_schema(&schema.name, &schema.encoding, &schema.data)?,
+            None => 0,
+        };
+        let channel_id = writer.add_channel(
+            schema_id,
+            channel.topic(),
+            channel.message_encoding(),
+            channel.metadata(),
+        )?;
+        self.channels
+            .insert(channel.topic().to_string(), (index, channel_id));
+        Ok((index, channel_id))
+    }
+}
+
+impl OutputSink for McapOutput {
+    fn write(&mut self, message: &Message) {
+        if self.failed.contains(message.topic()) {
+            return;
+        }
+        let written = self.channel(message).and_then(|(index, channel_id)| {
+            self.files[index].writer.write_to_known_channel(
+                &mcap::records::MessageHeader {
+                    channel_id,
+                    sequence: message.sequence,
+                    log_time: message.log_time,
+                    publish_time: message.log_time,
+                },
+                message.payload,
+            )
+        });
+        if let Err(e) = written {
+            log::error!("Failed to write {} to the mcap file: {e}", message.topic());
+            if self.failed.contains(message.topic()) {
+                log::error!("Not recording {} anymore", message.topic());
+            }
+        }
+    }
+
+    /// Finalizes every file, even when one fails, returning their paths or the first error
+    fn close(self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::with_capacity(self.files.len());
+        let mut error = None;
+        for mut file in self.files {
+            match file.writer.finish() {
+                Ok(_) => paths.push(file.path),
+                Err(e) => {
+                    log::error!("Failed to finish {}: {e}", file.path.display());
+                    error.get_or_insert(std::io::Error::other(e));
+                }
+            }
+        }
+        paths.sort();
+        error.map_or(Ok(paths), Err)
+    }
+}
+
+fn create_file(path: &Path, overwrite: bool) -> mcap::McapResult<McapFile> {
+    if overwrite && path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(McapFile {
+        writer: mcap::Writer::new(BufWriter::new(File::create_new(path)?))?,
+        path: path.to_path_buf(),
+    })
+}
+
+/// Name of the file of a channel, its topic without the leading slash, e.g. `/cpu` is written to
+/// `cpu.mcap`
+pub fn file_name(topic: &str) -> String {
+    format!("{}.mcap", topic.trim_start_matches('/').replace('/', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn file_names_follow_the_topic() {
+        assert_eq!(file_name("/cpu"), "cpu.mcap");
+        assert_eq!(
+            file_name("/temperature_summary"),
+            "temperature_summary.mcap"
+        );
+        assert_eq!(file_name("/robot/cpu"), "robot_cpu.mcap");
+    }
+
+    #[test]
+    fn split_writes_each_logged_channel_to_its_file() {
+        let root = Fixture::new("recording-split");
+        let channels = ["/recording_split_a", "/recording_split_b"].map(|topic| {
+            ChannelBuilder::new(topic)
+                .message_encoding("json")
+                .build()
+                .unwrap()
+        });
+        let topics = ["/recording_split_a", "/recording_split_b", "/never_logged"];
+        let mut output = Box::new(McapOutput::split(&root, false, &topics).unwrap());
+        for channel in &channels {
+            output.write(&Message::new(channel, b"{}", 1));
+        }
+        output.write(&Message::new(&channels[0], b"{}", 2));
+        let paths = output.close().unwrap();
+
+        assert_eq!(
+            paths,
+            [
+                root.join("recording_split_a.mcap"),
+                root.join("recording_split_b.mcap")
+            ]
+        );
+        let counts = crate::verify::verify_file(&paths[0]).unwrap();
+        assert_eq!(counts["/recording_split_a"], 2);
+        assert!(!root.join("never_logged.mcap").exists());
+    }
+
+    #[test]
+    fn split_fails_on_a_leftover_file_unless_overwriting() {
+        let root = Fixture::new("recording-leftover");
+        root.write("cpu.mcap", "");
+        let error = McapOutput::split(&root, false, &["/memory", "/cpu"])
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(McapOutput::split(&root, true, &["/memory", "/cpu"]).is_ok());
+    }
+
+    #[test]
+    fn writable_check_leaves_no_file_behind() {
+        let root = Fixture::new("recording-writable");
+        check_writable(&root).unwrap();
+        assert_eq!(std::fs::read_dir(&*root).unwrap().count(), 0);
+        assert!(check_writable(&root.join("missing")).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn read_only_directory_is_not_writable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Root bypasses the permission bits, the directory would still be writable
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let root = Fixture::new("recording-read-only");
+        std::fs::set_permissions(&*root, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let result = check_writable(&root);
+        std::fs::set_permissions(&*root, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            result.unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn missing_parent_directories_are_created() {
+        let root = Fixture::new("recording-create-dirs");
+        let path = root.join("runs/2024/run.mcap");
+        create_directory_of(&path).unwrap();
+        assert!(root.join("runs/2024").is_dir());
+        check_writable(directory_of(&path)).unwrap();
+        // Already existing
+        create_directory_of(&path).unwrap();
+        assert_eq!(directory_of(Path::new("run.mcap")), Path::new("."));
+    }
+
+    #[test]
+    fn failing_file_doesnt_stop_the_others_from_finishing() {
+        let root = Fixture::new("recording-finish-error");
+        let topics = [
+            "/recording_finish_a",
+            "/recording_finish_b",
+            "/recording_finish_c",
+        ];
+        let mut output = Box::new(McapOutput::split(&root, false, &topics).unwrap());
+        for topic in topics {
+            let channel = ChannelBuilder::new(topic)
+                .message_encoding("json")
+                .build()
+                .unwrap();
+            output.write(&Message::new(&channel, b"{}", 1));
+        }
+        // A file opened read-only, which fails once the buffered data is flushed on finish
+        let read_only = root.join("read_only.mcap");
+        File::create_new(&read_only).unwrap();
+        output.files[1] = McapFile {
+            writer: mcap::Writer::new(BufWriter::new(File::open(&read_only).unwrap())).unwrap(),
+            path: read_only,
+        };
+
+        assert!(output.close().is_err());
+        for finished in ["recording_finish_a.mcap", "recording_finish_c.mcap"] {
+            assert!(crate::verify::verify_file(&root.join(finished)).is_ok());
+        }
+    }
+}
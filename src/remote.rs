@@ -0,0 +1,203 @@
+// Ingests the metrics of a remote host. A transport runs fox-monitor with `--format jsonl` on the
+// remote host and pipes its stdout back, and each line is written again on a local channel, so the
+// remote samples reach the same outputs as local ones.
+//
+// SSH is the only transport for now, another one only has to build the command that runs the
+// remote fox-monitor.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use foxglove::{Channel, ChannelBuilder, Schema};
+
+use crate::jsonl::Line;
+use crate::output::{self, Message, Outputs};
+
+/// Runs a command on a remote host
+pub trait Transport {
+    /// Command that runs `command` on the remote host, with the remote stdout as its stdout
+    fn command(&self, command: &str) -> Command;
+}
+
+/// Runs the command through `ssh`, without prompting since there is no terminal to answer
+pub struct Ssh {
+    pub destination: String,
+}
+
+impl Transport for Ssh {
+    fn command(&self, command: &str) -> Command {
+        let mut ssh = Command::new("ssh");
+        ssh.args(["-T", "-o", "BatchMode=yes", &self.destination, command]);
+        ssh
+    }
+}
+
+#[derive(Debug)]
+pub enum RemoteError {
+    Io(std::io::Error),
+    /// The remote command exited unsuccessfully, with its exit code if any
+    Exited(Option<i32>),
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteError::Io(e) => write!(f, "failed to run the remote command: {e}"),
+            RemoteError::Exited(Some(code)) => {
+                write!(f, "the remote command exited with code {code}")
+            }
+            RemoteError::Exited(None) => write!(f, "the remote command was killed"),
+        }
+    }
+}
+
+/// Runs `command` with `--format jsonl` appended through the transport and writes every line it
+/// prints to the outputs, until it exits or `done` is set. Returns the number of logged messages.
+pub fn ingest(
+    transport: &dyn Transport,
+    command: &str,
+    outputs: &Outputs,
+    done: &AtomicBool,
+) -> Result<u64, RemoteError> {
+    let mut child = transport
+        .command(&format!("{command} --format jsonl"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(RemoteError::Io)?;
+    let stdout = child.stdout.take().expect("Remote stdout is piped");
+    let mut channels: HashMap<String, Arc<Channel>> = HashMap::new();
+    let mut logged = 0;
+    let mut last_flush = Instant::now();
+
+    for line in BufReader::new(stdout).lines() {
+        let line = line.map_err(RemoteError::Io)?;
+        if done.load(Ordering::Relaxed) {
+            break;
+        }
+        // The remote run report and other plain output aren't messages
+        let Ok(line) = serde_json::from_str::<Line>(&line) else {
+            log::debug!("Ignoring remote output: {line}");
+            continue;
+        };
+        let channel = channels
+            .entry(line.topic.clone())
+            .or_insert_with(|| build_channel(&line));
+        let payload =
+            serde_json::to_vec(&line.message).expect("Failed to serialize remote message");
+        outputs.write(&Message::new(channel, &payload, line.log_time));
+        if last_flush.elapsed() >= output::FLUSH_INTERVAL {
+            outputs.flush();
+            last_flush = Instant::now();
+        }
+        logged += 1;
+    }
+
+    if done.load(Ordering::Relaxed) {
+        // Already stopped if the interrupt reached the whole process group
+        let _ = child.kill();
+        child.wait().map_err(RemoteError::Io)?;
+        return Ok(logged);
+    }
+    let status = child.wait().map_err(RemoteError::Io)?;
+    if !status.success() {
+        return Err(RemoteError::Exited(status.code()));
+    }
+    Ok(logged)
+}
+
+/// Registers the local channel of a remote topic, with the schema and metadata of its first line
+fn build_channel(line: &Line) -> Arc<Channel> {
+    ChannelBuilder::new(line.topic.clone())
+        .message_encoding("json")
+        .schema(line.schema.as_ref().map(|schema| {
+            Schema::new(
+                schema.name.clone(),
+                schema.encoding.clone(),
+                schema.data.clone().into_bytes(),
+            )
+        }))
+        .metadata(line.metadata.clone().unwrap_or_default())
+        .build()
+        .expect("Failed to create remote channel")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+    use crate::output::OutputSink;
+
+    /// Runs the command with the local shell instead of a remote host
+    struct Shell;
+
+    impl Transport for Shell {
+        fn command(&self, command: &str) -> Command {
+            let mut shell = Command::new("sh");
+            shell.args(["-c", command]);
+            shell
+        }
+    }
+
+    /// Keeps the topic, log time, payload and schema name of the messages written to it
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    impl OutputSink for Capture {
+        fn write(&mut self, message: &Message) {
+            let description = message
+                .channel
+                .schema()
+                .map_or("no schema", |schema| schema.name.as_str());
+            self.0.lock().unwrap().push(format!(
+                "{} {} {} ({description})",
+                message.topic(),
+                message.log_time,
+                String::from_utf8_lossy(message.payload)
+            ));
+        }
+    }
+
+    #[test]
+    fn logs_the_remote_lines_on_local_channels() {
+        let root = Fixture::new("remote");
+        root.write(
+            "lines.jsonl",
+            concat!(
+                r#"{"topic":"/remote_cpu","log_time":5,"message":{"usage":1},"#,
+                r#""schema":{"name":"CpuStats","encoding":"jsonschema","data":"{}"}}"#,
+                "\nRun report\n",
+                r#"{"topic":"/remote_cpu","log_time":6,"message":{"usage":2}}"#,
+                "\n",
+            ),
+        );
+        let outputs = Outputs::new();
+        let capture = Capture::default();
+        outputs.add(Box::new(capture.clone()));
+        // `true` ignores the `--format jsonl` appended to the command
+        let command = format!("cat {}; true", root.join("lines.jsonl").display());
+        let logged = ingest(&Shell, &command, &outputs, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(logged, 2);
+        assert_eq!(
+            *capture.0.lock().unwrap(),
+            [
+                r#"/remote_cpu 5 {"usage":1} (CpuStats)"#,
+                r#"/remote_cpu 6 {"usage":2} (CpuStats)"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_remote_command_is_an_error() {
+        let error = ingest(&Shell, "false", &Outputs::new(), &AtomicBool::new(false)).unwrap_err();
+        assert!(matches!(error, RemoteError::Exited(Some(1))));
+    }
+}
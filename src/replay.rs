@@ -0,0 +1,170 @@
+// Replays a recorded mcap file, publishing its messages again with their original spacing (or
+// sped up) so dashboards can be tested without a live machine. Each recorded channel is registered
+// again with the schema, encoding and metadata it was recorded with.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use foxglove::{Channel, ChannelBuilder, Schema};
+
+use crate::clock::LogClock;
+use crate::output::{self, Message, Outputs};
+use crate::verify::VerifyError;
+
+/// Writes every message of the mcap file at `path` to the outputs, waiting between messages for their
+/// original interval divided by `speed`, until the file ends or `done` is set. The messages are
+/// timestamped when published, shifted by `offset_ns`. Returns the number of published messages.
+pub fn replay_file(
+    path: &Path,
+    speed: f64,
+    offset_ns: i64,
+    outputs: &Outputs,
+    done: &AtomicBool,
+) -> Result<u64, VerifyError> {
+    let contents = std::fs::read(path).map_err(VerifyError::Io)?;
+    let clock = LogClock::monotonic().with_offset(offset_ns);
+    let start = Instant::now();
+    let mut first_log_time = None;
+    let mut channels: HashMap<u16, Arc<Channel>> = HashMap::new();
+    let mut published = 0;
+    let mut last_flush = start;
+
+    for message in mcap::MessageStream::new(&contents).map_err(VerifyError::Corrupt)? {
+        let message = message.map_err(VerifyError::Corrupt)?;
+        if done.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let first_log_time = *first_log_time.get_or_insert(message.log_time);
+        let offset = Duration::from_nanos(message.log_time.saturating_sub(first_log_time));
+        let due = start + offset.div_f64(speed);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+
+        let channel = match channels.get(&message.channel.id) {
+            Some(channel) => channel,
+            None => {
+                let recorded = &message.channel;
+                let channel = ChannelBuilder::new(recorded.topic.clone())
+                    .message_encoding(&recorded.message_encoding)
+                    .schema(recorded.schema.as_ref().map(|schema| {
+                        Schema::new(
+                            schema.name.clone(),
+                            schema.encoding.clone(),
+                            schema.data.to_vec(),
+                        )
+                    }))
+                    .metadata(recorded.metadata.clone())
+                    .build()
+                    .expect("Failed to create replay channel");
+                channels.entry(recorded.id).or_insert(channel)
+            }
+        };
+        let log_time = clock.now_nanos();
+        outputs.write(&Message::new(channel, &message.data, log_time));
+        if last_flush.elapsed() >= output::FLUSH_INTERVAL {
+            outputs.flush();
+            last_flush = Instant::now();
+        }
+        published += 1;
+    }
+    Ok(published)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    use foxglove::Context;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+    use crate::output::OutputSink;
+    use crate::recording::McapOutput;
+
+    /// Topic, log time and `usage.unit` metadata of a written message
+    type Captured = (String, u64, Option<String>);
+
+    /// Keeps the messages written to it
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Captured>>>);
+
+    impl OutputSink for Capture {
+        fn write(&mut self, message: &Message) {
+            let unit = message.channel.metadata().get("usage.unit").cloned();
+            self.0
+                .lock()
+                .unwrap()
+                .push((message.topic().to_string(), message.log_time, unit));
+        }
+    }
+
+    /// Records messages 10ms apart on `topic` to `path`, from a separate context so the replay
+    /// can register the topic again
+    fn record(path: &Path, topic: &str, messages: u64) {
+        let channel = ChannelBuilder::new(topic)
+            .context(&Context::new())
+            .message_encoding("json")
+            .metadata(BTreeMap::from([(
+                "usage.unit".to_string(),
+                "%".to_string(),
+            )]))
+            .build()
+            .unwrap();
+        let mut output = Box::new(McapOutput::single(path, false).unwrap());
+        for index in 0..messages {
+            let log_time = 1_000_000_000 + index * 10_000_000;
+            output.write(&Message::new(&channel, br#"{"usage":1}"#, log_time));
+        }
+        output.close().unwrap();
+    }
+
+    #[test]
+    fn publishes_the_recorded_messages_with_new_timestamps() {
+        let root = Fixture::new("replay");
+        let path = root.join("run.mcap");
+        record(&path, "/replay_publish", 3);
+        let outputs = Outputs::new();
+        let capture = Capture::default();
+        outputs.add(Box::new(capture.clone()));
+
+        let start = LogClock::wall_clock().now_nanos();
+        let started = Instant::now();
+        let published = replay_file(&path, 1.0, 0, &outputs, &AtomicBool::new(false)).unwrap();
+        assert_eq!(published, 3);
+        // The original 10ms spacing is kept
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        let messages = capture.0.lock().unwrap();
+        assert!(
+            messages
+                .iter()
+                .all(|(topic, log_time, unit)| topic == "/replay_publish"
+                    && *log_time >= start
+                    && unit.as_deref() == Some("%"))
+        );
+    }
+
+    #[test]
+    fn stops_once_done() {
+        let root = Fixture::new("replay-done");
+        let path = root.join("run.mcap");
+        record(&path, "/replay_done", 3);
+        let published = replay_file(&path, 1.0, 0, &Outputs::new(), &AtomicBool::new(true));
+        assert_eq!(published.unwrap(), 0);
+        assert!(matches!(
+            replay_file(
+                &root.join("missing.mcap"),
+                1.0,
+                0,
+                &Outputs::new(),
+                &AtomicBool::new(false)
+            ),
+            Err(VerifyError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+// Summary of the data produced during a run, printed at shutdown and optionally saved as JSON.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub samples: BTreeMap<String, u64>,
+    pub total_samples: u64,
+    /// Size of the mcap file, `None` when no mcap file was written
+    pub mcap_size_bytes: Option<u64>,
+}
+
+impl RunReport {
+    pub fn new(samples: BTreeMap<String, u64>, mcap_size_bytes: Option<u64>) -> Self {
+        Self {
+            total_samples: samples.values().sum(),
+            samples,
+            mcap_size_bytes,
+        }
+    }
+
+    pub fn print(&self) {
+        let width = self
+            .samples
+            .keys()
+            .map(String::len)
+            .chain(["Channel".len(), "Total".len()])
+            .max()
+            .unwrap_or_default();
+
+        println!("{:<width$}  Samples", "Channel");
+        for (topic, count) in &self.samples {
+            println!("{topic:<width$}  {count}");
+        }
+        println!("{:<width$}  {}", "Total", self.total_samples);
+        if let Some(size) = self.mcap_size_bytes {
+            println!("MCAP file size: {size} bytes");
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn totals_the_samples_of_every_channel() {
+        let samples = BTreeMap::from([("/cpu".to_string(), 3), ("/memory".to_string(), 2)]);
+        let report = RunReport::new(samples, Some(1024));
+        assert_eq!(report.total_samples, 5);
+    }
+
+    #[test]
+    fn writes_the_report_as_json() {
+        let root = Fixture::new("report");
+        let path = root.join("report.json");
+        let samples = BTreeMap::from([("/cpu".to_string(), 3)]);
+        RunReport::new(samples, None).write(&path).unwrap();
+        let report: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "samples": {"/cpu": 3},
+                "total_samples": 3,
+                "mcap_size_bytes": null,
+            })
+        );
+    }
+}
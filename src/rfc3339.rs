@@ -0,0 +1,78 @@
+// Formats Unix timestamps as RFC 3339 strings, e.g. `2024-03-01T12:00:00Z`, in UTC or in a time
+// zone of the system time zone database, e.g. `2024-03-01T13:00:00+01:00` for Europe/Paris. The
+// conversion is done by jiff with the time zone loaded once at startup, so it never touches the
+// `TZ` variable or the C library's time zone state.
+//
+// jiff is used instead of chrono-tz: it reads the system time zone database instead of compiling a
+// copy of it into the binary, and it needs no chrono on top. The duration parsing uses it too.
+
+use jiff::Timestamp;
+pub use jiff::tz::TimeZone;
+
+/// Parses `utc`, `local` or an IANA time zone name found in the system time zone database
+pub fn parse_time_zone(name: &str) -> Result<TimeZone, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "utc" => Ok(TimeZone::UTC),
+        "local" => Ok(TimeZone::system()),
+        _ => TimeZone::get(name).map_err(|_| {
+            format!(
+                "unknown time zone `{name}`, expected utc, local or an IANA name like Europe/Paris"
+            )
+        }),
+    }
+}
+
+/// Formats the seconds since the Unix epoch in the time zone, an empty string for a zero
+/// timestamp which sysinfo reports when the start time is unknown
+pub fn format_epoch_seconds(seconds: u64, time_zone: &TimeZone) -> String {
+    let Some(timestamp) = i64::try_from(seconds)
+        .ok()
+        .filter(|&seconds| seconds != 0)
+        .and_then(|seconds| Timestamp::from_second(seconds).ok())
+    else {
+        return String::new();
+    };
+    if time_zone.iana_name() == Some("UTC") {
+        return timestamp.strftime("%Y-%m-%dT%H:%M:%SZ").to_string();
+    }
+    timestamp
+        .to_zoned(time_zone.clone())
+        .strftime("%Y-%m-%dT%H:%M:%S%:z")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(
+            format_epoch_seconds(1_709_294_400, &TimeZone::UTC),
+            "2024-03-01T12:00:00Z"
+        );
+    }
+
+    #[test]
+    fn unknown_start_time_is_empty() {
+        assert_eq!(format_epoch_seconds(0, &TimeZone::UTC), "");
+        assert_eq!(format_epoch_seconds(u64::MAX, &TimeZone::UTC), "");
+    }
+
+    #[test]
+    fn formats_in_other_time_zones_with_their_offset() {
+        let plus_one = TimeZone::fixed(jiff::tz::offset(1));
+        assert_eq!(
+            format_epoch_seconds(1_709_294_400, &plus_one),
+            "2024-03-01T13:00:00+01:00"
+        );
+    }
+
+    #[test]
+    fn parses_time_zone_names() {
+        assert_eq!(parse_time_zone("UTC").unwrap(), TimeZone::UTC);
+        assert!(parse_time_zone("local").is_ok());
+        let error = parse_time_zone("Mars/Olympus_Mons").unwrap_err();
+        assert!(error.starts_with("unknown time zone `Mars/Olympus_Mons`"));
+    }
+}
@@ -0,0 +1,105 @@
+// Min, average and max of the cpu usage, the used memory and the load over each
+// --summary-interval, logged on /rollup. The values are accumulated as the metrics log them, so a
+// rollup only covers the metrics that are enabled, and the accumulators are reset after each one.
+
+use std::time::{Duration, Instant};
+
+use crate::channels::{RollupStats, RollupValueStats};
+
+#[derive(Default)]
+pub struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl Accumulator {
+    pub fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Summary of the values added since the last reset, `None` without any
+    pub fn summary(&self) -> Option<RollupValueStats> {
+        (self.count > 0).then(|| RollupValueStats {
+            min: self.min,
+            avg: self.sum / self.count as f64,
+            max: self.max,
+        })
+    }
+}
+
+pub struct Rollup {
+    interval: Duration,
+    window_start: Instant,
+    pub cpu_usage: Accumulator,
+    pub memory_used_kb: Accumulator,
+    pub load_avg_one: Accumulator,
+}
+
+impl Rollup {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            window_start: Instant::now(),
+            cpu_usage: Accumulator::default(),
+            memory_used_kb: Accumulator::default(),
+            load_avg_one: Accumulator::default(),
+        }
+    }
+
+    /// Summarizes the window and starts a new one once the interval has elapsed
+    pub fn take_if_due(&mut self) -> Option<RollupStats> {
+        let window = self.window_start.elapsed();
+        if window < self.interval {
+            return None;
+        }
+        let stats = RollupStats {
+            window_seconds: window.as_secs_f64(),
+            cpu_usage: self.cpu_usage.summary(),
+            memory_used_kb: self.memory_used_kb.summary(),
+            load_avg_one: self.load_avg_one.summary(),
+        };
+        *self = Self::new(self.interval);
+        Some(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_the_added_values() {
+        let mut accumulator = Accumulator::default();
+        assert!(accumulator.summary().is_none());
+        for value in [4.0, -2.0, 10.0] {
+            accumulator.add(value);
+        }
+        let summary = accumulator.summary().unwrap();
+        assert_eq!((summary.min, summary.avg, summary.max), (-2.0, 4.0, 10.0));
+    }
+
+    #[test]
+    fn rollup_is_taken_once_the_interval_elapsed() {
+        let mut rollup = Rollup::new(Duration::ZERO);
+        rollup.cpu_usage.add(50.0);
+        let stats = rollup.take_if_due().unwrap();
+        assert_eq!(stats.cpu_usage.unwrap().avg, 50.0);
+        assert!(stats.memory_used_kb.is_none());
+        // The accumulators start over with the new window
+        assert!(rollup.take_if_due().unwrap().cpu_usage.is_none());
+
+        let mut rollup = Rollup::new(Duration::from_secs(3600));
+        rollup.cpu_usage.add(50.0);
+        assert!(rollup.take_if_due().is_none());
+    }
+}
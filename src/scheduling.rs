@@ -0,0 +1,85 @@
+// Reads the scheduling priority and nice value of a process from `/proc/<pid>/stat`, which
+// explain why a process gets less cpu than it asks for. Unlike most of `/proc/<pid>`, the stat file
+// is readable for every user's processes, so the values are only missing for a process that exited
+// since it was listed.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scheduling {
+    /// Kernel priority, e.g. 20 for a normal process at nice 0 and negative for real-time ones
+    pub priority: i32,
+    /// From -20, the most favorable, to 19
+    pub nice: i32,
+}
+
+pub fn read_scheduling(pid: u32) -> Option<Scheduling> {
+    read_scheduling_in(kernel_fs::proc_root()?, pid)
+}
+
+/// Reads the scheduling of a process in the proc directory at `proc_root`
+pub fn read_scheduling_in(proc_root: &Path, pid: u32) -> Option<Scheduling> {
+    let stat = std::fs::read_to_string(proc_root.join(pid.to_string()).join("stat")).ok()?;
+    parse_stat(&stat)
+}
+
+/// Parses the priority and nice columns, the 18th and 19th, of a `/proc/<pid>/stat` line. The
+/// command name in the 2nd column is in parentheses and may contain spaces or parentheses itself,
+/// so the columns are counted from the last closing parenthesis.
+pub fn parse_stat(stat: &str) -> Option<Scheduling> {
+    let (_, columns) = stat.rsplit_once(')')?;
+    // The columns after the command name start with the 3rd, the state
+    let mut columns = columns.split_whitespace().skip(18 - 3);
+    Some(Scheduling {
+        priority: columns.next()?.parse().ok()?,
+        nice: columns.next()?.parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    const STAT: &str = "1234 (my (weird) cmd) S 1 1234 1234 0 -1 4194560 500 0 0 0 \
+                        12 34 0 0 25 5 1 0 100 12345678 300 18446744073709551615";
+
+    #[test]
+    fn counts_the_columns_after_the_command_name() {
+        assert_eq!(
+            parse_stat(STAT),
+            Some(Scheduling {
+                priority: 25,
+                nice: 5
+            })
+        );
+    }
+
+    #[test]
+    fn parses_real_time_priorities() {
+        let stat = "7 (migration/0) S 2 0 0 0 -1 69238848 0 0 0 0 0 0 0 0 -100 0 1 0 3";
+        assert_eq!(
+            parse_stat(stat),
+            Some(Scheduling {
+                priority: -100,
+                nice: 0
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_stat_is_none() {
+        assert_eq!(parse_stat("1234 (cmd) S 1 1234"), None);
+        assert_eq!(parse_stat("garbage"), None);
+    }
+
+    #[test]
+    fn reads_the_stat_of_a_process() {
+        let root = Fixture::new("scheduling");
+        root.write("1234/stat", STAT);
+        assert_eq!(read_scheduling_in(&root, 1234).map(|s| s.nice), Some(5));
+        assert_eq!(read_scheduling_in(&root, 4321), None);
+    }
+}
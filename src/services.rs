@@ -0,0 +1,175 @@
+// Enumerates the Windows services through the service control manager. Other platforms have no
+// service control manager, so `list_services` always returns `None`.
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::channels::ServiceStats;
+
+#[cfg(windows)]
+pub fn list_services() -> Option<Vec<ServiceStats>> {
+    use std::ptr::null;
+    use windows_sys::Win32::Foundation::{ERROR_MORE_DATA, GetLastError};
+    use windows_sys::Win32::System::Services::{
+        CloseServiceHandle, ENUM_SERVICE_STATUS_PROCESSW, EnumServicesStatusExW, OpenSCManagerW,
+        SC_ENUM_PROCESS_INFO, SC_MANAGER_CONNECT, SC_MANAGER_ENUMERATE_SERVICE, SERVICE_STATE_ALL,
+        SERVICE_WIN32,
+    };
+
+    // SAFETY: null machine and database names open the local active services database
+    let manager = unsafe {
+        OpenSCManagerW(
+            null(),
+            null(),
+            SC_MANAGER_CONNECT | SC_MANAGER_ENUMERATE_SERVICE,
+        )
+    };
+    if manager.is_null() {
+        return None;
+    }
+
+    let mut services = Vec::new();
+    // u64 elements keep the buffer aligned for the service entries written into it
+    let mut buffer: Vec<u64> = Vec::new();
+    let mut resume_handle = 0;
+    loop {
+        let mut bytes_needed = 0;
+        let mut services_returned = 0;
+        // SAFETY: the buffer size passed matches the allocation, and the out pointers are valid
+        let succeeded = unsafe {
+            EnumServicesStatusExW(
+                manager,
+                SC_ENUM_PROCESS_INFO,
+                SERVICE_WIN32,
+                SERVICE_STATE_ALL,
+                buffer.as_mut_ptr().cast(),
+                (buffer.len() * size_of::<u64>()) as u32,
+                &mut bytes_needed,
+                &mut services_returned,
+                &mut resume_handle,
+                null(),
+            ) != 0
+        };
+        if !succeeded && unsafe { GetLastError() } != ERROR_MORE_DATA {
+            break;
+        }
+
+        // SAFETY: on success or partial success the buffer starts with `services_returned` entries
+        let entries = unsafe {
+            std::slice::from_raw_parts(
+                buffer.as_ptr().cast::<ENUM_SERVICE_STATUS_PROCESSW>(),
+                services_returned as usize,
+            )
+        };
+        for entry in entries {
+            services.push(ServiceStats {
+                name: from_wide(entry.lpServiceName),
+                display_name: from_wide(entry.lpDisplayName),
+                status: status_name(entry.ServiceStatusProcess.dwCurrentState).to_string(),
+                start_type: query_start_type(manager, entry.lpServiceName)
+                    .map_or("Unknown", start_type_name)
+                    .to_string(),
+            });
+        }
+
+        if succeeded || bytes_needed == 0 {
+            break;
+        }
+        buffer.resize((bytes_needed as usize).div_ceil(size_of::<u64>()), 0);
+    }
+
+    // SAFETY: the handle was opened above and is not used afterwards
+    unsafe { CloseServiceHandle(manager) };
+    Some(services)
+}
+
+#[cfg(not(windows))]
+pub fn list_services() -> Option<Vec<ServiceStats>> {
+    None
+}
+
+#[cfg(windows)]
+fn query_start_type(
+    manager: windows_sys::Win32::System::Services::SC_HANDLE,
+    name: windows_sys::core::PWSTR,
+) -> Option<u32> {
+    use std::ptr::null_mut;
+    use windows_sys::Win32::System::Services::{
+        CloseServiceHandle, OpenServiceW, QUERY_SERVICE_CONFIGW, QueryServiceConfigW,
+        SERVICE_QUERY_CONFIG,
+    };
+
+    // SAFETY: the manager handle is open and the name is a null-terminated service name
+    let service = unsafe { OpenServiceW(manager, name, SERVICE_QUERY_CONFIG) };
+    if service.is_null() {
+        return None;
+    }
+
+    let mut bytes_needed = 0;
+    // SAFETY: a null buffer of size zero only queries the required size
+    unsafe { QueryServiceConfigW(service, null_mut(), 0, &mut bytes_needed) };
+    let mut buffer: Vec<u64> = vec![0; (bytes_needed as usize).div_ceil(size_of::<u64>())];
+    let config = buffer.as_mut_ptr().cast::<QUERY_SERVICE_CONFIGW>();
+    // SAFETY: the buffer is aligned and at least `bytes_needed` long
+    let succeeded = unsafe {
+        bytes_needed > 0
+            && QueryServiceConfigW(service, config, bytes_needed, &mut bytes_needed) != 0
+    };
+    // SAFETY: the config is only read after a successful query
+    let start_type = succeeded.then(|| unsafe { (*config).dwStartType });
+
+    // SAFETY: the handle was opened above and is not used afterwards
+    unsafe { CloseServiceHandle(service) };
+    start_type
+}
+
+#[cfg(windows)]
+fn from_wide(ptr: windows_sys::core::PWSTR) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    // SAFETY: the service control manager returns null-terminated UTF-16 strings
+    unsafe {
+        let len = (0..).take_while(|&i| *ptr.add(i) != 0).count();
+        String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// Maps a `SERVICE_STATUS_CURRENT_STATE` value to a readable name
+pub fn status_name(state: u32) -> &'static str {
+    match state {
+        1 => "Stopped",
+        2 => "StartPending",
+        3 => "StopPending",
+        4 => "Running",
+        5 => "ContinuePending",
+        6 => "PausePending",
+        7 => "Paused",
+        _ => "Unknown",
+    }
+}
+
+/// Maps a `SERVICE_START_TYPE` value to a readable name
+pub fn start_type_name(start_type: u32) -> &'static str {
+    match start_type {
+        0 => "Boot",
+        1 => "System",
+        2 => "Auto",
+        3 => "Manual",
+        4 => "Disabled",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_service_states() {
+        assert_eq!(status_name(4), "Running");
+        assert_eq!(status_name(1), "Stopped");
+        assert_eq!(status_name(0), "Unknown");
+        assert_eq!(start_type_name(2), "Auto");
+        assert_eq!(start_type_name(4), "Disabled");
+        assert_eq!(start_type_name(9), "Unknown");
+    }
+}
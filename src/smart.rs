@@ -0,0 +1,128 @@
+// Reads the SMART health of the disks through `smartctl` from smartmontools, using its JSON
+// output. Opening the devices usually requires root, so a permission failure is reported
+// separately to let the caller disable the metric. Hosts without `smartctl` have nothing to
+// report, so `read_smart` returns `Ok(None)` there.
+
+use std::fmt;
+use std::process::Command;
+
+use serde_json::Value;
+
+use crate::channels::SmartDeviceStats;
+
+#[derive(Debug)]
+pub enum SmartError {
+    PermissionDenied,
+    Failed(String),
+}
+
+impl fmt::Display for SmartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmartError::PermissionDenied => write!(f, "smartctl must be run as root"),
+            SmartError::Failed(reason) => write!(f, "smartctl failed: {reason}"),
+        }
+    }
+}
+
+pub fn read_smart() -> Result<Option<Vec<SmartDeviceStats>>, SmartError> {
+    let Some(scan) = smartctl(&["--scan", "-j"])? else {
+        return Ok(None);
+    };
+    let devices = scan["devices"].as_array().cloned().unwrap_or_default();
+    let mut stats = Vec::with_capacity(devices.len());
+    for device in devices {
+        let (Some(name), Some(kind)) = (device["name"].as_str(), device["type"].as_str()) else {
+            continue;
+        };
+        let Some(output) = smartctl(&["-j", "-H", "-A", "-d", kind, name])? else {
+            return Ok(None);
+        };
+        if is_permission_denied(&output) {
+            return Err(SmartError::PermissionDenied);
+        }
+        stats.push(parse_smartctl(name, &output));
+    }
+    Ok(Some(stats))
+}
+
+/// Runs smartctl and parses its JSON output, `None` when smartctl is not installed. The exit
+/// status is a bit mask that is also set for failing disks, so it is not checked.
+fn smartctl(args: &[&str]) -> Result<Option<Value>, SmartError> {
+    let output = match Command::new("smartctl").args(args).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(SmartError::Failed(e.to_string())),
+    };
+    serde_json::from_slice(&output.stdout)
+        .map(Some)
+        .map_err(|e| SmartError::Failed(format!("invalid JSON output: {e}")))
+}
+
+fn is_permission_denied(output: &Value) -> bool {
+    output["smartctl"]["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|message| message["string"].as_str())
+        .any(|message| message.contains("Permission denied"))
+}
+
+/// Parses the output of `smartctl -j -H -A`. The health is `Unknown` when the device doesn't
+/// report it, e.g. behind some USB bridges.
+pub fn parse_smartctl(device: &str, output: &Value) -> SmartDeviceStats {
+    SmartDeviceStats {
+        device: device.to_string(),
+        health: match output["smart_status"]["passed"].as_bool() {
+            Some(true) => "PASSED",
+            Some(false) => "FAILED",
+            None => "Unknown",
+        }
+        .to_string(),
+        temperature: output["temperature"]["current"]
+            .as_f64()
+            .map(|celsius| celsius as f32),
+        power_on_hours: output["power_on_time"]["hours"].as_u64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_the_health_and_attributes() {
+        let output = json!({
+            "smart_status": {"passed": true},
+            "temperature": {"current": 38},
+            "power_on_time": {"hours": 12000},
+        });
+        let stats = parse_smartctl("/dev/sda", &output);
+        assert_eq!(stats.device, "/dev/sda");
+        assert_eq!(stats.health, "PASSED");
+        assert_eq!(stats.temperature, Some(38.0));
+        assert_eq!(stats.power_on_hours, Some(12000));
+
+        let stats = parse_smartctl("/dev/sdb", &json!({"smart_status": {"passed": false}}));
+        assert_eq!(stats.health, "FAILED");
+        assert_eq!(stats.temperature, None);
+    }
+
+    #[test]
+    fn health_is_unknown_when_not_reported() {
+        assert_eq!(parse_smartctl("/dev/sdc", &json!({})).health, "Unknown");
+    }
+
+    #[test]
+    fn detects_permission_failures() {
+        let output = json!({"smartctl": {"messages": [
+            {"string": "Smartctl open device: /dev/sda failed: Permission denied", "severity": "error"},
+        ]}});
+        assert!(is_permission_denied(&output));
+        assert!(!is_permission_denied(
+            &json!({"smartctl": {"messages": []}})
+        ));
+    }
+}
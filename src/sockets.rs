@@ -0,0 +1,49 @@
+// Counts the open sockets of a process from its file descriptors in `/proc/<pid>/fd`, where
+// socket descriptors link to `socket:[<inode>]`. Listing the descriptors of another user's process
+// needs the same privileges as ptrace, so `open_sockets` is `null` for the processes the monitor
+// isn't allowed to inspect.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+
+pub fn count_sockets(pid: u32) -> Option<u32> {
+    count_sockets_in(kernel_fs::proc_root()?, pid)
+}
+
+/// Counts the sockets of a process in the proc directory at `proc_root`. Returns `None` when the
+/// descriptors can't be listed.
+pub fn count_sockets_in(proc_root: &Path, pid: u32) -> Option<u32> {
+    let entries = std::fs::read_dir(proc_root.join(pid.to_string()).join("fd")).ok()?;
+    let count = entries
+        .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+        .filter(|target| target.to_string_lossy().starts_with("socket:"))
+        .count();
+    u32::try_from(count).ok()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn counts_the_socket_descriptors() {
+        let root = Fixture::new("sockets");
+        let fd = root.join("42/fd");
+        std::fs::create_dir_all(&fd).unwrap();
+        symlink("/dev/null", fd.join("0")).unwrap();
+        symlink("socket:[1234]", fd.join("3")).unwrap();
+        symlink("pipe:[5678]", fd.join("4")).unwrap();
+        symlink("socket:[1235]", fd.join("5")).unwrap();
+        assert_eq!(count_sockets_in(&root, 42), Some(2));
+    }
+
+    #[test]
+    fn unlisted_descriptors_are_none() {
+        let root = Fixture::new("sockets-missing");
+        assert_eq!(count_sockets_in(&root, 42), None);
+    }
+}
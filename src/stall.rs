@@ -0,0 +1,173 @@
+// Exits the process when the main loop stops completing iterations, e.g. because a metric
+// source hangs in a syscall. The loop records the time of each completed iteration in an atomic
+// that a separate thread checks, so the check keeps running while the loop is stuck.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::output::Outputs;
+
+/// Exit code of the process when the loop stalled
+pub const STALL_EXIT_CODE: i32 = 3;
+/// How long the watchdog waits for the stuck iteration to release the outputs before exiting
+const CLOSE_WAIT: Duration = Duration::from_secs(5);
+
+/// Shared between the main loop, which marks completed iterations, and the watchdog thread
+#[derive(Clone)]
+pub struct Heartbeat {
+    start: Instant,
+    /// Milliseconds since `start` at which the last iteration completed
+    last_beat_ms: Arc<AtomicU64>,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            last_beat_ms: Arc::default(),
+        }
+    }
+
+    /// Records that an iteration completed
+    pub fn beat(&self) {
+        self.last_beat_ms
+            .store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Time since the last completed iteration, or since startup before the first one
+    pub fn since_last_beat(&self) -> Duration {
+        let last_beat = Duration::from_millis(self.last_beat_ms.load(Ordering::Relaxed));
+        self.start.elapsed().saturating_sub(last_beat)
+    }
+}
+
+/// Spawns a thread that closes the outputs, finalizing the mcap files, and exits the process once
+/// no iteration completed within `timeout`
+pub fn spawn_watchdog(heartbeat: Heartbeat, timeout: Duration, outputs: Outputs) {
+    std::thread::spawn(move || {
+        wait_for_stall(&heartbeat, timeout);
+        log::error!(
+            "No iteration completed in the last {}s, exiting",
+            timeout.as_secs()
+        );
+        close_outputs(&outputs, CLOSE_WAIT);
+        std::process::exit(STALL_EXIT_CODE);
+    });
+}
+
+/// Closes the outputs before exiting on a stall, unless the stuck iteration holds them, e.g.
+/// because it hangs in an output's write. They are then left as they are, since waiting for them
+/// would stall the watchdog too. Returns whether the outputs were closed.
+fn close_outputs(outputs: &Outputs, wait: Duration) -> bool {
+    match outputs.try_close(wait) {
+        Some(Ok(_)) => true,
+        Some(Err(e)) => {
+            log::error!("Failed to close the outputs: {e}");
+            true
+        }
+        None => {
+            log::error!(
+                "The outputs are still in use after {}s, exiting without closing them",
+                wait.as_secs()
+            );
+            false
+        }
+    }
+}
+
+/// Blocks until no iteration has completed within `timeout`
+pub fn wait_for_stall(heartbeat: &Heartbeat, timeout: Duration) {
+    let poll_interval = (timeout / 10).clamp(Duration::from_millis(10), Duration::from_secs(1));
+    while heartbeat.since_last_beat() < timeout {
+        std::thread::sleep(poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+    use crate::output::{Message, OutputSink};
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn beat_resets_the_time_since_the_last_beat() {
+        let heartbeat = Heartbeat::new();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(heartbeat.since_last_beat() >= Duration::from_millis(20));
+        heartbeat.beat();
+        assert!(heartbeat.since_last_beat() < Duration::from_millis(20));
+    }
+
+    #[test]
+    fn stall_is_detected_once_the_beats_stop() {
+        let heartbeat = Heartbeat::new();
+        let beating = std::thread::spawn({
+            let heartbeat = heartbeat.clone();
+            move || {
+                for _ in 0..10 {
+                    heartbeat.beat();
+                    std::thread::sleep(TIMEOUT / 10);
+                }
+            }
+        });
+        let start = Instant::now();
+        wait_for_stall(&heartbeat, TIMEOUT);
+        assert!(start.elapsed() >= TIMEOUT);
+        beating.join().unwrap();
+    }
+
+    /// An output whose writes never return, after signaling that one started
+    struct StuckSink(mpsc::Sender<()>);
+
+    impl OutputSink for StuckSink {
+        fn write(&mut self, _message: &Message) {
+            let _ = self.0.send(());
+            loop {
+                std::thread::park();
+            }
+        }
+    }
+
+    #[test]
+    fn outputs_held_by_a_stuck_write_are_left_open() {
+        let outputs = Outputs::new();
+        let (started_tx, started_rx) = mpsc::channel();
+        outputs.add(Box::new(StuckSink(started_tx)));
+        std::thread::spawn({
+            let outputs = outputs.clone();
+            move || {
+                let channel = ChannelBuilder::new("/stall_stuck_write")
+                    .message_encoding("json")
+                    .build()
+                    .unwrap();
+                outputs.write(&Message::new(&channel, b"{}", 0));
+            }
+        });
+        started_rx.recv().unwrap();
+
+        let start = Instant::now();
+        assert!(!close_outputs(&outputs, Duration::from_millis(50)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn idle_outputs_are_closed() {
+        let outputs = Outputs::new();
+        let (started_tx, _started_rx) = mpsc::channel();
+        outputs.add(Box::new(StuckSink(started_tx)));
+        assert!(close_outputs(&outputs, Duration::from_millis(50)));
+        assert!(
+            outputs
+                .try_close(Duration::ZERO)
+                .unwrap()
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
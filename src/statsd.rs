@@ -0,0 +1,254 @@
+// Sends the numeric fields of the logged messages as StatsD gauges over UDP. Each field becomes a
+// gauge named after its prefix, topic and path, e.g. `fox.cpu.usage:42|g`. The items of a list are
+// told apart by their index, e.g. `fox.cpu.cores.0.usage`. With DogStatsD tags, the host name and
+// the run labels are appended to every gauge, e.g. `|#host:web1,env:prod`.
+//
+// The gauges of an iteration are packed into as few packets as fit the usual MTU and sent at the
+// end of it. UDP is fire-and-forget: a failed send is only counted, and the count is logged on
+// shutdown.
+
+use std::collections::BTreeMap;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::output::{Message, OutputSink};
+
+/// Largest packet payload that fits an Ethernet MTU without fragmentation
+const MAX_PACKET_BYTES: usize = 1432;
+
+/// Checks that a `host:port` address resolves, so a typo is reported before the run starts
+pub fn parse_address(address: &str) -> Result<String, String> {
+    match address.to_socket_addrs() {
+        Ok(mut addresses) => match addresses.next() {
+            Some(_) => Ok(address.to_string()),
+            None => Err(format!("`{address}` doesn't resolve to any address")),
+        },
+        Err(e) => Err(format!("invalid address `{address}`: {e}")),
+    }
+}
+
+pub struct StatsdSender {
+    socket: UdpSocket,
+    prefix: String,
+    /// DogStatsD tags appended to every gauge, e.g. `|#host:web1`, empty without tags
+    tags: String,
+    /// Gauge lines waiting for the next send
+    lines: Vec<String>,
+    failed_sends: u64,
+}
+
+impl StatsdSender {
+    /// Sends to the StatsD server at `address`, a `host:port` pair
+    pub fn new(
+        address: &str,
+        prefix: String,
+        tags: Option<BTreeMap<String, String>>,
+    ) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(if address.starts_with('[') {
+            "[::]:0"
+        } else {
+            "0.0.0.0:0"
+        })?;
+        socket.connect(address)?;
+        let tags = match tags {
+            Some(labels) => {
+                let host = sysinfo::System::host_name().unwrap_or_else(|| "<unknown>".to_owned());
+                let tags: Vec<String> = [("host".to_string(), host)]
+                    .into_iter()
+                    .chain(labels)
+                    .map(|(key, value)| format!("{}:{}", sanitize(&key), sanitize(&value)))
+                    .collect();
+                format!("|#{}", tags.join(","))
+            }
+            None => String::new(),
+        };
+        Ok(Self {
+            socket,
+            prefix,
+            tags,
+            lines: Vec::new(),
+            failed_sends: 0,
+        })
+    }
+
+    /// Sends the gauges recorded since the previous send
+    fn send(&mut self) {
+        let mut packet = String::with_capacity(MAX_PACKET_BYTES);
+        for line in std::mem::take(&mut self.lines) {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_BYTES {
+                self.send_packet(&packet);
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            self.send_packet(&packet);
+        }
+    }
+
+    fn send_packet(&mut self, packet: &str) {
+        if self.socket.send(packet.as_bytes()).is_err() {
+            self.failed_sends += 1;
+        }
+    }
+}
+
+impl OutputSink for StatsdSender {
+    /// Turns the numeric fields of a message into gauge lines
+    fn write(&mut self, message: &Message) {
+        let name = format!(
+            "{}.{}",
+            self.prefix,
+            message.topic().trim_start_matches('/').replace('/', ".")
+        );
+        let mut gauges = Vec::new();
+        if let Some(Value::Object(fields)) = message.json() {
+            // The sequence number identifies the sample, it is not a measurement
+            for (key, field) in fields.iter().filter(|(key, _)| *key != "seq") {
+                flatten(field, &format!("{name}.{}", sanitize(key)), &mut gauges);
+            }
+        }
+        for (name, value) in gauges {
+            // A signed value changes a StatsD gauge instead of setting it, so a negative value
+            // is set by resetting the gauge to zero first
+            if value < 0.0 {
+                self.lines.push(format!("{name}:0|g{}", self.tags));
+            }
+            self.lines.push(format!("{name}:{value}|g{}", self.tags));
+        }
+    }
+
+    fn flush(&mut self) {
+        self.send();
+    }
+
+    /// Reports the packets that couldn't be sent
+    fn close(self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        if self.failed_sends > 0 {
+            log::warn!("{} StatsD packets couldn't be sent", self.failed_sends);
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// Replaces the characters that delimit the parts of a StatsD line
+fn sanitize(value: &str) -> String {
+    value.replace([':', '|', '@', '#', ',', ' ', '\n'], "_")
+}
+
+/// Collects the numeric and boolean leaves of a message as (gauge name, value)
+fn flatten(value: &Value, name: &str, gauges: &mut Vec<(String, f64)>) {
+    match value {
+        Value::Number(number) => {
+            if let Some(number) = number.as_f64() {
+                gauges.push((name.to_string(), number));
+            }
+        }
+        Value::Bool(flag) => gauges.push((name.to_string(), f64::from(u8::from(*flag)))),
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                flatten(field, &format!("{name}.{}", sanitize(key)), gauges);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten(item, &format!("{name}.{index}"), gauges);
+            }
+        }
+        Value::String(_) | Value::Null => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+
+    /// Socket the sender under test sends its packets to
+    fn server() -> (UdpSocket, SocketAddr) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let address = socket.local_addr().unwrap();
+        (socket, address)
+    }
+
+    fn receive(socket: &UdpSocket) -> String {
+        let mut packet = [0; 2048];
+        let len = socket.recv(&mut packet).unwrap();
+        String::from_utf8(packet[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn formats_the_numeric_fields_as_gauges() {
+        let (server, address) = server();
+        let mut sender = StatsdSender::new(&address.to_string(), "fox".to_string(), None).unwrap();
+        let channel = ChannelBuilder::new("/statsd_gauges")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let payload = br#"{"seq":7,"usage":42.5,"offset":-3,"throttled":true,
+            "cores":[{"usage":1,"brand":"i7"}],"name":"web 1"}"#;
+        sender.write(&Message::new(&channel, payload, 0));
+        sender.flush();
+        assert_eq!(
+            receive(&server).lines().collect::<Vec<_>>(),
+            [
+                "fox.statsd_gauges.cores.0.usage:1|g",
+                "fox.statsd_gauges.offset:0|g",
+                "fox.statsd_gauges.offset:-3|g",
+                "fox.statsd_gauges.throttled:1|g",
+                "fox.statsd_gauges.usage:42.5|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn appends_the_tags_to_every_gauge() {
+        let (server, address) = server();
+        let labels = BTreeMap::from([("env".to_string(), "prod:eu".to_string())]);
+        let mut sender =
+            StatsdSender::new(&address.to_string(), "fox".to_string(), Some(labels)).unwrap();
+        let channel = ChannelBuilder::new("/statsd_tags")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        sender.write(&Message::new(&channel, br#"{"usage":1}"#, 0));
+        sender.flush();
+        let packet = receive(&server);
+        assert!(packet.starts_with("fox.statsd_tags.usage:1|g|#host:"));
+        assert!(packet.ends_with(",env:prod_eu"));
+    }
+
+    #[test]
+    fn packs_the_gauges_into_packets_that_fit_the_mtu() {
+        let (server, address) = server();
+        let mut sender = StatsdSender::new(&address.to_string(), "fox".to_string(), None).unwrap();
+        let channel = ChannelBuilder::new("/statsd_packets")
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let values: Vec<u32> = (0..200).collect();
+        let payload = serde_json::to_vec(&serde_json::json!({ "values": values })).unwrap();
+        sender.write(&Message::new(&channel, &payload, 0));
+        sender.flush();
+
+        let mut gauges = 0;
+        while gauges < values.len() {
+            let packet = receive(&server);
+            assert!(packet.len() <= MAX_PACKET_BYTES);
+            gauges += packet.lines().count();
+        }
+        assert_eq!(gauges, values.len());
+    }
+}
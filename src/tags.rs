@@ -0,0 +1,120 @@
+// Run tags describing the deployment, read from a `--tags-file` and inline `--label` entries.
+//
+// The tags file is either a JSON object or `key=value` lines. Blank lines and lines starting
+// with `#` are ignored, and malformed lines are skipped with a warning naming the line number.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Parses a `key=value` label passed on the command line
+pub fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected key=value, got `{label}`")),
+    }
+}
+
+/// Merges the tags file and the inline labels, labels override file entries with the same key
+pub fn resolve(
+    tags_file: Option<&Path>,
+    labels: &[(String, String)],
+) -> Result<BTreeMap<String, String>, String> {
+    let mut tags = match tags_file {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            let (tags, malformed_lines) = parse_tags(&contents)
+                .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+            for line in malformed_lines {
+                log::warn!("Skipping malformed line {line} in {}", path.display());
+            }
+            tags
+        }
+        None => BTreeMap::new(),
+    };
+    tags.extend(labels.iter().cloned());
+    Ok(tags)
+}
+
+/// Parses the contents of a tags file, returning the tags and the 1-based numbers of the
+/// malformed lines. Only an invalid JSON object is an error.
+pub fn parse_tags(contents: &str) -> Result<(BTreeMap<String, String>, Vec<usize>), String> {
+    if contents.trim_start().starts_with('{') {
+        let object: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        let tags = object
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            })
+            .collect();
+        return Ok((tags, Vec::new()));
+    }
+
+    let mut tags = BTreeMap::new();
+    let mut malformed_lines = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_label(line) {
+            Ok((key, value)) => {
+                tags.insert(key, value);
+            }
+            Err(_) => malformed_lines.push(index + 1),
+        }
+    }
+    Ok((tags, malformed_lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn labels_need_a_key() {
+        assert_eq!(
+            parse_label(" site = lab=2 "),
+            Ok(("site".to_string(), "lab=2".to_string()))
+        );
+        assert!(parse_label("site").is_err());
+        assert!(parse_label(" =lab").is_err());
+    }
+
+    #[test]
+    fn parses_json_tags() {
+        let (tags, malformed_lines) =
+            parse_tags(r#"{"site": "lab", "rack": 4, "spare": true}"#).unwrap();
+        assert_eq!(tags["site"], "lab");
+        assert_eq!(tags["rack"], "4");
+        assert_eq!(tags["spare"], "true");
+        assert!(malformed_lines.is_empty());
+        assert!(parse_tags("{\"site\": ").is_err());
+    }
+
+    #[test]
+    fn skips_comments_and_malformed_lines() {
+        let (tags, malformed_lines) =
+            parse_tags("# deployment\nsite=lab\n\nrack\nrobot = r2\n=orphan\n").unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags["site"], "lab");
+        assert_eq!(tags["robot"], "r2");
+        assert_eq!(malformed_lines, [4, 6]);
+    }
+
+    #[test]
+    fn labels_override_the_tags_file() {
+        let root = Fixture::new("tags");
+        root.write("tags", "site=lab\nrobot=r1\n");
+        let labels = [("robot".to_string(), "r2".to_string())];
+        let tags = resolve(Some(&root.join("tags")), &labels).unwrap();
+        assert_eq!(tags["site"], "lab");
+        assert_eq!(tags["robot"], "r2");
+        assert!(resolve(Some(&root.join("missing")), &labels).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+// Unit of the logged temperatures. sysinfo and smartctl report Celsius, the conversion happens
+// when the message is built so the alerts and throttling checks keep working in Celsius. The unit
+// is selected once at startup, before the temperature channels are registered, so their `.unit`
+// metadata names it.
+
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[value(name = "c")]
+    Celsius,
+    #[value(name = "f")]
+    Fahrenheit,
+}
+
+/// Unit selected with --temp-unit
+static SELECTED: OnceLock<TemperatureUnit> = OnceLock::new();
+
+impl TemperatureUnit {
+    /// Selects the unit of the logged temperatures, only the first selection is kept
+    pub fn select(self) {
+        let _ = SELECTED.set(self);
+    }
+
+    /// Unit selected at startup, Celsius when none was
+    pub fn selected() -> Self {
+        SELECTED.get().copied().unwrap_or(TemperatureUnit::Celsius)
+    }
+
+    /// Symbol of the unit in the channel metadata, e.g. `°C`
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Converts a temperature in Celsius to this unit
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_from_celsius() {
+        assert_eq!(TemperatureUnit::Celsius.convert(36.6), 36.6);
+        assert_eq!(TemperatureUnit::Fahrenheit.convert(100.0), 212.0);
+        assert_eq!(TemperatureUnit::Fahrenheit.convert(-40.0), -40.0);
+    }
+
+    #[test]
+    fn symbols_name_the_unit() {
+        assert_eq!(TemperatureUnit::Celsius.symbol(), "°C");
+        assert_eq!(TemperatureUnit::Fahrenheit.symbol(), "°F");
+    }
+}
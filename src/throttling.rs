@@ -0,0 +1,141 @@
+// Detects thermal throttling of the cpu.
+//
+// The kernel counts the throttling events of Intel cpus under
+// `/sys/devices/system/cpu/cpu<N>/thermal_throttle`. Other cpus are flagged by a heuristic instead,
+// a cpu running well below the maximum frequency of its cpufreq driver while hot, which needs the
+// temperatures to be sampled. Where neither is exposed nothing is ever flagged.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+
+/// Fraction of the maximum frequency below which the cpu is considered slowed down
+const FREQUENCY_RATIO_THRESHOLD: f64 = 0.6;
+/// Temperature above which a slowed down cpu is considered throttled
+const TEMPERATURE_THRESHOLD: f32 = 85.0;
+
+/// Directory of the cpus in sysfs
+const CPU_DIRECTORY: &str = "devices/system/cpu";
+
+/// Maximum frequency of the cpu in MHz
+pub fn max_frequency_mhz() -> Option<u64> {
+    read_max_frequency_mhz(kernel_fs::sys_root()?)
+}
+
+/// Number of throttling events reported by the kernel since boot, summed across cores
+pub fn throttle_count() -> Option<u64> {
+    read_throttle_count(kernel_fs::sys_root()?)
+}
+
+/// Reads `cpu0/cpufreq/cpuinfo_max_freq`, reported in kHz, from the sys directory at `sys_root`
+pub fn read_max_frequency_mhz(sys_root: &Path) -> Option<u64> {
+    let path = sys_root
+        .join(CPU_DIRECTORY)
+        .join("cpu0/cpufreq/cpuinfo_max_freq");
+    let contents = std::fs::read_to_string(path).ok()?;
+    let khz: u64 = contents.trim().parse().ok()?;
+    Some(khz / 1000)
+}
+
+/// Sums the `cpu*/thermal_throttle/core_throttle_count` files of the sys directory at `sys_root`,
+/// `None` when no cpu has one
+pub fn read_throttle_count(sys_root: &Path) -> Option<u64> {
+    let mut total = None;
+    for entry in std::fs::read_dir(sys_root.join(CPU_DIRECTORY))
+        .ok()?
+        .flatten()
+    {
+        let name = entry.file_name();
+        let Some(index) = name.to_str().and_then(|n| n.strip_prefix("cpu")) else {
+            continue;
+        };
+        if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let count =
+            std::fs::read_to_string(entry.path().join("thermal_throttle/core_throttle_count"))
+                .ok()
+                .and_then(|contents| contents.trim().parse::<u64>().ok());
+        if let Some(count) = count {
+            *total.get_or_insert(0) += count;
+        }
+    }
+    total
+}
+
+/// Flags throttling only when the cpu runs well below its maximum frequency while hot, so a cpu
+/// idling at a low frequency is not reported
+pub fn is_throttling(
+    frequency_mhz: u64,
+    max_frequency_mhz: Option<u64>,
+    max_temperature: Option<f32>,
+) -> bool {
+    let (Some(max_frequency_mhz), Some(max_temperature)) = (max_frequency_mhz, max_temperature)
+    else {
+        return false;
+    };
+    max_frequency_mhz > 0
+        && (frequency_mhz as f64) < max_frequency_mhz as f64 * FREQUENCY_RATIO_THRESHOLD
+        && max_temperature >= TEMPERATURE_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn reads_the_max_frequency_in_mhz() {
+        let root = Fixture::new("throttling-frequency");
+        root.write(
+            "devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq",
+            "3600000\n",
+        );
+        assert_eq!(read_max_frequency_mhz(&root), Some(3600));
+        assert_eq!(
+            read_max_frequency_mhz(&Fixture::new("throttling-none")),
+            None
+        );
+    }
+
+    #[test]
+    fn sums_the_throttle_counts_of_the_cpus() {
+        let root = Fixture::new("throttling-count");
+        root.write(
+            "devices/system/cpu/cpu0/thermal_throttle/core_throttle_count",
+            "3\n",
+        )
+        .write(
+            "devices/system/cpu/cpu1/thermal_throttle/core_throttle_count",
+            "4\n",
+        )
+        .write(
+            "devices/system/cpu/cpufreq/thermal_throttle/core_throttle_count",
+            "100\n",
+        )
+        .write("devices/system/cpu/online", "0-1\n");
+        assert_eq!(read_throttle_count(&root), Some(7));
+    }
+
+    #[test]
+    fn no_throttle_count_without_the_files() {
+        let root = Fixture::new("throttling-missing");
+        root.write(
+            "devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq",
+            "3600000\n",
+        );
+        assert_eq!(read_throttle_count(&root), None);
+    }
+
+    #[test]
+    fn throttling_needs_a_slow_and_hot_cpu() {
+        assert!(is_throttling(1000, Some(3600), Some(95.0)));
+        // Idling at a low frequency
+        assert!(!is_throttling(1000, Some(3600), Some(45.0)));
+        // Hot but at full speed
+        assert!(!is_throttling(3500, Some(3600), Some(95.0)));
+        assert!(!is_throttling(1000, None, Some(95.0)));
+        assert!(!is_throttling(1000, Some(3600), None));
+        assert!(!is_throttling(0, Some(0), Some(95.0)));
+    }
+}
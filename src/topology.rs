@@ -0,0 +1,82 @@
+// Reads which physical package and core each logical cpu belongs to from
+// `/sys/devices/system/cpu/cpu<N>/topology`, once at startup. Some virtual machines report -1
+// for an id they don't know, and a cpu without a topology directory, e.g. where sysfs isn't
+// available, has neither, so both ids are `None` then.
+
+use std::path::Path;
+
+use crate::kernel_fs;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CoreTopology {
+    pub package_id: Option<u32>,
+    pub core_id: Option<u32>,
+}
+
+/// Topology of the first `cpus` logical cpus, by cpu index
+pub fn read_topology(cpus: usize) -> Vec<CoreTopology> {
+    match kernel_fs::sys_root() {
+        Some(sys_root) => read_topology_in(sys_root, cpus),
+        None => Vec::new(),
+    }
+}
+
+/// Reads the topology of the first `cpus` logical cpus from the sys directory at `sys_root`
+pub fn read_topology_in(sys_root: &Path, cpus: usize) -> Vec<CoreTopology> {
+    let root = sys_root.join("devices/system/cpu");
+    (0..cpus)
+        .map(|cpu| {
+            let topology = root.join(format!("cpu{cpu}")).join("topology");
+            let read = |file: &str| {
+                std::fs::read_to_string(topology.join(file))
+                    .ok()?
+                    .trim()
+                    .parse()
+                    .ok()
+            };
+            CoreTopology {
+                package_id: read("physical_package_id"),
+                core_id: read("core_id"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_fs::Fixture;
+
+    #[test]
+    fn reads_the_ids_of_each_cpu() {
+        let root = Fixture::new("topology");
+        root.write(
+            "devices/system/cpu/cpu0/topology/physical_package_id",
+            "0\n",
+        )
+        .write("devices/system/cpu/cpu0/topology/core_id", "0\n")
+        .write(
+            "devices/system/cpu/cpu1/topology/physical_package_id",
+            "1\n",
+        )
+        .write("devices/system/cpu/cpu1/topology/core_id", "3\n")
+        .write(
+            "devices/system/cpu/cpu2/topology/physical_package_id",
+            "-1\n",
+        );
+        let topology = read_topology_in(&root, 4);
+        let ids: Vec<_> = topology
+            .iter()
+            .map(|cpu| (cpu.package_id, cpu.core_id))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                (Some(0), Some(0)),
+                (Some(1), Some(3)),
+                (None, None),
+                (None, None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+// Reopens a written mcap file to check that it is readable, which catches files truncated by a
+// full disk or an interrupted write.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use mcap::records::Record;
+use mcap::sans_io::read::{LinearReader, ReadAction};
+
+#[derive(Debug)]
+pub enum VerifyError {
+    Io(std::io::Error),
+    Corrupt(mcap::McapError),
+    Empty,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Io(e) => write!(f, "failed to read mcap file: {e}"),
+            VerifyError::Corrupt(e) => write!(f, "mcap file is corrupt: {e}"),
+            VerifyError::Empty => write!(f, "mcap file contains no messages"),
+        }
+    }
+}
+
+/// Reads every message of the mcap file at `path`, returning the message counts by topic. The file
+/// is streamed rather than loaded whole, since a long run can record gigabytes.
+pub fn verify_file(path: &Path) -> Result<BTreeMap<String, u64>, VerifyError> {
+    let file = File::open(path).map_err(VerifyError::Io)?;
+    verify(file)
+}
+
+/// Reads every message of an mcap file from `input`, returning the message counts by topic
+pub fn verify(mut input: impl Read) -> Result<BTreeMap<String, u64>, VerifyError> {
+    let mut topics = HashMap::new();
+    let mut counts = BTreeMap::new();
+    let mut reader = LinearReader::new();
+    while let Some(action) = reader.next_action() {
+        match action.map_err(VerifyError::Corrupt)? {
+            ReadAction::NeedMore(needed) => {
+                let read = input.read(reader.insert(needed)).map_err(VerifyError::Io)?;
+                reader.set_written(read);
+            }
+            ReadAction::GetRecord { opcode, data } => {
+                match mcap::parse_record(opcode, data).map_err(VerifyError::Corrupt)? {
+                    Record::Channel(channel) => {
+                        topics.insert(channel.id, channel.topic);
+                    }
+                    Record::Message { header, .. } => {
+                        let topic = topics.get(&header.channel_id).ok_or(VerifyError::Corrupt(
+                            mcap::McapError::UnknownChannel(header.sequence, header.channel_id),
+                        ))?;
+                        *counts.entry(topic.clone()).or_default() += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if counts.is_empty() {
+        return Err(VerifyError::Empty);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use foxglove::ChannelBuilder;
+
+    use super::*;
+    use crate::kernel_fs::Fixture;
+    use crate::output::{Message, OutputSink};
+    use crate::recording::McapOutput;
+
+    /// Records `messages` messages on `topic` to an mcap file, returning its contents
+    fn record(root: &Fixture, topic: &str, messages: u64) -> Vec<u8> {
+        let channel = ChannelBuilder::new(topic)
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let path = root.join("run.mcap");
+        let mut output = Box::new(McapOutput::single(&path, false).unwrap());
+        for log_time in 0..messages {
+            output.write(&Message::new(&channel, br#"{"seq":0}"#, log_time));
+        }
+        output.close().unwrap();
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn counts_the_messages_of_each_topic() {
+        let root = Fixture::new("verify-counts");
+        record(&root, "/verify_counts", 3);
+        let counts = verify_file(&root.join("run.mcap")).unwrap();
+        assert_eq!(counts, BTreeMap::from([("/verify_counts".to_string(), 3)]));
+    }
+
+    #[test]
+    fn truncated_file_is_corrupt() {
+        let root = Fixture::new("verify-truncated");
+        let contents = record(&root, "/verify_truncated", 3);
+        let truncated = &contents[..contents.len() / 2];
+        assert!(matches!(verify(truncated), Err(VerifyError::Corrupt(_))));
+    }
+
+    #[test]
+    fn file_without_messages_is_empty() {
+        let root = Fixture::new("verify-empty");
+        let contents = record(&root, "/verify_empty", 0);
+        assert!(matches!(verify(&contents[..]), Err(VerifyError::Empty)));
+        assert!(matches!(
+            verify_file(&root.join("missing.mcap")),
+            Err(VerifyError::Io(_))
+        ));
+    }
+}
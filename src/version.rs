@@ -0,0 +1,39 @@
+// Machine-readable build information printed by `--version-json`.
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub rustc_version: &'static str,
+    pub features: Vec<&'static str>,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: env!("FOX_MONITOR_GIT_COMMIT"),
+            rustc_version: env!("FOX_MONITOR_RUSTC_VERSION"),
+            features: env!("FOX_MONITOR_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_the_build_information() {
+        let json = serde_json::to_value(VersionInfo::current()).unwrap();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["git_commit"].is_string());
+        assert!(!json["rustc_version"].as_str().unwrap().is_empty());
+        assert!(json["features"].is_array());
+    }
+}
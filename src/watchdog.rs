@@ -0,0 +1,133 @@
+// Keeps the websocket server alive during long runs. The server handle exposes no health
+// status, so the watchdog probes the listening port and restarts the server when nothing accepts
+// connections anymore, backing off between failed restarts.
+
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use foxglove::{WebSocketServer, WebSocketServerBlockingHandle};
+
+use crate::parameters::{self, RuntimeConfig};
+
+/// Time between two health checks of a running server
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub struct WebSocketWatchdog {
+    handle: Option<WebSocketServerBlockingHandle>,
+    port: u16,
+    /// Parameters served again by a restarted server
+    config: Arc<RuntimeConfig>,
+    /// Delay before the next restart attempt, doubled after each failure
+    backoff: Duration,
+    next_check: Instant,
+}
+
+impl WebSocketWatchdog {
+    /// Watches a started server, restarting it on the same port
+    pub fn new(handle: WebSocketServerBlockingHandle, config: Arc<RuntimeConfig>) -> Self {
+        Self {
+            port: handle.port(),
+            handle: Some(handle),
+            config,
+            backoff: INITIAL_BACKOFF,
+            next_check: Instant::now() + CHECK_INTERVAL,
+        }
+    }
+
+    /// Checks the server if it is due, restarting it when it no longer accepts connections
+    pub fn check(&mut self) {
+        let now = Instant::now();
+        if now < self.next_check {
+            return;
+        }
+        if self.handle.is_some() && self.is_alive() {
+            self.backoff = INITIAL_BACKOFF;
+            self.next_check = now + CHECK_INTERVAL;
+            return;
+        }
+
+        if let Some(handle) = self.handle.take() {
+            log::warn!("Websocket server on port {} stopped responding", self.port);
+            handle.stop();
+        }
+        match parameters::serve(WebSocketServer::new(), &self.config)
+            .bind(Ipv4Addr::LOCALHOST.to_string(), self.port)
+            .start_blocking()
+        {
+            Ok(handle) => {
+                log::info!("Restarted websocket server on port {}", self.port);
+                self.handle = Some(handle);
+                self.backoff = INITIAL_BACKOFF;
+                self.next_check = now + CHECK_INTERVAL;
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to restart websocket server, retrying in {:?}: {e}",
+                    self.backoff
+                );
+                self.next_check = now + self.backoff;
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+
+    /// Stops the server, if it is running
+    pub fn stop(self) {
+        if let Some(handle) = self.handle {
+            handle.stop();
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, self.port));
+        TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarts_a_stopped_server_on_the_same_port() {
+        let handle = WebSocketServer::new()
+            .bind(Ipv4Addr::LOCALHOST.to_string(), 0)
+            .start_blocking()
+            .unwrap();
+        let mut watchdog = WebSocketWatchdog::new(handle, Arc::default());
+        let port = watchdog.port;
+        assert!(watchdog.is_alive());
+
+        watchdog.handle.take().unwrap().stop();
+        // The port is released shortly after the stop, until then the restart is retried
+        for _ in 0..100 {
+            watchdog.next_check = Instant::now();
+            watchdog.check();
+            if watchdog.handle.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(watchdog.handle.as_ref().unwrap().port(), port);
+        assert!(watchdog.is_alive());
+        watchdog.stop();
+    }
+
+    #[test]
+    fn healthy_server_is_left_running() {
+        let handle = WebSocketServer::new()
+            .bind(Ipv4Addr::LOCALHOST.to_string(), 0)
+            .start_blocking()
+            .unwrap();
+        let mut watchdog = WebSocketWatchdog::new(handle, Arc::default());
+        watchdog.next_check = Instant::now();
+        watchdog.check();
+        assert!(watchdog.handle.is_some());
+        assert!(watchdog.next_check > Instant::now());
+        watchdog.stop();
+    }
+}
@@ -0,0 +1,184 @@
+// Serves the logged messages to Foxglove clients over a websocket server. foxglove 0.4 queues each
+// message for the subscribed clients, and each client's task writes it with `SinkExt::send`, which
+// flushes the socket after every message. So there is nothing to flush at the end of an iteration:
+// the flush only runs the watchdog that restarts a dead server. The clients can change the runtime
+// parameters.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use foxglove::{FoxgloveError, PartialMetadata, WebSocketServer, WebSocketServerBlockingHandle};
+
+use crate::output::{Message, OutputSink};
+use crate::parameters::{self, RuntimeConfig};
+use crate::watchdog::WebSocketWatchdog;
+
+enum Server {
+    /// Restarted by the watchdog when it stops responding
+    Watched(WebSocketWatchdog),
+    Unwatched(WebSocketServerBlockingHandle),
+}
+
+pub struct WebsocketOutput {
+    server: Server,
+}
+
+impl WebsocketOutput {
+    /// Starts the server, serving the channels of the default context, and restarts it when it
+    /// stops responding if `restart` is set
+    pub fn start(config: &Arc<RuntimeConfig>, restart: bool) -> Result<Self, FoxgloveError> {
+        let handle = parameters::serve(WebSocketServer::new(), config).start_blocking()?;
+        Ok(Self {
+            server: if restart {
+                Server::Watched(WebSocketWatchdog::new(handle, config.clone()))
+            } else {
+                Server::Unwatched(handle)
+            },
+        })
+    }
+}
+
+impl OutputSink for WebsocketOutput {
+    fn write(&mut self, message: &Message) {
+        message.channel.log_with_meta(
+            message.payload,
+            PartialMetadata {
+                sequence: Some(message.sequence),
+                log_time: Some(message.log_time),
+                publish_time: Some(message.log_time),
+            },
+        );
+    }
+
+    fn flush(&mut self) {
+        if let Server::Watched(watchdog) = &mut self.server {
+            watchdog.check();
+        }
+    }
+
+    /// Stops the server, disconnecting the clients
+    fn close(self: Box<Self>) -> std::io::Result<Vec<PathBuf>> {
+        match self.server {
+            Server::Watched(watchdog) => watchdog.stop(),
+            Server::Unwatched(handle) => handle.stop(),
+        }
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+
+    use foxglove::{ChannelBuilder, WebSocketServer};
+
+    use super::*;
+    use crate::output::FLUSH_INTERVAL;
+
+    const TOPIC: &str = "/websocket_test";
+
+    /// Reads a frame sent by the server, returning its opcode and payload
+    fn read_frame(stream: &mut TcpStream) -> std::io::Result<(u8, Vec<u8>)> {
+        let mut head = [0; 2];
+        stream.read_exact(&mut head)?;
+        let length = match head[1] & 0x7f {
+            126 => {
+                let mut length = [0; 2];
+                stream.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as usize
+            }
+            127 => {
+                let mut length = [0; 8];
+                stream.read_exact(&mut length)?;
+                u64::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        let mut payload = vec![0; length];
+        stream.read_exact(&mut payload)?;
+        Ok((head[0] & 0x0f, payload))
+    }
+
+    /// Sends a masked text frame, as clients must
+    fn send_text(stream: &mut TcpStream, text: &str) {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | 126];
+        frame.extend_from_slice(&(text.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        stream.write_all(&frame).unwrap();
+    }
+
+    /// Connects to the server and subscribes to the test channel
+    fn subscribe(port: u16) -> TcpStream {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Protocol: foxglove.websocket.v1\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            response.push(byte[0]);
+        }
+        loop {
+            let (_, payload) = read_frame(&mut stream).unwrap();
+            let Ok(message) = serde_json::from_slice::<serde_json::Value>(&payload) else {
+                continue;
+            };
+            let channel = message["channels"]
+                .as_array()
+                .and_then(|channels| channels.iter().find(|channel| channel["topic"] == TOPIC));
+            if let Some(channel) = channel {
+                let subscribe = serde_json::json!({
+                    "op": "subscribe",
+                    "subscriptions": [{"id": 1, "channelId": channel["id"]}],
+                });
+                send_text(&mut stream, &subscribe.to_string());
+                return stream;
+            }
+        }
+    }
+
+    #[test]
+    fn client_receives_message_without_flush() {
+        let channel = ChannelBuilder::new(TOPIC)
+            .message_encoding("json")
+            .build()
+            .unwrap();
+        let handle = WebSocketServer::new()
+            .bind("127.0.0.1", 0)
+            .start_blocking()
+            .unwrap();
+        let port = handle.port();
+        let mut output = WebsocketOutput {
+            server: Server::Unwatched(handle),
+        };
+        let mut stream = subscribe(port);
+        stream.set_read_timeout(Some(FLUSH_INTERVAL)).unwrap();
+
+        // The subscription is applied asynchronously, so the first messages may be dropped
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let received = loop {
+            assert!(Instant::now() < deadline, "no message received");
+            output.write(&Message::new(&channel, br#"{"value":1}"#, 42));
+            let written = Instant::now();
+            if let Ok((0x2, payload)) = read_frame(&mut stream) {
+                break (payload, written.elapsed());
+            }
+        };
+        let (payload, latency) = received;
+        assert!(latency < FLUSH_INTERVAL);
+        // Opcode, subscription id and log time come before the message
+        assert_eq!(payload[0], 1);
+        assert_eq!(&payload[5..13], &42u64.to_le_bytes());
+        assert_eq!(&payload[13..], br#"{"value":1}"#);
+        Box::new(output).close().unwrap();
+    }
+}